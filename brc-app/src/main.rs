@@ -1,195 +1,1144 @@
 // #[global_allocator]
 // static ALLOC: snmalloc_rs::SnMalloc = snmalloc_rs::SnMalloc;
 
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::fs::File;
 use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use brc_core::{
-    DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER, StateF, naive_line_by_line,
-    naive_line_by_line_dummy, naive_line_by_line_v2, parse_large_chunks_as_bytes,
-    parse_large_chunks_as_bytes_dummy, parse_large_chunks_as_i64,
-    parse_large_chunks_as_i64_as_java, parse_large_chunks_as_i64_dummy,
-    parse_large_chunks_as_i64_unsafe, parse_large_chunks_as_i64_v2,
-    parse_large_chunks_full_simd_table, parse_large_chunks_memchr_table, parse_large_chunks_simd,
-    parse_large_chunks_simd_dummy, parse_large_chunks_simd_temperature_table,
-    parse_large_chunks_simd_v1, parse_large_chunks_simd_v2, parse_large_chunks_simd_v2_dummy,
-    parse_large_chunks_std_simd_table, sort_result,
+    ChunkIndex, ChunkSample, Collation, ColumnarFile, ColumnarWriter, DEFAULT_BLOCK_RECORDS,
+    DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER, DEFAULT_INDEX_STRIDE, ExtremeState, GlobalExtremes,
+    Granularity, InputFormat, Kernel, MultiState, Normalization, Progress, ProgressTracker, Schema,
+    StateI, StationDictionary, StationFilter, StationMetadata, TableParser, ThresholdState,
+    Thresholds, TimeSeriesState, TrackedReader, UnknownStations, Z_95, choose_implementation,
+    expand_inputs, find_implementation, format_output, implementations, is_columnar,
+    parse_large_chunks_dictionary, parse_large_chunks_extremes, parse_large_chunks_filtered,
+    parse_large_chunks_metrics, parse_large_chunks_thresholds, parse_large_chunks_time_series,
+    parse_threshold, profile_input, read_station_list,
 };
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{RngExt, SeedableRng};
 
 /// The capacity of BufReader to improve reading
 const BUF_READER_CAPACITY: usize = 64 * 1024 * 1024;
 
 const DEFAULT_IMPL: &str = "naive_line_by_line";
 
+/// Implementation used when a station list is given with `--stations`.
+const DICTIONARY_IMPL: &str = "parse_large_chunks_dictionary";
+
+/// Implementation used when station filters are given.
+const FILTERED_IMPL: &str = "parse_large_chunks_filtered";
+
+/// Implementation used when temperature thresholds are given.
+const THRESHOLDS_IMPL: &str = "parse_large_chunks_thresholds";
+
+/// Implementation used when top-k extremes are requested with `--top`.
+const EXTREMES_IMPL: &str = "parse_large_chunks_extremes";
+
+/// Implementation used for timestamped records given `--timestamps`.
+const TIME_SERIES_IMPL: &str = "parse_large_chunks_time_series";
+
+/// Implementation used for records with several values given `--metrics`.
+const METRICS_IMPL: &str = "parse_large_chunks_metrics";
+
+/// Implementation used for files written by `brc-app transcode`.
+const COLUMNAR_IMPL: &str = "columnar_blocks";
+
+/// Pseudo-implementation that samples the input and picks the implementation
+/// and table size for it.
+const ADAPTIVE_IMPL: &str = "adaptive";
+
+/// Length of the chunks drawn by `--sample` and `--time-budget`.
+const SAMPLE_CHUNK_LEN: usize = 256 * 1024;
+
+/// Buffer of the readers of columnar chunks.
+const COLUMNAR_BUFFER_LEN: usize = 1024 * 1024;
+
+/// Aggregated stations, unsorted unless stated otherwise.
+type Stations<S = StateI> = Vec<(String, S)>;
+
+/// Parser of one chunk: a reader, the inclusive byte range and whether to sort.
+type ChunkParser<S = StateI> =
+    dyn Fn(BufReader<Input>, u64, u64, bool) -> Stations<S> + Send + Sync;
+
+/// Input files are read through the progress tracker of the run.
+type Input = TrackedReader<File>;
+
+/// Options that take no value.
+const FLAGS: [&str; 2] = ["per-file", "progress"];
+
+/// Usage: `brc-app [OPTIONS] [PATH...] [THREADS] [IMPLEMENTATION]`, or
+/// `brc-app index [--stride=BYTES] PATH...` to write the sidecar chunk index
+/// of every file, see [`index_files`], or
+/// `brc-app transcode [--block-records=N] PATH... OUTPUT` to convert the text
+/// files into one columnar file, see [`transcode_files`].
+///
+/// Every `PATH` is a file, a directory standing for the files in it, or a glob
+/// like `'data/2026-10-17T*.txt'`; the positional arguments up to the first
+/// number are paths. All files are parsed as one input on a single pool of
+/// `THREADS` threads. `IMPLEMENTATION` is one of [`implementations`], or
+/// `adaptive` to pick one from a sample of the first file. Columnar inputs
+/// written by `transcode` are detected and read with `columnar_blocks`.
+///
+/// Options:
+/// - `--stations=<FILE>`: aggregate only the stations listed in `FILE`, one per
+///   line, using a perfect-hash dictionary built at startup.
+/// - `--unknown-stations=<reject|count>`: with `--stations`, abort on records
+///   of unlisted stations (default) or skip and count them.
+/// - `--allow=<FILE>`, `--deny=<FILE>`: aggregate only the stations listed in
+///   `FILE`, or all but them, one per line.
+/// - `--prefix=<PREFIX>`, `--regex=<REGEX>`, `--glob=<GLOB>`: aggregate only
+///   the stations whose name starts with `PREFIX`, contains a match of `REGEX`
+///   or matches `GLOB` as a whole. Filters combine, a station must pass all.
+///   They run with `parse_large_chunks_filtered`, the memchr table parser, or
+///   with any of the [`TableParser`] implementations given as `IMPLEMENTATION`.
+/// - `--normalize=<none|nfc>`: merge stations whose names are equal after NFC
+///   normalization. The [`TableParser`] implementations and the aggregations
+///   built on the fingerprint table normalize a spelling when they first see
+///   it, the other parsers when their results are merged. Default: `none`.
+/// - `--sort=<bytes|codepoint|locale:TAG>`: order of the stations in the
+///   output, e.g. `locale:sv` for Swedish collation. Default: `bytes`.
+/// - `--above=<DEGREES>`, `--below=<DEGREES>`: also count the readings of each
+///   station strictly above or below the threshold and their longest run in
+///   file order, printed as `min/mean/max >30.0:count/run <0.0:count/run`.
+/// - `--metadata=<FILE>`, `--group-by=<COLUMN>`: join the result with the
+///   station metadata CSV `FILE`, with a header row and a `name` column, and
+///   print one aggregate per value of `COLUMN`, e.g. `country`, instead of one
+///   per station. Stations missing from `FILE` are reported on stderr.
+/// - `--top=<K>`: also keep the `K` hottest and coldest readings of each
+///   station and of all stations, each with the byte offset of its record, as
+///   `min/mean/max hottest:T@offset;... coldest:T@offset;...` followed by a
+///   `hottest: name=T@offset, ...` and a `coldest: ...` line.
+/// - `--timestamps=<hour|day|month>`: read `name;YYYY-MM-DDTHH:MM:SSZ;value`
+///   records and also aggregate each station per hour, day or month, printed as
+///   `min/mean/max series:2026-10-17T12=min/mean/max;...`.
+/// - `--metrics=<COLUMN,...>`: read `name;value;...;value` records with one
+///   value per column, e.g. `temperature,humidity`, and aggregate every column,
+///   printed as `temperature:min/mean/max humidity:min/mean/max`.
+/// - `--per-file`: print the result of every file, as `PATH: {...}`, before the
+///   merged one.
+/// - `--sample=<FRACTION>`, `--time-budget=<MS>`: parse a random sample of the
+///   256 KiB chunks of the input, until `FRACTION` of them are parsed or `MS`
+///   milliseconds have passed, and estimate every station from it, printed as
+///   `<=min/mean+-margin/>=max ~count` with a 95% confidence interval of the
+///   mean. `--seed=<N>` makes the sample reproducible.
+/// - `--progress`: draw a progress bar on stderr with the bytes and records
+///   parsed so far and the rate of every thread.
 fn main() {
+    if let Err(e) = try_main() {
+        eprintln!("{e}");
+        std::process::exit(2);
+    }
+}
+
+fn try_main() -> Result<(), String> {
     let instant = Instant::now();
-    let path = std::env::args()
-        .skip(1)
-        .next()
-        .unwrap_or_else(|| "brc-core/test_resources/sample.txt".to_owned());
-    let cores: usize = std::env::args()
-        .skip(2)
-        .next()
-        .map(|c| usize::from_str(c.as_str()).unwrap())
-        .unwrap_or_else(|| thread::available_parallelism().unwrap().into());
-
-    let method: String = std::env::args()
-        .skip(3)
-        .next()
-        .map(|c| c.clone())
-        .unwrap_or_else(|| DEFAULT_IMPL.to_string());
-
-    let func: fn(BufReader<_>, u64, u64, bool) -> Vec<(String, StateF)> = match method.as_str() {
-        "naive_line_by_line_dummy" => naive_line_by_line_dummy,
-        DEFAULT_IMPL => naive_line_by_line,
-        "naive_line_by_line_v2" => naive_line_by_line_v2,
-        "parse_large_chunks_as_bytes_dummy" => parse_large_chunks_as_bytes_dummy,
-        "parse_large_chunks_as_bytes" => parse_large_chunks_as_bytes,
-        "parse_large_chunks_as_i64_dummy" => parse_large_chunks_as_i64_dummy,
-        "parse_large_chunks_as_i64" => parse_large_chunks_as_i64,
-        "parse_large_chunks_memchr_table" => parse_large_chunks_memchr_table,
-        "parse_large_chunks_full_simd_table" => parse_large_chunks_full_simd_table,
-        "parse_large_chunks_simd_temperature_table" => parse_large_chunks_simd_temperature_table,
-        "parse_large_chunks_std_simd_table" => parse_large_chunks_std_simd_table,
-        "parse_large_chunks_as_i64_v2" => parse_large_chunks_as_i64_v2,
-        "parse_large_chunks_as_i64_unsafe" => parse_large_chunks_as_i64_unsafe,
-        "parse_large_chunks_as_i64_as_java" => parse_large_chunks_as_i64_as_java,
-        "parse_large_chunks_simd_dummy" => parse_large_chunks_simd_dummy,
-        "parse_large_chunks_simd" => parse_large_chunks_simd,
-        "parse_large_chunks_simd_v1" => parse_large_chunks_simd_v1,
-        "parse_large_chunks_simd_v2_dummy" => parse_large_chunks_simd_v2_dummy,
-        "parse_large_chunks_simd_v2" => parse_large_chunks_simd_v2,
-        x => panic!("{}", x),
+    let (args, options) = parse_args()?;
+    match args.first().map(String::as_str) {
+        Some("index") => return index_files(&args[1..], &options),
+        Some("transcode") => return transcode_files(&args[1..], &options),
+        _ => {},
+    }
+    let (run, mode) = configure(&args, &options)?;
+    let (output, kernel) = match mode {
+        Mode::Thresholds(thresholds) => (run_thresholds(&run, thresholds), Kernel::baseline()),
+        Mode::Extremes(k) => (run_extremes(&run, k), Kernel::baseline()),
+        Mode::TimeSeries(granularity) => (run_time_series(&run, granularity), Kernel::baseline()),
+        Mode::Metrics(schema) => (run_metrics(&run, schema), Kernel::baseline()),
+        Mode::Sample(parser, kernel, sampling) => (run_sample(&run, parser, sampling), kernel),
+        Mode::Plain(parser, kernel, group_by) => (run_plain(&run, parser, group_by)?, kernel),
     };
 
-    let file = File::open(&path).unwrap();
-    let file_length = file.metadata().unwrap().len() as usize;
+    let stdout = std::io::stdout();
+    let mut handle = stdout.lock();
+    handle.write_all(output.as_bytes()).unwrap();
+
+    // Write some stats
+    let file_length: usize = run.inputs.iter().map(|p| input_len(p)).sum();
+    let file_length_mbytes = file_length as f64 / 1024.0f64 / 1024.0f64;
+    let elapsed_secs = instant.elapsed().as_millis() as f64 / 1000.0f64;
+    let avg_processing_throughput = file_length_mbytes / elapsed_secs;
+    eprintln!(
+        "Processed using `{}` ({kernel} kernel) in {} ms, avg_processing_throughput: {:.4} \
+         MBytes/s",
+        run.method,
+        instant.elapsed().as_millis(),
+        avg_processing_throughput
+    );
+    let skipped = run.skipped.load(Ordering::Relaxed);
+    if skipped != 0 {
+        eprintln!("Skipped {skipped} records of stations missing from the station list");
+    }
+    Ok(())
+}
+
+/// The inputs and settings every mode runs with.
+struct Run {
+    method: String,
+    inputs: Vec<PathBuf>,
+    cores: usize,
+    per_file: bool,
+    normalization: Normalization,
+    collation: Collation,
+    tracker: ProgressTracker,
+    /// Records of unlisted stations skipped by `--unknown-stations=count`.
+    skipped: Arc<AtomicU64>,
+}
+
+/// What to aggregate, picked from the implementation and the options.
+enum Mode {
+    Thresholds(Thresholds),
+    Extremes(usize),
+    TimeSeries(Granularity),
+    Metrics(Arc<Schema>),
+    /// `min/mean/max` estimated from a sample of the chunks.
+    Sample(Arc<ChunkParser>, Kernel, Sampling),
+    /// `min/mean/max` of every station, or of every group of `--group-by`.
+    Plain(Arc<ChunkParser>, Kernel, Option<GroupBy>),
+}
+
+/// The `--sample`, `--time-budget` and `--seed` options.
+struct Sampling {
+    fraction: f64,
+    budget: Option<Duration>,
+    seed: u64,
+}
+
+/// The `--metadata` file and the `--group-by` column of it.
+type GroupBy = (StationMetadata, String);
+
+/// Reads the inputs, the thread count, the implementation and the options into
+/// the run and its mode.
+fn configure(args: &[String], options: &HashMap<String, String>) -> Result<(Run, Mode), String> {
+    let paths = args
+        .iter()
+        .take_while(|a| usize::from_str(a).is_err())
+        .count();
+    let inputs = if paths == 0 {
+        vec![PathBuf::from("brc-core/test_resources/sample.txt")]
+    } else {
+        expand_inputs(&args[..paths])?
+    };
+    let args = &args[paths..];
+    let format = input_format(&inputs[0]);
+    check(inputs.iter().all(|p| input_format(p) == format), || {
+        "Text and columnar inputs cannot be mixed".to_owned()
+    })?;
+    let cores: usize = match args.first() {
+        Some(c) => usize::from_str(c).map_err(|e| format!("Invalid thread count `{c}`: {e}"))?,
+        None => thread::available_parallelism().unwrap().into(),
+    };
+    let normalization = parse_option(options, "normalize", Normalization::from_str)?;
+    let mut tracker = ProgressTracker::new(cores);
+    if options.contains_key("progress") {
+        tracker = tracker.with_callback(Duration::from_millis(200), draw_progress);
+    }
+    let run = Run {
+        method: String::new(),
+        inputs,
+        cores,
+        per_file: options.contains_key("per-file"),
+        normalization: normalization.unwrap_or_default(),
+        collation: parse_option(options, "sort", Collation::from_str)?.unwrap_or_default(),
+        tracker,
+        skipped: Arc::new(AtomicU64::new(0)),
+    };
+    let (method, mode) = select_mode(&run, args.get(1), format, options)?;
+    Ok((Run { method, ..run }, mode))
+}
+
+/// The implementation to run, `method` or the default for the options, and
+/// the mode it runs in. Fails on options the implementation does not take.
+fn select_mode(
+    run: &Run,
+    method: Option<&String>,
+    format: InputFormat,
+    options: &HashMap<String, String>,
+) -> Result<(String, Mode), String> {
+    let stations = options.get("stations");
+    let unknown = parse_option(options, "unknown-stations", UnknownStations::from_str)?;
+    let filter = station_filter(options)?;
+    let thresholds = thresholds(options)?;
+    let top = parse_option(options, "top", usize::from_str)?;
+    let granularity = parse_option(options, "timestamps", Granularity::from_str)?;
+    let schema = parse_option(options, "metrics", Schema::parse)?.map(Arc::new);
+    let group_by = group_by(options)?;
+    let sampling = sampling(options)?;
+    let default_impl = if !thresholds.is_empty() {
+        THRESHOLDS_IMPL
+    } else if top.is_some() {
+        EXTREMES_IMPL
+    } else if granularity.is_some() {
+        TIME_SERIES_IMPL
+    } else if schema.is_some() {
+        METRICS_IMPL
+    } else if stations.is_some() {
+        DICTIONARY_IMPL
+    } else if !filter.is_empty() {
+        FILTERED_IMPL
+    } else if format == InputFormat::Columnar {
+        COLUMNAR_IMPL
+    } else {
+        DEFAULT_IMPL
+    };
+    let method = method.map_or(default_impl, String::as_str);
+
+    check(
+        filtered_parser(method).is_some() || filter.is_empty(),
+        || {
+            format!(
+                "Station filters only work with {FILTERED_IMPL} and {:?}",
+                TableParser::ALL.map(TableParser::name)
+            )
+        },
+    )?;
+    check(method == THRESHOLDS_IMPL || thresholds.is_empty(), || {
+        format!("`--above` and `--below` only work with {THRESHOLDS_IMPL}")
+    })?;
+    check(method == EXTREMES_IMPL || top.is_none(), || {
+        format!("`--top` only works with {EXTREMES_IMPL}")
+    })?;
+    check(method == TIME_SERIES_IMPL || granularity.is_none(), || {
+        format!("`--timestamps` only works with {TIME_SERIES_IMPL}")
+    })?;
+    check(method == METRICS_IMPL || schema.is_none(), || {
+        format!("`--metrics` only works with {METRICS_IMPL}")
+    })?;
+    check(method == DICTIONARY_IMPL || stations.is_none(), || {
+        format!("`--stations` only works with {DICTIONARY_IMPL}")
+    })?;
+    let input = find_implementation::<Input>(method).map_or(InputFormat::Text, |i| i.input);
+    check(input == format, || {
+        let format = match format {
+            InputFormat::Text => "text",
+            InputFormat::Columnar => "columnar",
+        };
+        format!("`{method}` does not read {format} inputs")
+    })?;
+    let extended = [
+        THRESHOLDS_IMPL,
+        EXTREMES_IMPL,
+        TIME_SERIES_IMPL,
+        METRICS_IMPL,
+    ]
+    .contains(&method);
+    check(!extended || group_by.is_none(), || {
+        format!("`--group-by` does not work with {method}")
+    })?;
+    check(!extended || sampling.is_none(), || {
+        format!("`--sample` and `--time-budget` do not work with {method}")
+    })?;
+    check(
+        sampling.is_none() || (group_by.is_none() && !run.per_file),
+        || {
+            "`--sample` and `--time-budget` do not work with `--group-by` or `--per-file`"
+                .to_owned()
+        },
+    )?;
+
+    let required = |option: &str| format!("`--{option}` is required by {method}");
+    let mode = if method == THRESHOLDS_IMPL {
+        Mode::Thresholds(thresholds)
+    } else if method == EXTREMES_IMPL {
+        Mode::Extremes(top.ok_or_else(|| required("top"))?)
+    } else if method == TIME_SERIES_IMPL {
+        Mode::TimeSeries(granularity.ok_or_else(|| required("timestamps"))?)
+    } else if method == METRICS_IMPL {
+        Mode::Metrics(schema.ok_or_else(|| required("metrics"))?)
+    } else {
+        let (parser, kernel) = state_parser(
+            method,
+            &run.inputs[0],
+            stations,
+            unknown.unwrap_or_default(),
+            filter,
+            run.normalization,
+            &run.skipped,
+        )?;
+        match sampling {
+            Some(sampling) => Mode::Sample(parser, kernel, sampling),
+            None => Mode::Plain(parser, kernel, group_by),
+        }
+    };
+    Ok((method.to_owned(), mode))
+}
+
+/// `Err` with `message` unless `condition` holds.
+fn check(condition: bool, message: impl FnOnce() -> String) -> Result<(), String> {
+    if condition { Ok(()) } else { Err(message()) }
+}
+
+/// The value of `--name` parsed with `parse`, if given.
+fn parse_option<T, E: Display>(
+    options: &HashMap<String, String>,
+    name: &str,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+) -> Result<Option<T>, String> {
+    options
+        .get(name)
+        .map(|value| parse(value).map_err(|e| format!("Invalid `--{name}={value}`: {e}")))
+        .transpose()
+}
+
+/// Reads the `--metadata` file for `--group-by`.
+fn group_by(options: &HashMap<String, String>) -> Result<Option<GroupBy>, String> {
+    match (options.get("metadata"), options.get("group-by")) {
+        (Some(path), Some(column)) => {
+            let metadata = StationMetadata::from_file(path)?;
+            check(metadata.columns().contains(column), || {
+                format!(
+                    "Unknown metadata column `{column}`, expected one of {:?}",
+                    metadata.columns()
+                )
+            })?;
+            Ok(Some((metadata, column.clone())))
+        },
+        (None, None) => Ok(None),
+        _ => Err("`--metadata` and `--group-by` must be given together".to_owned()),
+    }
+}
+
+/// Reads the `--sample`, `--time-budget` and `--seed` options, drawing a seed
+/// if none is given.
+fn sampling(options: &HashMap<String, String>) -> Result<Option<Sampling>, String> {
+    let fraction = parse_option(options, "sample", |f| {
+        f64::from_str(f)
+            .ok()
+            .filter(|f| *f > 0.0 && *f <= 1.0)
+            .ok_or("expected a fraction in (0, 1]")
+    })?;
+    let budget = parse_option(options, "time-budget", u64::from_str)?.map(Duration::from_millis);
+    let seed = parse_option(options, "seed", u64::from_str)?;
+    if fraction.is_none() && budget.is_none() {
+        check(seed.is_none(), || {
+            "`--seed` only works with `--sample` or `--time-budget`".to_owned()
+        })?;
+        return Ok(None);
+    }
+    Ok(Some(Sampling {
+        fraction: fraction.unwrap_or(1.0),
+        budget,
+        seed: seed.unwrap_or_else(|| rand::rng().random()),
+    }))
+}
+
+/// Sorts `result` and the stations of every file in `files` by the collation
+/// of `run`, and formats them as the `PATH: {...}` line of every file followed
+/// by the merged `{...}`.
+fn stations_output<S: Display>(
+    run: &Run,
+    result: &mut Stations<S>,
+    files: Vec<Stations<S>>,
+) -> String {
+    run.collation.sort(result);
+    per_file_output(&run.inputs, files, &run.collation) + &format_output(result)
+}
+
+fn run_thresholds(run: &Run, thresholds: Thresholds) -> String {
+    let normalization = run.normalization;
+    let func = move |rdr, start, end_inclusive, should_sort| {
+        parse_large_chunks_thresholds(
+            rdr,
+            start,
+            end_inclusive,
+            should_sort,
+            thresholds,
+            normalization,
+        )
+    };
+    let (mut result, files) = aggregate(run, Arc::new(func), |r| r, ThresholdState::merge);
+    stations_output(run, &mut result, files)
+}
+
+fn run_extremes(run: &Run, k: usize) -> String {
+    let normalization = run.normalization;
+    let func = move |rdr, start, end_inclusive, should_sort| {
+        parse_large_chunks_extremes(rdr, start, end_inclusive, should_sort, k, normalization)
+    };
+    let (mut result, files) = aggregate(run, Arc::new(func), |r| r, ExtremeState::merge);
+    let output = stations_output(run, &mut result, files);
+    output + &GlobalExtremes::new(&result).to_string()
+}
+
+fn run_time_series(run: &Run, granularity: Granularity) -> String {
+    let normalization = run.normalization;
+    let func = move |rdr, start, end_inclusive, should_sort| {
+        parse_large_chunks_time_series(
+            rdr,
+            start,
+            end_inclusive,
+            should_sort,
+            granularity,
+            normalization,
+        )
+    };
+    let (mut result, files) = aggregate(run, Arc::new(func), |r| r, TimeSeriesState::merge);
+    stations_output(run, &mut result, files)
+}
+
+fn run_metrics(run: &Run, schema: Arc<Schema>) -> String {
+    let normalization = run.normalization;
+    let func = move |rdr, start, end_inclusive, should_sort| {
+        parse_large_chunks_metrics(
+            rdr,
+            start,
+            end_inclusive,
+            should_sort,
+            schema.clone(),
+            normalization,
+        )
+    };
+    let (mut result, files) = aggregate(run, Arc::new(func), |r| r, MultiState::merge);
+    stations_output(run, &mut result, files)
+}
+
+fn run_sample(run: &Run, parser: Arc<ChunkParser>, sampling: Sampling) -> String {
+    let (chunks, total) = sample_chunks(run, parser, &sampling);
+    let mut sample = ChunkSample::new(total as u64);
+    for chunk in chunks {
+        sample.add_chunk(run.normalization.apply(chunk));
+    }
+    eprintln!(
+        "Sampled {} of {} chunks with seed {}",
+        sample.sampled(),
+        sample.chunks(),
+        sampling.seed
+    );
+    stations_output(run, &mut sample.estimates(Z_95), Vec::new())
+}
+
+fn run_plain(
+    run: &Run,
+    parser: Arc<ChunkParser>,
+    group_by: Option<GroupBy>,
+) -> Result<String, String> {
+    let (mut result, mut files) =
+        aggregate(run, parser, |r| run.normalization.apply(r), StateI::merge);
+    if let Some((metadata, column)) = &group_by {
+        for file in &mut files {
+            *file = metadata.roll_up(std::mem::take(file), column)?.groups;
+        }
+        let rollup = metadata.roll_up(result, column)?;
+        if !rollup.unmatched.is_empty() {
+            eprintln!(
+                "{} stations missing from the station metadata: {}",
+                rollup.unmatched.len(),
+                rollup.unmatched.join(", ")
+            );
+        }
+        result = rollup.groups;
+    }
+    Ok(stations_output(run, &mut result, files))
+}
+
+/// Picks the parser of `method` for the plain `min/mean/max` aggregation. A
+/// [`TableParser`] runs as `parse_large_chunks_filtered` when it has to filter
+/// or normalize stations.
+fn state_parser(
+    method: &str,
+    path: &Path,
+    stations: Option<&String>,
+    unknown: UnknownStations,
+    filter: StationFilter,
+    normalization: Normalization,
+    skipped: &Arc<AtomicU64>,
+) -> Result<(Arc<ChunkParser>, Kernel), String> {
+    if method == DICTIONARY_IMPL {
+        let stations =
+            stations.ok_or("`--stations` is required by parse_large_chunks_dictionary")?;
+        let dictionary = StationDictionary::from_file(stations)?;
+        let skipped = skipped.clone();
+        let func = move |rdr, start, end_inclusive, should_sort| {
+            let (result, n) = parse_large_chunks_dictionary(
+                rdr,
+                start,
+                end_inclusive,
+                should_sort,
+                &dictionary,
+                unknown,
+            );
+            skipped.fetch_add(n, Ordering::Relaxed);
+            result
+        };
+        Ok((Arc::new(func), Kernel::baseline()))
+    } else if let Some(parser) = filtered_parser(method)
+        && (method == FILTERED_IMPL || !filter.is_empty() || normalization != Normalization::None)
+    {
+        let func = move |rdr, start, end_inclusive, should_sort| {
+            parse_large_chunks_filtered(
+                rdr,
+                start,
+                end_inclusive,
+                should_sort,
+                &filter,
+                normalization,
+                parser,
+            )
+        };
+        Ok((Arc::new(func), Kernel::selected()))
+    } else if method == ADAPTIVE_IMPL {
+        let mut file = File::open(path).unwrap();
+        let len = file.metadata().unwrap().len();
+        let profile = profile_input(&mut file, len)?;
+        let choice = choose_implementation::<Input>(&profile);
+        eprintln!("Sampled {profile}");
+        eprintln!("Picked {choice}");
+        Ok((
+            Arc::new(choice.implementation.parse),
+            choice.implementation.kernel(),
+        ))
+    } else {
+        let implementation = find_implementation::<Input>(method).ok_or_else(|| {
+            let known: Vec<&str> = implementations::<Input>().iter().map(|i| i.name).collect();
+            format!("Unknown implementation `{method}`, expected one of {known:?}")
+        })?;
+        Ok((Arc::new(implementation.parse), implementation.kernel()))
+    }
+}
+
+/// Parses all inputs of `run` in chunks, newline-aligned for text, on one pool
+/// of its threads and merges the stations, unsorted. Every input is split in
+/// proportion to its size, into about `cores` chunks in total but at least
+/// one per non-empty input, and each thread takes the next chunk of any input
+/// when done. Chunks and inputs are merged in order, the stations of each
+/// chunk after passing through `normalize`. With `--per-file`, the second
+/// element holds the stations of every input on its own; it is empty
+/// otherwise.
+fn aggregate<S: Clone + Send>(
+    run: &Run,
+    func: Arc<ChunkParser<S>>,
+    normalize: impl Fn(Stations<S>) -> Stations<S>,
+    merge: fn(&mut S, &S),
+) -> (Stations<S>, Vec<Stations<S>>) {
+    let (inputs, cores, tracker) = (&run.inputs, run.cores, &run.tracker);
+    let lengths: Vec<usize> = inputs.iter().map(|p| input_len(p)).collect();
+    let formats: Vec<InputFormat> = inputs.iter().map(|p| input_format(p)).collect();
+    let total_length: usize = lengths.iter().sum();
+    let chunk_size = (total_length / cores.max(1)).max(1);
+    // (input, start, end_inclusive) of every chunk, in order
+    let mut chunks: Vec<(usize, usize, usize)> = Vec::new();
+    let mut indexed = 0;
+    for (input, (path, &length)) in inputs.iter().zip(&lengths).enumerate() {
+        if length == 0 {
+            continue;
+        }
+        if cores <= 1 {
+            chunks.push((input, 0, length - 1));
+            continue;
+        }
+        let count = ((length + chunk_size / 2) / chunk_size).max(1);
+        if formats[input] == InputFormat::Columnar {
+            // The reader takes the blocks starting in its range, split at
+            // block starts so that the ranges hold what their readers read
+            let file = ColumnarFile::open(&mut File::open(path).unwrap())
+                .unwrap_or_else(|e| panic!("{e}"));
+            let count = count.min(file.blocks() as usize).max(1) as u64;
+            let starts: Vec<usize> = (0..count)
+                .map(|i| match i {
+                    0 => 0,
+                    i => file.block_offset(file.blocks() * i / count) as usize,
+                })
+                .chain([length])
+                .collect();
+            chunks.extend(starts.windows(2).map(|w| (input, w[0], w[1] - 1)));
+            continue;
+        }
+        let index = ChunkIndex::load_for(path).unwrap_or_else(|e| {
+            eprintln!("{e}, splitting without it");
+            None
+        });
+        match index {
+            Some(index) => {
+                indexed += 1;
+                chunks.extend(
+                    index
+                        .chunks(count)
+                        .into_iter()
+                        .map(|chunk| (input, chunk.start as usize, chunk.end_inclusive as usize)),
+                );
+            },
+            None => chunks.extend(
+                get_chunks(count, File::open(path).unwrap())
+                    .into_iter()
+                    .map(|(start, end)| (input, start, end)),
+            ),
+        }
+    }
 
-    let xs = if cores <= 1 {
-        let rdr = BufReader::with_capacity(
-            DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
-            File::open(&path).unwrap(),
+    let mut results: Vec<Option<Stations<S>>> = chunks.iter().map(|_| None).collect();
+    if cores <= 1 {
+        tracker
+            .run(total_length as u64, || {
+                for (result, &(input, start, end)) in results.iter_mut().zip(&chunks) {
+                    let file = File::open(&inputs[input]).unwrap();
+                    let rdr = open_chunk(
+                        tracker,
+                        0,
+                        file,
+                        formats[input],
+                        (start, end),
+                        DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
+                    );
+                    *result = Some(func(rdr, start as u64, end as u64, true));
+                }
+            })
+            .unwrap_or_else(|e| panic!("{e}"));
+    } else {
+        eprintln!(
+            "For {cores} cores prepared {} chunks of {} files ({indexed} indexed), chunk_size: \
+             {chunk_size}, file_length: {total_length}",
+            chunks.len(),
+            inputs.len()
         );
-        vec![func(rdr, 0, (file_length - 1) as u64, true)]
+        let next = &AtomicUsize::new(0);
+        let (chunks, formats, func) = (&chunks, &formats, &func);
+        let done: Vec<Vec<(usize, Stations<S>)>> = tracker
+            .run(total_length as u64, || {
+                thread::scope(|scope| {
+                    let workers: Vec<_> = (0..cores.min(chunks.len()))
+                        .map(|thread| {
+                            scope.spawn(move || {
+                                let mut done = Vec::new();
+                                loop {
+                                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                                    let Some(&(input, start, end)) = chunks.get(idx) else {
+                                        return done;
+                                    };
+                                    let file = File::open(&inputs[input]).unwrap();
+                                    let rdr = open_chunk(
+                                        tracker,
+                                        thread,
+                                        file,
+                                        formats[input],
+                                        (start, end),
+                                        BUF_READER_CAPACITY,
+                                    );
+                                    done.push((idx, func(rdr, start as u64, end as u64, false)));
+                                }
+                            })
+                        })
+                        .collect();
+                    workers.into_iter().map(|w| w.join().unwrap()).collect()
+                })
+            })
+            .unwrap_or_else(|e| panic!("{e}"));
+        for (idx, result) in done.into_iter().flatten() {
+            results[idx] = Some(result);
+        }
+    }
+
+    let mut by_input: Vec<Vec<Stations<S>>> = inputs.iter().map(|_| Vec::new()).collect();
+    for (&(input, _, _), result) in chunks.iter().zip(results) {
+        by_input[input].push(normalize(result.expect("Chunk was not parsed")));
+    }
+    if run.per_file {
+        let files: Vec<Stations<S>> = by_input
+            .into_iter()
+            .map(|chunks| merge_results(chunks, merge))
+            .collect();
+        (merge_results(files.clone(), merge), files)
     } else {
-        // Prepare chunks and run threads with chunks assigned to them
-        let chunks = get_chunks(cores, file);
-        let threads: Vec<_> = chunks
-            .iter()
-            .map(|(s, e)| {
-                let start = *s as u64;
-                let end_inclusive = *e as u64;
-                let path = path.clone();
-                thread::Builder::new()
-                    .spawn(move || {
-                        let rdr = BufReader::with_capacity(
-                            BUF_READER_CAPACITY,
-                            File::open(&path).unwrap(),
-                        );
-                        func(rdr, start, end_inclusive, false)
+        (
+            merge_results(by_input.into_iter().flatten(), merge),
+            Vec::new(),
+        )
+    }
+}
+
+/// Parses a random sample of the [`SAMPLE_CHUNK_LEN`] chunks of all inputs of
+/// `run` on a pool of its threads and returns the stations of every parsed
+/// chunk with the number of chunks. Chunks are parsed in an order shuffled
+/// with the seed until the fraction of them are done or the time budget of
+/// `sampling` is spent. A text chunk holds the lines that start in it, so
+/// every chunk finds its bounds on its own, without a pass over the file.
+fn sample_chunks(run: &Run, func: Arc<ChunkParser>, sampling: &Sampling) -> (Vec<Stations>, usize) {
+    let (inputs, cores, tracker) = (&run.inputs, run.cores, &run.tracker);
+    let &Sampling {
+        fraction,
+        budget,
+        seed,
+    } = sampling;
+    let deadline = budget.map(|budget| Instant::now() + budget);
+    let lengths: Vec<usize> = inputs.iter().map(|p| input_len(p)).collect();
+    let formats: Vec<InputFormat> = inputs.iter().map(|p| input_format(p)).collect();
+    // (input, start, end) of every chunk
+    let mut chunks: Vec<(usize, usize, usize)> = Vec::new();
+    for (input, &length) in lengths.iter().enumerate() {
+        chunks.extend((0..length.div_ceil(SAMPLE_CHUNK_LEN)).map(|i| {
+            (
+                input,
+                i * SAMPLE_CHUNK_LEN,
+                ((i + 1) * SAMPLE_CHUNK_LEN).min(length),
+            )
+        }));
+    }
+    chunks.shuffle(&mut StdRng::seed_from_u64(seed));
+    let target = ((chunks.len() as f64 * fraction).ceil() as usize).min(chunks.len());
+
+    let target_bytes: usize = chunks[..target]
+        .iter()
+        .map(|&(_, start, end)| end - start)
+        .sum();
+
+    let next = &AtomicUsize::new(0);
+    let (chunks, formats, func) = (&chunks, &formats, &func);
+    let results = tracker
+        .run(target_bytes as u64, || {
+            thread::scope(|scope| {
+                let workers: Vec<_> = (0..cores.clamp(1, target.max(1)))
+                    .map(|thread| {
+                        scope.spawn(move || {
+                            let mut done = Vec::new();
+                            loop {
+                                let idx = next.fetch_add(1, Ordering::Relaxed);
+                                if idx >= target || deadline.is_some_and(|d| Instant::now() >= d) {
+                                    return done;
+                                }
+                                let (input, chunk_start, chunk_end) = chunks[idx];
+                                let mut file = File::open(&inputs[input]).unwrap();
+                                let (start, end) = match formats[input] {
+                                    InputFormat::Text => (
+                                        line_start(&mut file, chunk_start),
+                                        line_start(&mut file, chunk_end),
+                                    ),
+                                    InputFormat::Columnar => (chunk_start, chunk_end),
+                                };
+                                done.push(if start < end {
+                                    let rdr = open_chunk(
+                                        tracker,
+                                        thread,
+                                        file,
+                                        formats[input],
+                                        (chunk_start, chunk_end - 1),
+                                        SAMPLE_CHUNK_LEN,
+                                    );
+                                    func(rdr, start as u64, end as u64 - 1, false)
+                                } else {
+                                    Vec::new()
+                                });
+                            }
+                        })
                     })
-                    .unwrap()
+                    .collect();
+                workers
+                    .into_iter()
+                    .flat_map(|w| w.join().unwrap())
+                    .collect()
             })
-            .collect();
-        let mut r: Vec<Vec<(String, StateF)>> = Vec::with_capacity(cores);
-        for t in threads {
-            r.push(t.join().unwrap());
+        })
+        .unwrap_or_else(|e| panic!("{e}"));
+    (results, chunks.len())
+}
+
+/// Opens `file` for worker `thread`, counting the progress of the bytes
+/// `start..=end_inclusive` of it.
+fn open_chunk(
+    tracker: &ProgressTracker,
+    thread: usize,
+    file: File,
+    format: InputFormat,
+    (start, end_inclusive): (usize, usize),
+    capacity: usize,
+) -> BufReader<Input> {
+    match format {
+        InputFormat::Text => BufReader::with_capacity(
+            capacity,
+            tracker.reader(thread, file, start as u64, end_inclusive as u64),
+        ),
+        // The columnar parser reads through the buffer, which a tracked reader
+        // has to zero first, unlike a file, so it gets a small one
+        InputFormat::Columnar => BufReader::with_capacity(
+            capacity.min(COLUMNAR_BUFFER_LEN),
+            tracker.binary_reader(thread, file, start as u64, end_inclusive as u64),
+        ),
+    }
+}
+
+/// Draws `progress` as a bar on stderr, redrawn in place until the run is
+/// finished.
+fn draw_progress(progress: &Progress) {
+    const WIDTH: usize = 30;
+    const MBYTES: f64 = 1024.0 * 1024.0;
+    let filled = (progress.fraction() * WIDTH as f64) as usize;
+    let rates: Vec<String> = progress
+        .threads
+        .iter()
+        .map(|t| format!("{:.0}", t.rate / MBYTES))
+        .collect();
+    eprint!(
+        "\r[{}{}] {:5.1}% {:.1}/{:.1} MBytes, {} records, {:.1} MBytes/s (per thread: {})",
+        "#".repeat(filled),
+        "-".repeat(WIDTH - filled),
+        progress.fraction() * 100.0,
+        progress.bytes() as f64 / MBYTES,
+        progress.total_bytes as f64 / MBYTES,
+        progress.records(),
+        progress.rate() / MBYTES,
+        rates.join(" ")
+    );
+    if progress.finished {
+        eprintln!();
+    }
+}
+
+/// The start of the first line at or after `offset` of `file`, which ends with
+/// a newline, or its length if there is none.
+fn line_start(file: &mut File, offset: usize) -> usize {
+    if offset == 0 {
+        return 0;
+    }
+    file.seek(SeekFrom::Start(offset as u64 - 1)).unwrap();
+    let mut buf = [0_u8; 512];
+    let mut position = offset - 1;
+    loop {
+        let read_bytes = file.read(&mut buf).unwrap();
+        if read_bytes == 0 {
+            return position;
         }
-        r
-    };
+        if let Some(i) = buf[..read_bytes].iter().position(|&b| b == b'\n') {
+            return position + i + 1;
+        }
+        position += read_bytes;
+    }
+}
 
-    // Build the final hashmap by merging all the measurements for the same location
-    let mut hs: hashbrown::HashMap<String, StateF> = hashbrown::HashMap::new();
-    for r in xs {
+/// Merges the stations of `results` in order. States are merged in integer
+/// tenths, so the output does not depend on how the input was split between
+/// threads.
+fn merge_results<S>(
+    results: impl IntoIterator<Item = Stations<S>>,
+    merge: fn(&mut S, &S),
+) -> Stations<S> {
+    let mut hs: hashbrown::HashMap<String, S> = hashbrown::HashMap::new();
+    for r in results {
         for (k, s) in r {
             match hs.get_mut(k.as_str()) {
                 None => {
                     hs.insert(k, s);
                 },
                 Some(prev) => {
-                    prev.merge(&s);
+                    merge(prev, &s);
                 },
             }
         }
     }
-    let mut final_result = hs.into_iter().collect();
-    sort_result(&mut final_result);
+    hs.into_iter().collect()
+}
 
-    // Prepare result and write to console
-    let output = prepare_output(&mut final_result);
-    let stdout = std::io::stdout();
-    let mut handle = stdout.lock();
-    handle.write_all(output.as_bytes()).unwrap();
+/// `PATH: {...}` lines with the sorted stations of every input.
+fn per_file_output<S: Display>(
+    inputs: &[PathBuf],
+    files: Vec<Stations<S>>,
+    collation: &Collation,
+) -> String {
+    let mut output = String::new();
+    for (path, mut result) in inputs.iter().zip(files) {
+        collation.sort(&mut result);
+        output.push_str(&format!("{}: ", path.display()));
+        output.push_str(&format_output(&result));
+    }
+    output
+}
 
-    // Write some stats
-    let file_length_mbytes = file_length as f64 / 1024.0f64 / 1024.0f64;
-    let elapsed_secs = instant.elapsed().as_millis() as f64 / 1000.0f64;
-    let avg_processing_throughput = file_length_mbytes / elapsed_secs;
+fn input_format(path: &Path) -> InputFormat {
+    if is_columnar(path) {
+        InputFormat::Columnar
+    } else {
+        InputFormat::Text
+    }
+}
+
+fn input_len(path: &Path) -> usize {
+    File::open(path)
+        .and_then(|file| file.metadata())
+        .unwrap_or_else(|e| panic!("Unable to open {}: {e}", path.display()))
+        .len() as usize
+}
+
+/// Reads the `--above` and `--below` thresholds.
+fn thresholds(options: &HashMap<String, String>) -> Result<Thresholds, String> {
+    let mut thresholds = Thresholds::new();
+    if let Some(above) = parse_option(options, "above", parse_threshold)? {
+        thresholds = thresholds.above(above);
+    }
+    if let Some(below) = parse_option(options, "below", parse_threshold)? {
+        thresholds = thresholds.below(below);
+    }
+    Ok(thresholds)
+}
+
+/// The table parser that runs station filters for `method`, if it can.
+fn filtered_parser(method: &str) -> Option<TableParser> {
+    if method == FILTERED_IMPL {
+        return Some(TableParser::Memchr);
+    }
+    TableParser::ALL.into_iter().find(|p| p.name() == method)
+}
+
+/// Builds the station filter from the `--allow`, `--deny`, `--prefix`,
+/// `--regex` and `--glob` options.
+fn station_filter(options: &HashMap<String, String>) -> Result<StationFilter, String> {
+    let mut filter = StationFilter::new();
+    if let Some(path) = options.get("allow") {
+        filter = filter.allow(read_station_list(path)?);
+    }
+    if let Some(path) = options.get("deny") {
+        filter = filter.deny(read_station_list(path)?);
+    }
+    if let Some(prefix) = options.get("prefix") {
+        filter = filter.prefix(prefix.as_str());
+    }
+    if let Some(regex) = options.get("regex") {
+        filter = filter.regex(regex)?;
+    }
+    if let Some(glob) = options.get("glob") {
+        filter = filter.glob(glob)?;
+    }
+    Ok(filter)
+}
+
+/// Writes the [`ChunkIndex`] of every file in `paths` next to it, with
+/// offsets `--stride` bytes apart, 1 MiB by default. Later runs split an
+/// indexed file into any number of chunks without reading it, after checking
+/// that the file did not change.
+fn index_files(paths: &[String], options: &HashMap<String, String>) -> Result<(), String> {
+    let stride = parse_option(options, "stride", positive::<u64>)?.unwrap_or(DEFAULT_INDEX_STRIDE);
+    for input in expand_inputs(paths)? {
+        let instant = Instant::now();
+        let index = File::open(&input)
+            .map_err(|e| e.to_string())
+            .and_then(|mut file| ChunkIndex::build(&mut file, stride))
+            .map_err(|e| format!("Unable to index {}: {e}", input.display()))?;
+        let sidecar = ChunkIndex::sidecar_path(&input);
+        index.write_to(&sidecar)?;
+        eprintln!(
+            "Indexed {} in {} ms: {} rows in {} blocks, written to {}",
+            input.display(),
+            instant.elapsed().as_millis(),
+            index.rows(),
+            index.blocks(),
+            sidecar.display()
+        );
+    }
+    Ok(())
+}
+
+/// A number above zero.
+fn positive<T: FromStr + Default + PartialEq>(s: &str) -> Result<T, &'static str> {
+    T::from_str(s)
+        .ok()
+        .filter(|n| *n != T::default())
+        .ok_or("expected a positive number")
+}
+
+/// Converts the text files in `paths`, all but the last argument, into one
+/// columnar file at the last one, with `--block-records` records per block,
+/// 65536 by default. Aggregating it reads 4 bytes per record instead of
+/// parsing about 14.
+fn transcode_files(paths: &[String], options: &HashMap<String, String>) -> Result<(), String> {
+    let block_records =
+        parse_option(options, "block-records", positive::<u32>)?.unwrap_or(DEFAULT_BLOCK_RECORDS);
+    let [inputs @ .., output] = paths else {
+        return Err("Usage: brc-app transcode [--block-records=N] PATH... OUTPUT".to_owned());
+    };
+    let inputs = expand_inputs(inputs)?;
+    let output = Path::new(output);
+    check(!inputs.iter().any(|i| i == output), || {
+        format!("{} is both an input and the output", output.display())
+    })?;
+    let instant = Instant::now();
+    let file =
+        File::create(output).map_err(|e| format!("Unable to create {}: {e}", output.display()))?;
+    let mut writer = ColumnarWriter::new(std::io::BufWriter::new(file), block_records)?;
+    for input in &inputs {
+        File::open(input)
+            .map_err(|e| e.to_string())
+            .and_then(|file| writer.push_text(file))
+            .map_err(|e| format!("Unable to transcode {}: {e}", input.display()))?;
+    }
+    let summary = writer.finish()?;
+    let text_len: usize = inputs.iter().map(|p| input_len(p)).sum();
     eprintln!(
-        "Processed using `{method}` in {} ms, avg_processing_throughput: {:.4} MBytes/s",
+        "Transcoded {} files of {text_len} bytes in {} ms: {} records of {} stations in {} \
+         blocks, {} bytes written to {}",
+        inputs.len(),
         instant.elapsed().as_millis(),
-        avg_processing_throughput
+        summary.records,
+        summary.stations,
+        summary.blocks,
+        summary.bytes,
+        output.display()
     );
+    Ok(())
 }
 
-fn prepare_output(final_result: &mut Vec<(String, StateF)>) -> String {
-    let mut res: String = String::new();
-    res.push_str("{");
-    for (i, (name, state)) in final_result.iter().enumerate() {
-        if i == 0 {
-            res.push_str(name.as_str());
-            res.push_str("=");
-            res.push_str(&state.to_string());
-        } else {
-            res.push_str(", ");
-            res.push_str(name.as_str());
-            res.push_str("=");
-            res.push_str(&state.to_string());
+/// Splits the command line into positional arguments and `--name=value`
+/// options.
+fn parse_args() -> Result<(Vec<String>, HashMap<String, String>), String> {
+    let mut args = Vec::new();
+    let mut options = HashMap::new();
+    for arg in std::env::args().skip(1) {
+        match arg.strip_prefix("--") {
+            Some(option) => {
+                let (name, value) = match option.split_once('=') {
+                    Some(pair) => pair,
+                    None if FLAGS.contains(&option) => (option, ""),
+                    None => return Err(format!("Expected `--name=value`, got `{arg}`")),
+                };
+                options.insert(name.to_owned(), value.to_owned());
+            },
+            None => args.push(arg),
         }
     }
-    res.push_str("}");
-    res.push_str("\n");
-    res
+    Ok((args, options))
 }
 
-fn get_chunks(cores: usize, file: File) -> Vec<(usize, usize)> {
+/// Splits `file` into about `count` newline-aligned chunks, fewer if its
+/// lines are longer than the chunks.
+fn get_chunks(count: usize, file: File) -> Vec<(usize, usize)> {
     let file_length = file.metadata().unwrap().len() as usize;
     let mut rdr = BufReader::with_capacity(1024 * 1024, file);
-    let chunk_size = file_length / cores;
+    let chunk_size = file_length / count;
     let mut chunks: Vec<(usize, usize)> = vec![];
     let mut start = 0;
 
     let mut buf = [0_u8; 512];
-    for _ in 0..cores {
-        let end = (start + chunk_size).min(file_length - 1);
+    while start < file_length && chunks.len() < count {
+        let end = if chunks.len() + 1 == count {
+            file_length - 1
+        } else {
+            (start + chunk_size).min(file_length - 1)
+        };
         rdr.seek(SeekFrom::Start(end as u64)).unwrap();
 
-        let read_bytes = rdr.read(&mut buf).unwrap();
-        assert_ne!(0, read_bytes, "start: {start}, end: {end}");
-
         // We move forward to find the closes new line to simplify reading per chunk - a
         // chunk is always complete, it will have full line
-        let valid_buf = &buf[0..read_bytes];
-        let mut i: usize = 0;
-        while i < valid_buf.len() && valid_buf[i] != 0xA {
-            i += 1;
+        let mut fixed_end = end;
+        loop {
+            let read_bytes = rdr.read(&mut buf).unwrap();
+            assert_ne!(
+                0, read_bytes,
+                "No newline after byte {end}, the file must end with one"
+            );
+            match buf[..read_bytes].iter().position(|&b| b == b'\n') {
+                Some(i) => {
+                    fixed_end += i;
+                    break;
+                },
+                None => fixed_end += read_bytes,
+            }
         }
-        assert_ne!(
-            i,
-            valid_buf.len(),
-            "Could not find 0xA in the buffer, something is wrong..."
-        );
-        let fixed_end = end + i;
         chunks.push((start, fixed_end));
         start = fixed_end + 1;
     }
-    eprintln!(
-        "For {cores} cores prepared {} chunks, chunk_size: {chunk_size}, file_length: \
-         {file_length}",
-        chunks.len()
-    );
     chunks
 }
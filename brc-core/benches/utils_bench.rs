@@ -1,10 +1,11 @@
+use std::hint::black_box;
 use std::time::Duration;
 
 use brc_core::{
     byte_to_string, byte_to_string_unsafe, parse_f64, to_scaled_integer,
     to_scaled_integer_branchless, to_scaled_integer_v2,
 };
-use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use criterion::{Criterion, criterion_group, criterion_main};
 
 pub fn criterion_benchmark(c: &mut Criterion) {
     let str_as_bytes = "Thiès Lake Havasu City Yaoundé Petropavlovsk-Kamchatsky".as_bytes();
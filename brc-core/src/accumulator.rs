@@ -0,0 +1,169 @@
+use crate::StateI;
+
+/// Per-station aggregation driven by the tables. Every table slot starts from
+/// [`Default`], the aggregate of no records, so it should not allocate;
+/// [`StateI`] is the default and costs nothing over hard-wiring it.
+pub trait Accumulator: Default + Clone {
+    /// What the aggregate of all records of a station is reported as.
+    type Output;
+
+    /// The aggregate of a single record.
+    fn new(value: i16) -> Self;
+
+    fn update(&mut self, value: i16);
+
+    /// Adds `other`. Chunks are merged in file order, `other` being the later
+    /// one, so order-sensitive aggregates like [`LastValue`] stay exact.
+    fn merge(&mut self, other: &Self);
+
+    fn finalize(self) -> Self::Output;
+}
+
+impl Accumulator for StateI {
+    type Output = StateI;
+
+    #[inline(always)]
+    fn new(value: i16) -> Self { StateI::new(value) }
+
+    #[inline(always)]
+    fn update(&mut self, value: i16) { StateI::update(self, value) }
+
+    fn merge(&mut self, other: &Self) { StateI::merge(self, other) }
+
+    fn finalize(self) -> Self::Output { self }
+}
+
+/// Finalizes the merged aggregates of all stations.
+pub fn finalize_result<A: Accumulator>(all: Vec<(String, A)>) -> Vec<(String, A::Output)> {
+    all.into_iter()
+        .map(|(name, acc)| (name, acc.finalize()))
+        .collect()
+}
+
+/// Number of records per whole degree: bucket `d` counts the readings in
+/// `[d, d + 1)`, from `-100` to `99`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    /// Empty until the first record, so that empty slots do not allocate.
+    buckets: Vec<u64>,
+}
+
+impl Histogram {
+    const BUCKETS: usize = 200;
+
+    #[inline(always)]
+    fn bucket(value: i16) -> usize { (value.div_euclid(10) + 100) as usize }
+
+    /// Records in `[degrees, degrees + 1)`.
+    pub fn count(&self, degrees: i16) -> u64 {
+        self.buckets
+            .get((degrees + 100) as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Records per bucket, from `-100` to `99`.
+    pub fn buckets(&self) -> &[u64] { &self.buckets }
+}
+
+impl Accumulator for Histogram {
+    type Output = Histogram;
+
+    fn new(value: i16) -> Self {
+        let mut histogram = Self::default();
+        histogram.update(value);
+        histogram
+    }
+
+    #[inline(always)]
+    fn update(&mut self, value: i16) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; Self::BUCKETS];
+        }
+        self.buckets[Self::bucket(value)] += 1;
+    }
+
+    fn merge(&mut self, other: &Self) {
+        if self.buckets.is_empty() {
+            self.buckets = other.buckets.clone();
+        } else {
+            for (count, other) in self.buckets.iter_mut().zip(&other.buckets) {
+                *count += other;
+            }
+        }
+    }
+
+    fn finalize(self) -> Self::Output { self }
+}
+
+/// The last record of a station in file order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LastValue(Option<i16>);
+
+impl Accumulator for LastValue {
+    type Output = Option<i16>;
+
+    fn new(value: i16) -> Self { Self(Some(value)) }
+
+    #[inline(always)]
+    fn update(&mut self, value: i16) { self.0 = Some(value) }
+
+    fn merge(&mut self, other: &Self) { self.0 = other.0.or(self.0) }
+
+    fn finalize(self) -> Self::Output { self.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fingerprint_table::FingerprintTable;
+
+    fn fill<A: Accumulator>(records: &[(&str, i16)]) -> Vec<(String, A)> {
+        let mut table = FingerprintTable::<64, A>::new();
+        for (name, value) in records {
+            let mut padded_name = name.as_bytes().to_vec();
+            padded_name.resize(name.len() + 8, 0);
+            table.insert_or_update(name.as_bytes(), &padded_name, *value);
+        }
+        let mut all = table.into_result();
+        crate::sort_result(&mut all);
+        all
+    }
+
+    #[test]
+    fn test_custom_accumulators() {
+        let records = [
+            ("Oslo", 5),
+            ("Lima", -5),
+            ("Oslo", 9),
+            ("Lima", -10),
+            ("Oslo", 10),
+            ("Lima", -11),
+        ];
+        let last = finalize_result(fill::<LastValue>(&records));
+        assert_eq!(
+            [
+                ("Lima".to_owned(), Some(-11)),
+                ("Oslo".to_owned(), Some(10))
+            ],
+            last.as_slice()
+        );
+
+        let histograms = fill::<Histogram>(&records);
+        let (lima, oslo) = (&histograms[0].1, &histograms[1].1);
+        assert_eq!((2, 1), (oslo.count(0), oslo.count(1)));
+        assert_eq!((0, 2, 1), (lima.count(0), lima.count(-1), lima.count(-2)));
+        assert_eq!(3, lima.buckets().iter().sum::<u64>());
+
+        // Merging later chunks keeps the later value
+        let mut merged = LastValue::new(1);
+        merged.merge(&LastValue::new(2));
+        merged.merge(&LastValue::default());
+        assert_eq!(Some(2), merged.finalize());
+
+        let mut merged = Histogram::default();
+        merged.merge(oslo);
+        merged.merge(lima);
+        assert_eq!(6, merged.buckets().iter().sum::<u64>());
+    }
+}
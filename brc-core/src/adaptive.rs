@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::fingerprint_table::fingerprint;
+use crate::registry::Implementation;
+use crate::*;
+
+/// Number of evenly spaced windows read by [`profile_input`].
+const SAMPLE_WINDOWS: u64 = 16;
+
+/// Size of one sample window. Sixteen windows sample 4 MiB.
+const SAMPLE_WINDOW_LEN: u64 = 256 * 1024;
+
+/// Smallest and largest table of [`parse_large_chunks_structural_table_sized`]
+/// the adaptive mode picks from.
+const MIN_TABLE_SIZE: usize = 1024;
+const MAX_TABLE_SIZE: usize = 65536;
+
+/// Shape of the measurements found in the sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// The challenge format: `-?D?D.D`, i.e. -99.9 to 99.9 with one decimal.
+    Challenge,
+    /// Any other number `f64::from_str` accepts.
+    Decimal,
+}
+
+/// What a sample of the input looks like, see [`profile_input`].
+#[derive(Debug, Clone)]
+pub struct InputProfile {
+    pub sampled_bytes: u64,
+    pub records: usize,
+    /// Stations seen in the sample.
+    pub distinct_stations: usize,
+    /// Stations expected in the whole input: the bias-corrected Chao1
+    /// estimate, which adds the stations likely missed by the sample based on
+    /// how many were seen only once or twice.
+    pub estimated_stations: usize,
+    /// Whether two distinct sampled names share a fingerprint, which the
+    /// fingerprint tables would merge.
+    pub fingerprint_collision: bool,
+    pub format: RecordFormat,
+    pub name_lengths: NameLengths,
+}
+
+/// Distribution of the station name lengths of the sampled records, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NameLengths {
+    pub mean: f64,
+    pub median: usize,
+    pub p99: usize,
+    pub max: usize,
+}
+
+impl NameLengths {
+    /// `histogram[len]` counts the records whose name is `len` bytes long.
+    fn from_histogram(histogram: &[u64]) -> Self {
+        let records: u64 = histogram.iter().sum();
+        if records == 0 {
+            return Self::default();
+        }
+        let bytes: u64 = histogram
+            .iter()
+            .enumerate()
+            .map(|(len, count)| len as u64 * count)
+            .sum();
+        let percentile = |p: u64| {
+            let rank = (records * p).div_ceil(100).max(1);
+            let mut seen = 0;
+            histogram
+                .iter()
+                .position(|count| {
+                    seen += count;
+                    seen >= rank
+                })
+                .unwrap()
+        };
+        Self {
+            mean: bytes as f64 / records as f64,
+            median: percentile(50),
+            p99: percentile(99),
+            max: histogram.len() - 1,
+        }
+    }
+}
+
+impl Display for InputProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} records in {} sampled bytes, {} stations seen, ~{} expected, {:?} format, names \
+             {:.1} bytes on average (median {}, p99 {}, max {})",
+            self.records,
+            self.sampled_bytes,
+            self.distinct_stations,
+            self.estimated_stations,
+            self.format,
+            self.name_lengths.mean,
+            self.name_lengths.median,
+            self.name_lengths.p99,
+            self.name_lengths.max
+        )
+    }
+}
+
+/// Reads [`SAMPLE_WINDOWS`] windows spread evenly over the first `len` bytes of
+/// `rdr` (all of it for small inputs), and profiles the complete records in
+/// them. Fails on lines that are not `name;number`, including CRLF line
+/// endings.
+pub fn profile_input<R: Read + Seek>(rdr: &mut R, len: u64) -> Result<InputProfile, String> {
+    let windows = if len <= SAMPLE_WINDOWS * SAMPLE_WINDOW_LEN {
+        vec![(0, len)]
+    } else {
+        let stride = len / SAMPLE_WINDOWS;
+        (0..SAMPLE_WINDOWS)
+            .map(|i| (i * stride, SAMPLE_WINDOW_LEN))
+            .collect()
+    };
+
+    let mut counts: HashMap<Vec<u8>, u64> = HashMap::new();
+    let mut profile = InputProfile {
+        sampled_bytes: 0,
+        records: 0,
+        distinct_stations: 0,
+        estimated_stations: 0,
+        fingerprint_collision: false,
+        format: RecordFormat::Challenge,
+        name_lengths: NameLengths::default(),
+    };
+    let mut name_lengths: Vec<u64> = Vec::new();
+    let mut window = Vec::new();
+    for (offset, window_len) in windows {
+        window.resize(window_len as usize, 0);
+        rdr.seek(SeekFrom::Start(offset))
+            .map_err(|e| e.to_string())?;
+        rdr.read_exact(&mut window).map_err(|e| e.to_string())?;
+        profile.sampled_bytes += window_len;
+
+        // Only complete lines: skip the one cut by the window start and the
+        // one cut by its end.
+        let first = if offset == 0 {
+            0
+        } else {
+            match memchr::memchr(b'\n', &window) {
+                Some(i) => i + 1,
+                None => continue,
+            }
+        };
+        let last = match memchr::memrchr(b'\n', &window) {
+            Some(last) if last >= first => last,
+            _ => continue,
+        };
+        for line in window[first..last].split(|b| *b == b'\n') {
+            let (name, value) = split_record(line)?;
+            if profile.format == RecordFormat::Challenge && !is_challenge_value(value) {
+                profile.format = RecordFormat::Decimal;
+            }
+            profile.records += 1;
+            if name_lengths.len() <= name.len() {
+                name_lengths.resize(name.len() + 1, 0);
+            }
+            name_lengths[name.len()] += 1;
+            *counts.entry(name.to_vec()).or_default() += 1;
+        }
+    }
+
+    profile.name_lengths = NameLengths::from_histogram(&name_lengths);
+    profile.distinct_stations = counts.len();
+    let seen_once = counts.values().filter(|c| **c == 1).count();
+    let seen_twice = counts.values().filter(|c| **c == 2).count();
+    profile.estimated_stations =
+        counts.len() + (seen_once * seen_once.saturating_sub(1)).div_ceil(2 * (seen_twice + 1));
+
+    let mut fingerprints: Vec<u64> = counts
+        .keys()
+        .map(|name| {
+            let mut padded_name = name.clone();
+            padded_name.resize(name.len() + 8, 0);
+            fingerprint(name, &padded_name)
+        })
+        .collect();
+    fingerprints.sort_unstable();
+    profile.fingerprint_collision = fingerprints.windows(2).any(|w| w[0] == w[1]);
+    Ok(profile)
+}
+
+fn split_record(line: &[u8]) -> Result<(&[u8], &[u8]), String> {
+    let describe = || String::from_utf8_lossy(line).into_owned();
+    if line.ends_with(b"\r") {
+        return Err(format!(
+            "Line `{}` ends with `\\r`, CRLF line endings are not supported",
+            describe().trim_end()
+        ));
+    }
+    let Some(semicolon_idx) = memchr::memchr(b';', line) else {
+        return Err(format!("Line `{}` has no `;`", describe()));
+    };
+    let (name, value) = (&line[..semicolon_idx], &line[semicolon_idx + 1..]);
+    if name.is_empty() || std::str::from_utf8(name).is_err() {
+        return Err(format!("Line `{}` has no valid station name", describe()));
+    }
+    if std::str::from_utf8(value)
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .is_none()
+    {
+        return Err(format!("Line `{}` has no valid measurement", describe()));
+    }
+    Ok((name, value))
+}
+
+fn is_challenge_value(value: &[u8]) -> bool {
+    let digits = value.strip_prefix(b"-").unwrap_or(value);
+    match digits {
+        [a, b'.', c] => a.is_ascii_digit() && c.is_ascii_digit(),
+        [a, b, b'.', c] => a.is_ascii_digit() && b.is_ascii_digit() && c.is_ascii_digit(),
+        _ => false,
+    }
+}
+
+/// The implementation picked by [`choose_implementation`] and why.
+pub struct AdaptiveChoice<R> {
+    pub implementation: Implementation<R>,
+    /// Slots of the station table, for implementations with a sized table.
+    pub table_size: Option<usize>,
+    pub reason: String,
+}
+
+impl<R> Display for AdaptiveChoice<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`", self.implementation.name)?;
+        if let Some(table_size) = self.table_size {
+            write!(f, " with {table_size} table slots")?;
+        }
+        write!(f, ": {}", self.reason)
+    }
+}
+
+/// Picks the fastest implementation that is correct for the profiled input:
+/// - [`naive_line_by_line`] when measurements are not in the challenge format,
+///   since all faster parsers rely on it. Measurements are still aggregated in
+///   tenths, so extra fractional digits are rounded.
+/// - [`parse_large_chunks_as_i64`], which compares whole names, when the sample
+///   already contains a fingerprint collision or there are too many stations
+///   for the largest fingerprint table.
+/// - [`parse_large_chunks_structural_table`] otherwise, with the smallest table
+///   that keeps the expected stations at a load factor of at most one half. It
+///   was the fastest table parser on every kernel when all of them were
+///   measured in one session, see the README.
+///
+/// Name lengths are only logged: the structural parser also beat the memchr
+/// loop at every mean name length from 8 to 80 bytes, so no length favours
+/// another parser.
+pub fn choose_implementation<R: Read + Seek>(profile: &InputProfile) -> AdaptiveChoice<R> {
+    if profile.format != RecordFormat::Challenge {
+        return AdaptiveChoice {
+            implementation: Implementation::new("naive_line_by_line", naive_line_by_line),
+            table_size: None,
+            reason: "measurements are not in the -99.9..99.9 one-decimal format, rounding to \
+                     tenths"
+                .to_owned(),
+        };
+    }
+    if profile.fingerprint_collision {
+        return AdaptiveChoice {
+            implementation: Implementation::new(
+                "parse_large_chunks_as_i64",
+                parse_large_chunks_as_i64,
+            ),
+            table_size: None,
+            reason: "station names share fingerprints".to_owned(),
+        };
+    }
+
+    let table_size = (2 * profile.estimated_stations)
+        .next_power_of_two()
+        .max(MIN_TABLE_SIZE);
+    if table_size > MAX_TABLE_SIZE {
+        return AdaptiveChoice {
+            implementation: Implementation::new(
+                "parse_large_chunks_as_i64",
+                parse_large_chunks_as_i64,
+            ),
+            table_size: None,
+            reason: format!(
+                "~{} stations do not fit the fingerprint table",
+                profile.estimated_stations
+            ),
+        };
+    }
+
+    let parse: ParseFn<R> = match table_size {
+        1024 => parse_large_chunks_structural_table_sized::<1024, R>,
+        2048 => parse_large_chunks_structural_table_sized::<2048, R>,
+        4096 => parse_large_chunks_structural_table_sized::<4096, R>,
+        8192 => parse_large_chunks_structural_table_sized::<8192, R>,
+        16384 => parse_large_chunks_structural_table_sized::<16384, R>,
+        32768 => parse_large_chunks_structural_table_sized::<32768, R>,
+        65536 => parse_large_chunks_structural_table_sized::<65536, R>,
+        _ => unreachable!("table size {table_size}"),
+    };
+    AdaptiveChoice {
+        implementation: Implementation::multiversioned(
+            "parse_large_chunks_structural_table",
+            parse,
+        ),
+        table_size: Some(table_size),
+        reason: format!(
+            "fastest for the challenge format, sized for ~{} stations",
+            profile.estimated_stations
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn profile(content: &[u8]) -> Result<InputProfile, String> {
+        profile_input(&mut Cursor::new(content), content.len() as u64)
+    }
+
+    #[test]
+    fn test_profile_sample() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let profile = profile(content).unwrap();
+        assert_eq!(RecordFormat::Challenge, profile.format);
+        assert_eq!(413, profile.distinct_stations);
+        assert_eq!(413, profile.estimated_stations);
+        assert!(!profile.fingerprint_collision);
+        assert_eq!(7, profile.name_lengths.median);
+        assert_eq!(16, profile.name_lengths.p99);
+        assert_eq!(26, profile.name_lengths.max);
+
+        let choice = choose_implementation::<Cursor<&[u8]>>(&profile);
+        assert_eq!(
+            "parse_large_chunks_structural_table",
+            choice.implementation.name
+        );
+        assert_eq!(Some(1024), choice.table_size);
+    }
+
+    #[test]
+    fn test_choose_implementation_falls_back() {
+        let decimal = profile(b"a;1.25\nb;-3\n").unwrap();
+        assert_eq!(RecordFormat::Decimal, decimal.format);
+        let choice = choose_implementation::<Cursor<&[u8]>>(&decimal);
+        assert_eq!("naive_line_by_line", choice.implementation.name);
+
+        // Same length, first and last eight bytes
+        let collision = profile(b"Station A Northwest;1.0\nStation B Northwest;2.0\n").unwrap();
+        assert!(collision.fingerprint_collision);
+        let choice = choose_implementation::<Cursor<&[u8]>>(&collision);
+        assert_eq!("parse_large_chunks_as_i64", choice.implementation.name);
+
+        let crlf = profile(b"a;1.0\r\nb;2.0\r\n").unwrap_err();
+        assert!(crlf.contains("CRLF"), "{crlf}");
+        assert!(profile(b"a 1.0\n").is_err());
+    }
+}
@@ -0,0 +1,324 @@
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use rustc_hash::FxHasher;
+
+const MAGIC: &[u8; 8] = b"BRCIDX1\n";
+
+/// Bytes hashed at the start and at the end of a file for its fingerprint.
+const FINGERPRINT_LEN: u64 = 64 * 1024;
+
+/// Default distance between the offsets of a [`ChunkIndex`].
+pub const DEFAULT_INDEX_STRIDE: u64 = 1024 * 1024;
+
+/// What a [`ChunkIndex`] knows about its file to tell whether it changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFingerprint {
+    pub len: u64,
+    /// Modification time in nanoseconds since the Unix epoch.
+    pub modified: u64,
+    /// Hash of the first and last [`FINGERPRINT_LEN`] bytes.
+    pub hash: u64,
+}
+
+impl FileFingerprint {
+    pub fn of(file: &mut File) -> Result<Self, String> {
+        let metadata = file.metadata().map_err(|e| e.to_string())?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_nanos() as u64);
+        let len = metadata.len();
+        let mut hasher = FxHasher::default();
+        let mut buf = vec![0; FINGERPRINT_LEN.min(len) as usize];
+        for start in [0, len - buf.len() as u64] {
+            file.seek(SeekFrom::Start(start))
+                .and_then(|_| file.read_exact(&mut buf))
+                .map_err(|e| e.to_string())?;
+            hasher.write(&buf);
+        }
+        Ok(Self {
+            len,
+            modified,
+            hash: hasher.finish(),
+        })
+    }
+}
+
+/// A byte range of whole lines of an indexed file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexedChunk {
+    pub start: u64,
+    pub end_inclusive: u64,
+    /// Lines in the chunk.
+    pub rows: u64,
+}
+
+/// Newline-aligned offsets of a file, about `stride` bytes apart, with the
+/// number of lines between them. Built once by scanning the file and kept in
+/// a sidecar file, it splits the file into any number of chunks without
+/// reading it, see [`ChunkIndex::chunks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkIndex {
+    pub fingerprint: FileFingerprint,
+    pub stride: u64,
+    /// Start of every block, the first one at 0; empty for an empty file.
+    offsets: Vec<u64>,
+    /// Lines in every block.
+    rows: Vec<u64>,
+}
+
+impl ChunkIndex {
+    /// Scans `file`, which must end with a newline.
+    pub fn build(file: &mut File, stride: u64) -> Result<Self, String> {
+        assert!(stride != 0, "Index stride of zero bytes");
+        let fingerprint = FileFingerprint::of(file)?;
+        let (mut offsets, mut rows) = (Vec::new(), Vec::new());
+        if fingerprint.len != 0 {
+            offsets.push(0);
+        }
+        let mut block_rows = 0;
+        let mut next_block = stride;
+        let mut position = 0;
+        let mut last_byte = b'\n';
+        let mut buf = vec![0; 1024 * 1024];
+        file.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+        loop {
+            let read = file.read(&mut buf).map_err(|e| e.to_string())?;
+            if read == 0 {
+                break;
+            }
+            for newline in memchr::memchr_iter(b'\n', &buf[..read]) {
+                block_rows += 1;
+                let line_start = position + newline as u64 + 1;
+                if line_start >= next_block && line_start < fingerprint.len {
+                    offsets.push(line_start);
+                    rows.push(block_rows);
+                    block_rows = 0;
+                    next_block = line_start + stride;
+                }
+            }
+            last_byte = buf[read - 1];
+            position += read as u64;
+        }
+        if position != fingerprint.len {
+            return Err("File changed while indexing".to_owned());
+        }
+        if last_byte != b'\n' {
+            return Err("File does not end with a newline".to_owned());
+        }
+        if fingerprint.len != 0 {
+            rows.push(block_rows);
+        }
+        Ok(Self {
+            fingerprint,
+            stride,
+            offsets,
+            rows,
+        })
+    }
+
+    /// Lines in the file.
+    pub fn rows(&self) -> u64 { self.rows.iter().sum() }
+
+    /// Number of blocks, the most chunks the index can split the file into.
+    pub fn blocks(&self) -> usize { self.offsets.len() }
+
+    /// Splits the file into `count` chunks of about equal size, fewer if it
+    /// has fewer blocks.
+    pub fn chunks(&self, count: usize) -> Vec<IndexedChunk> {
+        let len = self.fingerprint.len;
+        let blocks = self.blocks();
+        let count = count.min(blocks);
+        if count == 0 {
+            return Vec::new();
+        }
+        // First block of every chunk, and the end
+        let mut bounds = Vec::with_capacity(count + 1);
+        bounds.push(0);
+        for i in 1..count {
+            let target = (len as u128 * i as u128 / count as u128) as u64;
+            let mut block = self.offsets.partition_point(|&o| o < target);
+            if block == blocks || target - self.offsets[block - 1] < self.offsets[block] - target {
+                block -= 1;
+            }
+            // Leave at least one block for every chunk
+            bounds.push(block.clamp(bounds[i - 1] + 1, blocks - (count - i)));
+        }
+        bounds.push(blocks);
+        bounds
+            .windows(2)
+            .map(|w| IndexedChunk {
+                start: self.offsets[w[0]],
+                end_inclusive: self.offsets.get(w[1]).copied().unwrap_or(len) - 1,
+                rows: self.rows[w[0]..w[1]].iter().sum(),
+            })
+            .collect()
+    }
+
+    /// Checks that `file` is the one the index was built for.
+    pub fn validate(&self, file: &mut File) -> Result<(), String> {
+        let current = FileFingerprint::of(file)?;
+        if current.len != self.fingerprint.len {
+            Err(format!(
+                "indexed {} bytes, the file has {}",
+                self.fingerprint.len, current.len
+            ))
+        } else if current != self.fingerprint {
+            Err("the file was modified after indexing".to_owned())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Where the index of `input` is kept: `.NAME.brcidx` next to it, hidden
+    /// from directory inputs.
+    pub fn sidecar_path(input: &Path) -> PathBuf {
+        let name = input
+            .file_name()
+            .map_or_else(Default::default, |n| n.to_string_lossy());
+        input.with_file_name(format!(".{name}.brcidx"))
+    }
+
+    pub fn write_to(&self, path: &Path) -> Result<(), String> {
+        let mut bytes = Vec::with_capacity(48 + self.offsets.len() * 16);
+        bytes.extend_from_slice(MAGIC);
+        for value in [
+            self.fingerprint.len,
+            self.fingerprint.modified,
+            self.fingerprint.hash,
+            self.stride,
+            self.offsets.len() as u64,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for (offset, rows) in self.offsets.iter().zip(&self.rows) {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            bytes.extend_from_slice(&rows.to_le_bytes());
+        }
+        File::create(path)
+            .and_then(|mut file| file.write_all(&bytes))
+            .map_err(|e| format!("Unable to write chunk index {}: {e}", path.display()))
+    }
+
+    pub fn read_from(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("Unable to read chunk index {}: {e}", path.display()))?;
+        let invalid = || format!("{} is not a chunk index", path.display());
+        let (magic, rest) = bytes.split_at_checked(MAGIC.len()).ok_or_else(invalid)?;
+        if magic != MAGIC || rest.len() % 8 != 0 {
+            return Err(invalid());
+        }
+        let values: Vec<u64> = rest
+            .chunks_exact(8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        let [len, modified, hash, stride, blocks, ref entries @ ..] = values[..] else {
+            return Err(invalid());
+        };
+        if entries.len() as u64 != blocks * 2 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            fingerprint: FileFingerprint {
+                len,
+                modified,
+                hash,
+            },
+            stride,
+            offsets: entries.iter().step_by(2).copied().collect(),
+            rows: entries.iter().skip(1).step_by(2).copied().collect(),
+        })
+    }
+
+    /// The sidecar index of `input` if there is one, or an error if it cannot
+    /// be read or does not match the file any more.
+    pub fn load_for(input: &Path) -> Result<Option<Self>, String> {
+        let path = Self::sidecar_path(input);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let index = Self::read_from(&path)?;
+        let mut file =
+            File::open(input).map_err(|e| format!("Unable to open {}: {e}", input.display()))?;
+        index
+            .validate(&mut file)
+            .map_err(|e| format!("Stale chunk index {}: {e}", path.display()))?;
+        Ok(Some(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_index() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let dir = std::env::temp_dir().join(format!("brc-index-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("measurements.txt");
+        std::fs::write(&input, content).unwrap();
+
+        let index = ChunkIndex::build(&mut File::open(&input).unwrap(), 4096).unwrap();
+        let lines = content.iter().filter(|&&b| b == b'\n').count() as u64;
+        assert_eq!(lines, index.rows());
+        assert!(index.blocks() > 100);
+        for &offset in &index.offsets[1..] {
+            assert_eq!(b'\n', content[offset as usize - 1]);
+        }
+
+        for count in [1, 2, 3, 7, 64, index.blocks(), 10 * index.blocks()] {
+            let chunks = index.chunks(count);
+            assert_eq!(count.min(index.blocks()), chunks.len(), "{count} chunks");
+            let mut start = 0;
+            for chunk in &chunks {
+                assert_eq!(start, chunk.start);
+                assert_eq!(b'\n', content[chunk.end_inclusive as usize]);
+                let text = &content[chunk.start as usize..=chunk.end_inclusive as usize];
+                let rows = text.iter().filter(|&&b| b == b'\n').count() as u64;
+                assert_eq!(rows, chunk.rows);
+                start = chunk.end_inclusive + 1;
+            }
+            assert_eq!(content.len() as u64, start, "{count} chunks");
+            let largest = chunks.iter().map(|c| c.end_inclusive + 1 - c.start).max();
+            let ideal = content.len() as u64 / chunks.len() as u64;
+            assert!(
+                largest.unwrap() <= ideal + 2 * index.stride,
+                "{count} chunks"
+            );
+        }
+
+        let sidecar = ChunkIndex::sidecar_path(&input);
+        assert_eq!(dir.join(".measurements.txt.brcidx"), sidecar);
+        index.write_to(&sidecar).unwrap();
+        assert_eq!(Ok(Some(index)), ChunkIndex::load_for(&input));
+
+        // Same length, other content
+        let mut changed = content.to_vec();
+        changed[1] ^= 1;
+        std::fs::write(&input, &changed).unwrap();
+        assert!(
+            ChunkIndex::load_for(&input)
+                .unwrap_err()
+                .contains("modified")
+        );
+        changed.extend_from_slice(b"Oslo;1.0\n");
+        std::fs::write(&input, &changed).unwrap();
+        assert!(ChunkIndex::load_for(&input).unwrap_err().contains("bytes"));
+
+        std::fs::write(&sidecar, b"BRCIDX1\n\x01").unwrap();
+        assert!(ChunkIndex::load_for(&input).is_err());
+        std::fs::write(&input, b"Oslo;1.0").unwrap();
+        assert!(ChunkIndex::build(&mut File::open(&input).unwrap(), 4096).is_err());
+        std::fs::write(&input, b"").unwrap();
+        let empty = ChunkIndex::build(&mut File::open(&input).unwrap(), 4096).unwrap();
+        assert!(empty.chunks(4).is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
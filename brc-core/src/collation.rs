@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use icu_collator::{Collator, CollatorOptions};
+use icu_locid::Locale;
+use unicode_normalization::{IsNormalized, UnicodeNormalization, is_nfc_quick};
+
+use crate::{Accumulator, sort_result};
+
+/// How station names are normalized before stations are merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Normalization {
+    /// Names are compared as raw bytes.
+    #[default]
+    None,
+    /// Names are converted to Unicode Normalization Form C, so that e.g.
+    /// `Chișinău` spelled with a combining comma below and with the
+    /// precomposed `ș` is one station.
+    Nfc,
+}
+
+impl Normalization {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Normalization::None => "none",
+            Normalization::Nfc => "nfc",
+        }
+    }
+
+    /// `name` after normalization, or `None` if normalization leaves it
+    /// unchanged.
+    pub fn normalize(self, name: &str) -> Option<String> {
+        if self == Normalization::None || is_nfc_quick(name.chars()) == IsNormalized::Yes {
+            return None;
+        }
+        let normalized: String = name.nfc().collect();
+        (normalized != name).then_some(normalized)
+    }
+
+    /// Normalizes the names of already aggregated stations and merges the
+    /// stations that become equal, returning them unsorted. This is for
+    /// parsers that key stations by raw names; the table parsers normalize
+    /// names when a spelling is first inserted instead. Merging is exact for
+    /// order-independent states like [`StateI`](crate::StateI), but not for
+    /// states that depend on record order, like
+    /// [`Exceedances`](crate::Exceedances) runs.
+    pub fn apply<A: Accumulator>(self, result: Vec<(String, A)>) -> Vec<(String, A)> {
+        if result
+            .iter()
+            .all(|(name, _)| self.normalize(name).is_none())
+        {
+            return result;
+        }
+        let mut merged: HashMap<String, A> = HashMap::with_capacity(result.len());
+        for (name, state) in result {
+            let name = self.normalize(&name).unwrap_or(name);
+            merged
+                .entry(name)
+                .and_modify(|prev| prev.merge(&state))
+                .or_insert(state);
+        }
+        merged.into_iter().collect()
+    }
+}
+
+impl Display for Normalization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str(self.name()) }
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Normalization::None, Normalization::Nfc]
+            .into_iter()
+            .find(|n| n.name() == s)
+            .ok_or_else(|| {
+                format!("Unknown normalization `{s}`, expected one of [\"none\", \"nfc\"]")
+            })
+    }
+}
+
+/// Order of the stations in the output.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Collation {
+    /// By the UTF-8 bytes of the names, like [`sort_result`].
+    #[default]
+    Bytes,
+    /// By Unicode code points. UTF-8 preserves code point order and names are
+    /// always valid UTF-8, so this is exactly the order of
+    /// [`Collation::Bytes`]; it exists to name that guarantee. Java's
+    /// UTF-16 order differs from both for characters above U+FFFF.
+    CodePoint,
+    /// By the collation rules of a locale, e.g. `sv` sorts `Ö` after `Z` and
+    /// `de` next to `O`.
+    Locale(Locale),
+}
+
+impl Collation {
+    /// Sorts `all` by station name. Names the locale considers equal are
+    /// ordered by their bytes, so the order is total.
+    pub fn sort<S>(&self, all: &mut [(String, S)]) {
+        match self {
+            Collation::Bytes => sort_result(all),
+            Collation::CodePoint => all.sort_unstable_by(|a, b| a.0.chars().cmp(b.0.chars())),
+            Collation::Locale(locale) => {
+                let collator = collator(locale).unwrap_or_else(|e| panic!("{e}"));
+                all.sort_unstable_by(|a, b| {
+                    collator.compare(&a.0, &b.0).then_with(|| a.0.cmp(&b.0))
+                });
+            },
+        }
+    }
+}
+
+fn collator(locale: &Locale) -> Result<Collator, String> {
+    Collator::try_new(&locale.into(), CollatorOptions::new())
+        .map_err(|e| format!("No collation for locale `{locale}`: {e}"))
+}
+
+impl Display for Collation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Collation::Bytes => f.write_str("bytes"),
+            Collation::CodePoint => f.write_str("codepoint"),
+            Collation::Locale(locale) => write!(f, "locale:{locale}"),
+        }
+    }
+}
+
+impl FromStr for Collation {
+    type Err = String;
+
+    /// Parses `bytes`, `codepoint` or `locale:<BCP 47 tag>`, e.g. `locale:sv`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bytes" => Ok(Collation::Bytes),
+            "codepoint" => Ok(Collation::CodePoint),
+            _ => {
+                let tag = s.strip_prefix("locale:").ok_or_else(|| {
+                    format!(
+                        "Unknown collation `{s}`, expected one of [\"bytes\", \"codepoint\", \
+                         \"locale:<tag>\"]"
+                    )
+                })?;
+                let locale =
+                    Locale::from_str(tag).map_err(|e| format!("Invalid locale `{tag}`: {e}"))?;
+                collator(&locale)?;
+                Ok(Collation::Locale(locale))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StateI;
+
+    #[test]
+    fn test_nfc_merges_spellings() {
+        let precomposed = "Chi\u{219}in\u{103}u";
+        let decomposed = "Chis\u{326}ina\u{306}u";
+        let result = vec![
+            (precomposed.to_owned(), StateI::new(10)),
+            (decomposed.to_owned(), StateI::new(-20)),
+            ("Oslo".to_owned(), StateI::new(5)),
+        ];
+        assert_eq!(3, Normalization::None.apply(result.clone()).len());
+
+        let mut normalized = Normalization::Nfc.apply(result);
+        sort_result(&mut normalized);
+        assert_eq!(2, normalized.len());
+        assert_eq!(precomposed, normalized[0].0);
+        assert_eq!("-2.0/-0.5/1.0", normalized[0].1.to_string());
+    }
+
+    #[test]
+    fn test_collations() {
+        let sorted = |collation: &str| {
+            let mut all: Vec<(String, ())> =
+                ["Zürich", "Örebro", "Oslo", "Århus", "Zagreb", "Ängelholm"]
+                    .map(|name| (name.to_owned(), ()))
+                    .to_vec();
+            Collation::from_str(collation).unwrap().sort(&mut all);
+            all.into_iter().map(|(name, _)| name).collect::<Vec<_>>()
+        };
+        let bytes = ["Oslo", "Zagreb", "Zürich", "Ängelholm", "Århus", "Örebro"];
+        assert_eq!(bytes, sorted("bytes").as_slice());
+        assert_eq!(bytes, sorted("codepoint").as_slice());
+        assert_eq!(
+            ["Oslo", "Zagreb", "Zürich", "Århus", "Ängelholm", "Örebro"],
+            sorted("locale:sv").as_slice()
+        );
+        assert_eq!(
+            ["Ängelholm", "Århus", "Örebro", "Oslo", "Zagreb", "Zürich"],
+            sorted("locale:de").as_slice()
+        );
+
+        assert_eq!(
+            "locale:sv",
+            Collation::from_str("locale:sv").unwrap().to_string()
+        );
+        assert!(Collation::from_str("utf16").is_err());
+        assert!(Collation::from_str("locale:not a tag").is_err());
+    }
+}
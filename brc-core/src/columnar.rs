@@ -0,0 +1,514 @@
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use rustc_hash::FxHashMap;
+
+use crate::records::{RECORD_PADDING, Records};
+use crate::{Cancelled, StateI, sort_result};
+
+const MAGIC: &[u8; 8] = b"BRCCOL1\n";
+
+/// Magic, records per block and a reserved word.
+const HEADER_LEN: u64 = 16;
+
+/// Records, min, max and sum of the block.
+const BLOCK_HEADER_LEN: u64 = 16;
+
+/// Records, blocks and dictionary offset, followed by the magic again.
+const FOOTER_LEN: u64 = 32;
+
+/// Records per block of [`ColumnarWriter::new`] in `brc-app transcode`.
+pub const DEFAULT_BLOCK_RECORDS: u32 = 64 * 1024;
+
+/// At most this many stations fit the `u16` station ids.
+const MAX_STATIONS: usize = u16::MAX as usize + 1;
+
+/// Summary of a block, stored in front of its records, so that global
+/// statistics or the blocks worth decoding are known without reading them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockStats {
+    pub records: u32,
+    pub min: i16,
+    pub max: i16,
+    pub sum: i64,
+}
+
+impl BlockStats {
+    fn to_bytes(self) -> [u8; BLOCK_HEADER_LEN as usize] {
+        let mut bytes = [0; BLOCK_HEADER_LEN as usize];
+        bytes[0..4].copy_from_slice(&self.records.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.min.to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.max.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.sum.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            records: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            min: i16::from_le_bytes(bytes[4..6].try_into().unwrap()),
+            max: i16::from_le_bytes(bytes[6..8].try_into().unwrap()),
+            sum: i64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+        }
+    }
+}
+
+/// Writes measurements in the binary columnar format read by
+/// [`parse_columnar`]:
+///
+/// - a 16-byte header: magic and the number of records per block,
+/// - blocks of that many records, the last one shorter: [`BlockStats`], then
+///   the `u16` station ids of all records, then their `i16` tenths,
+/// - the station dictionary: the number of stations as `u32`, then every name
+///   as a `u16` length and UTF-8 bytes, in the order of their ids,
+/// - a 32-byte footer: records, blocks, dictionary offset and the magic.
+///
+/// All integers are little endian. As blocks have a fixed size, the offset of
+/// any block follows from its index. The dictionary is written last so that
+/// the text can be transcoded in a single pass.
+pub struct ColumnarWriter<W: Write> {
+    out: W,
+    block_records: u32,
+    ids: Vec<u16>,
+    tenths: Vec<i16>,
+    stations: FxHashMap<Box<[u8]>, u16>,
+    names: Vec<String>,
+    records: u64,
+    blocks: u64,
+    bytes: u64,
+}
+
+/// What [`ColumnarWriter::finish`] wrote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TranscodeSummary {
+    pub records: u64,
+    pub blocks: u64,
+    pub stations: usize,
+    pub bytes: u64,
+}
+
+impl<W: Write> ColumnarWriter<W> {
+    pub fn new(mut out: W, block_records: u32) -> Result<Self, String> {
+        assert!(block_records != 0, "Blocks of zero records");
+        let mut header = [0; HEADER_LEN as usize];
+        header[..8].copy_from_slice(MAGIC);
+        header[8..12].copy_from_slice(&block_records.to_le_bytes());
+        out.write_all(&header).map_err(|e| e.to_string())?;
+        Ok(Self {
+            out,
+            block_records,
+            ids: Vec::with_capacity(block_records as usize),
+            tenths: Vec::with_capacity(block_records as usize),
+            stations: FxHashMap::default(),
+            names: Vec::new(),
+            records: 0,
+            blocks: 0,
+            bytes: HEADER_LEN,
+        })
+    }
+
+    pub fn push(&mut self, name: &[u8], tenths: i16) -> Result<(), String> {
+        let id = match self.stations.get(name) {
+            Some(&id) => id,
+            None => {
+                if self.names.len() == MAX_STATIONS {
+                    return Err(format!("More than {MAX_STATIONS} stations"));
+                }
+                let name_str = std::str::from_utf8(name)
+                    .map_err(|e| format!("Station name is not UTF-8: {e}"))?;
+                let id = self.names.len() as u16;
+                self.names.push(name_str.to_owned());
+                self.stations.insert(name.into(), id);
+                id
+            },
+        };
+        self.ids.push(id);
+        self.tenths.push(tenths);
+        if self.ids.len() == self.block_records as usize {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    /// Transcodes the `name;temperature\n` records of `input`, which must end
+    /// with a newline, parsing them with [`Records`].
+    pub fn push_text<R: Read>(&mut self, mut input: R) -> Result<(), String> {
+        const BUFFER_LEN: usize = 16 * 1024 * 1024;
+        let mut buffer = vec![0; BUFFER_LEN + RECORD_PADDING];
+        let mut filled = 0;
+        loop {
+            let read = input
+                .read(&mut buffer[filled..BUFFER_LEN])
+                .map_err(|e| e.to_string())?;
+            filled += read;
+            let Some(last_newline) = memchr::memrchr(b'\n', &buffer[..filled]) else {
+                if read == 0 && filled == 0 {
+                    return Ok(());
+                }
+                if read == 0 {
+                    return Err("Input does not end with a newline".to_owned());
+                }
+                if filled == BUFFER_LEN {
+                    return Err(format!("Line longer than {BUFFER_LEN} bytes"));
+                }
+                continue;
+            };
+            let len = last_newline + 1;
+            buffer[filled..filled + RECORD_PADDING].fill(0);
+            for (name, tenths, _) in Records::new(&buffer, len) {
+                self.push(name, tenths)?;
+            }
+            buffer.copy_within(len..filled, 0);
+            filled -= len;
+            if read == 0 && filled != 0 {
+                return Err("Input does not end with a newline".to_owned());
+            }
+        }
+    }
+
+    fn flush_block(&mut self) -> Result<(), String> {
+        if self.ids.is_empty() {
+            return Ok(());
+        }
+        let stats = BlockStats {
+            records: self.ids.len() as u32,
+            min: *self.tenths.iter().min().unwrap(),
+            max: *self.tenths.iter().max().unwrap(),
+            sum: self.tenths.iter().map(|&t| t as i64).sum(),
+        };
+        let mut block = Vec::with_capacity(BLOCK_HEADER_LEN as usize + self.ids.len() * 4);
+        block.extend_from_slice(&stats.to_bytes());
+        block.extend(self.ids.iter().flat_map(|id| id.to_le_bytes()));
+        block.extend(self.tenths.iter().flat_map(|t| t.to_le_bytes()));
+        self.out.write_all(&block).map_err(|e| e.to_string())?;
+        self.records += self.ids.len() as u64;
+        self.blocks += 1;
+        self.bytes += block.len() as u64;
+        self.ids.clear();
+        self.tenths.clear();
+        Ok(())
+    }
+
+    /// Writes the last block, the dictionary and the footer.
+    pub fn finish(mut self) -> Result<TranscodeSummary, String> {
+        self.flush_block()?;
+        let dictionary_offset = self.bytes;
+        let mut tail = Vec::new();
+        tail.extend_from_slice(&(self.names.len() as u32).to_le_bytes());
+        for name in &self.names {
+            tail.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            tail.extend_from_slice(name.as_bytes());
+        }
+        for value in [self.records, self.blocks, dictionary_offset] {
+            tail.extend_from_slice(&value.to_le_bytes());
+        }
+        tail.extend_from_slice(MAGIC);
+        self.out.write_all(&tail).map_err(|e| e.to_string())?;
+        self.out.flush().map_err(|e| e.to_string())?;
+        Ok(TranscodeSummary {
+            records: self.records,
+            blocks: self.blocks,
+            stations: self.names.len(),
+            bytes: self.bytes + tail.len() as u64,
+        })
+    }
+}
+
+/// The layout and station dictionary of a file written by
+/// [`ColumnarWriter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnarFile {
+    block_records: u32,
+    records: u64,
+    blocks: u64,
+    stations: Vec<String>,
+}
+
+impl ColumnarFile {
+    /// Reads the header, footer and dictionary of `rdr`.
+    pub fn open<R: Read + Seek>(rdr: &mut R) -> Result<Self, String> {
+        Self::read(rdr).map_err(|e| e.to_string())
+    }
+
+    /// [`ColumnarFile::open`] keeping the read errors, so that
+    /// [`parse_columnar`] can tell a [`Cancelled`] run from a bad file.
+    fn read<R: Read + Seek>(rdr: &mut R) -> std::io::Result<Self> {
+        let invalid = |what: &str| {
+            std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Not a columnar measurements file: {what}"),
+            )
+        };
+        let len = rdr.seek(SeekFrom::End(0))?;
+        if len < HEADER_LEN + FOOTER_LEN {
+            return Err(invalid("too short"));
+        }
+        let mut header = [0; HEADER_LEN as usize];
+        rdr.seek(SeekFrom::Start(0))
+            .and_then(|_| rdr.read_exact(&mut header))?;
+        let mut footer = [0; FOOTER_LEN as usize];
+        rdr.seek(SeekFrom::Start(len - FOOTER_LEN))
+            .and_then(|_| rdr.read_exact(&mut footer))?;
+        if &header[..8] != MAGIC || &footer[24..] != MAGIC {
+            return Err(invalid("bad magic"));
+        }
+        let word = |i: usize| u64::from_le_bytes(footer[i * 8..i * 8 + 8].try_into().unwrap());
+        let block_records = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        let (records, blocks, dictionary_offset) = (word(0), word(1), word(2));
+        if dictionary_offset > len - FOOTER_LEN
+            || blocks != records.div_ceil(block_records.max(1) as u64)
+            || dictionary_offset != HEADER_LEN + BLOCK_HEADER_LEN * blocks + 4 * records
+        {
+            return Err(invalid("inconsistent footer"));
+        }
+
+        let mut dictionary = vec![0; (len - FOOTER_LEN - dictionary_offset) as usize];
+        rdr.seek(SeekFrom::Start(dictionary_offset))
+            .and_then(|_| rdr.read_exact(&mut dictionary))?;
+        let truncated = || invalid("truncated dictionary");
+        let count = u32::from_le_bytes(
+            dictionary
+                .get(..4)
+                .ok_or_else(truncated)?
+                .try_into()
+                .unwrap(),
+        );
+        let mut stations = Vec::with_capacity(count as usize);
+        let mut rest = &dictionary[4..];
+        for _ in 0..count {
+            let name_len =
+                u16::from_le_bytes(rest.get(..2).ok_or_else(truncated)?.try_into().unwrap());
+            let name = rest.get(2..2 + name_len as usize).ok_or_else(truncated)?;
+            stations
+                .push(String::from_utf8(name.to_vec()).map_err(|_| invalid("bad station name"))?);
+            rest = &rest[2 + name_len as usize..];
+        }
+        Ok(Self {
+            block_records,
+            records,
+            blocks,
+            stations,
+        })
+    }
+
+    pub fn records(&self) -> u64 { self.records }
+
+    pub fn blocks(&self) -> u64 { self.blocks }
+
+    /// Station names, indexed by station id.
+    pub fn stations(&self) -> &[String] { &self.stations }
+
+    fn block_len(&self) -> u64 { BLOCK_HEADER_LEN + 4 * self.block_records as u64 }
+
+    /// Offset of block `i` in the file.
+    pub fn block_offset(&self, i: u64) -> u64 { HEADER_LEN + i * self.block_len() }
+
+    /// The [`BlockStats`] of every block, read from the block headers only.
+    pub fn block_stats<R: Read + Seek>(&self, rdr: &mut R) -> Result<Vec<BlockStats>, String> {
+        let mut header = [0; BLOCK_HEADER_LEN as usize];
+        (0..self.blocks)
+            .map(|i| {
+                rdr.seek(SeekFrom::Start(self.block_offset(i)))
+                    .and_then(|_| rdr.read_exact(&mut header))
+                    .map_err(|e| e.to_string())?;
+                Ok(BlockStats::from_bytes(&header))
+            })
+            .collect()
+    }
+}
+
+/// Whether `path` starts like a file written by [`ColumnarWriter`].
+pub fn is_columnar(path: &Path) -> bool {
+    let mut magic = [0; MAGIC.len()];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok_and(|_| &magic == MAGIC)
+}
+
+/// Aggregates the blocks of a file written by [`ColumnarWriter`] that start
+/// within `start..=end_inclusive`, so any split of the file into consecutive
+/// byte ranges aggregates every block once. Records are fixed-width, so the
+/// loop is two loads and a [`StateI::update`] indexed by station id.
+pub fn parse_columnar<R: Read + Seek>(
+    mut rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+) -> Vec<(String, StateI)> {
+    let file = match ColumnarFile::read(&mut rdr) {
+        Ok(file) => file,
+        Err(e) if Cancelled::is(&e) => return Vec::new(),
+        Err(e) => panic!("{e}"),
+    };
+    let block_len = file.block_len();
+    let first_block = start.saturating_sub(HEADER_LEN).div_ceil(block_len);
+    let end_block = if end_inclusive < HEADER_LEN {
+        0
+    } else {
+        ((end_inclusive - HEADER_LEN) / block_len + 1).min(file.blocks)
+    };
+
+    let mut states = vec![StateI::default(); file.stations.len()];
+    let mut block = vec![0; block_len as usize];
+    if first_block < end_block {
+        rdr.seek(SeekFrom::Start(file.block_offset(first_block)))
+            .unwrap();
+    }
+    // Stops early only when the run is cancelled, see `ProgressTracker::run`
+    let mut read_block = |buf: &mut [u8]| match rdr.read_exact(buf) {
+        Ok(()) => true,
+        Err(e) if Cancelled::is(&e) => false,
+        Err(e) => panic!("Truncated block: {e}"),
+    };
+    for _ in first_block..end_block {
+        if !read_block(&mut block[..BLOCK_HEADER_LEN as usize]) {
+            break;
+        }
+        let records = BlockStats::from_bytes(&block).records as usize;
+        let columns = &mut block[BLOCK_HEADER_LEN as usize..][..records * 4];
+        if !read_block(columns) {
+            break;
+        }
+        let (ids, tenths) = columns.split_at(records * 2);
+        for (id, value) in ids.chunks_exact(2).zip(tenths.chunks_exact(2)) {
+            let id = u16::from_le_bytes([id[0], id[1]]) as usize;
+            states[id].update(i16::from_le_bytes([value[0], value[1]]));
+        }
+    }
+
+    let mut all: Vec<(String, StateI)> = states
+        .into_iter()
+        .zip(&file.stations)
+        .filter(|(state, _)| state.count != 0)
+        .map(|(state, name)| (name.clone(), state))
+        .collect();
+    if should_sort {
+        sort_result(&mut all);
+    }
+    all
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::ops::Range;
+
+    use super::*;
+    use crate::parse_large_chunks_memchr_table;
+
+    fn transcode(content: &[u8], block_records: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut writer = ColumnarWriter::new(&mut bytes, block_records).unwrap();
+        writer.push_text(content).unwrap();
+        let summary = writer.finish().unwrap();
+        assert_eq!(summary.bytes, bytes.len() as u64);
+        bytes
+    }
+
+    #[test]
+    fn test_columnar_round_trip() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let reference = parse_large_chunks_memchr_table(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+        );
+        let lines = content.iter().filter(|&&b| b == b'\n').count() as u64;
+
+        for block_records in [1000, DEFAULT_BLOCK_RECORDS] {
+            let bytes = transcode(content, block_records);
+            let file = ColumnarFile::open(&mut Cursor::new(&bytes)).unwrap();
+            assert_eq!(lines, file.records());
+            assert_eq!(reference.len(), file.stations().len());
+
+            // Any byte ranges, not only block-aligned ones
+            for chunks in [1, 2, 3, 7, 64] {
+                let mut merged: FxHashMap<String, StateI> = FxHashMap::default();
+                let mut start = 0;
+                for i in 1..=chunks {
+                    let end = bytes.len() as u64 * i / chunks;
+                    let rdr = BufReader::new(Cursor::new(&bytes));
+                    for (name, state) in parse_columnar(rdr, start, end - 1, false) {
+                        merged
+                            .entry(name)
+                            .and_modify(|prev| prev.merge(&state))
+                            .or_insert(state);
+                    }
+                    start = end;
+                }
+                let mut all: Vec<(String, StateI)> = merged.into_iter().collect();
+                sort_result(&mut all);
+                assert_eq!(
+                    reference, all,
+                    "{block_records} records per block, {chunks} chunks"
+                );
+            }
+
+            // Block stats add up to the global aggregate
+            let stats = file.block_stats(&mut Cursor::new(&bytes)).unwrap();
+            assert_eq!(file.blocks(), stats.len() as u64);
+            assert_eq!(lines, stats.iter().map(|s| s.records as u64).sum::<u64>());
+            let sum: i64 = reference.iter().map(|(_, s)| s.sum).sum();
+            assert_eq!(sum, stats.iter().map(|s| s.sum).sum::<i64>());
+            let min = reference.iter().map(|(_, s)| s.min).min().unwrap();
+            assert_eq!(min, stats.iter().map(|s| s.min).min().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_columnar_errors() {
+        let bytes = transcode(b"Oslo;1.0\nLima;-2.5\n", 4);
+        assert!(ColumnarFile::open(&mut Cursor::new(&bytes[..bytes.len() - 1])).is_err());
+        assert!(ColumnarFile::open(&mut Cursor::new(b"Oslo;1.0\n")).is_err());
+        let mut corrupt = bytes.clone();
+        corrupt[bytes.len() - 32] ^= 1;
+        assert!(ColumnarFile::open(&mut Cursor::new(&corrupt)).is_err());
+
+        let mut writer = ColumnarWriter::new(Vec::new(), 4).unwrap();
+        assert!(writer.push_text(&b"Oslo;1.0\nLima;-2.5"[..]).is_err());
+        let empty = transcode(b"", 4);
+        let rdr = BufReader::new(Cursor::new(&empty));
+        assert!(parse_columnar(rdr, 0, empty.len() as u64 - 1, true).is_empty());
+    }
+
+    /// Reads as the end of the file within `cut`, like a block cut short
+    /// under a reader.
+    struct CutBlock<'a> {
+        inner: Cursor<&'a [u8]>,
+        cut: Range<u64>,
+    }
+
+    impl Read for CutBlock<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let position = self.inner.position();
+            if self.cut.contains(&position) {
+                return Ok(0);
+            }
+            let len = if position < self.cut.start {
+                buf.len().min((self.cut.start - position) as usize)
+            } else {
+                buf.len()
+            };
+            self.inner.read(&mut buf[..len])
+        }
+    }
+
+    impl Seek for CutBlock<'_> {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> { self.inner.seek(pos) }
+    }
+
+    #[test]
+    #[should_panic(expected = "Truncated block")]
+    fn test_columnar_truncated_block() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let bytes = transcode(content, 100);
+        let file = ColumnarFile::open(&mut Cursor::new(&bytes)).unwrap();
+        let rdr = CutBlock {
+            inner: Cursor::new(&bytes),
+            cut: file.block_offset(1) + 10..file.block_offset(2),
+        };
+        parse_columnar(BufReader::new(rdr), 0, bytes.len() as u64 - 1, false);
+    }
+}
@@ -0,0 +1,259 @@
+use std::cmp::Ordering;
+
+use crate::fingerprint_table::FingerprintTable;
+use crate::{Normalization, StateI};
+
+/// One measurement and the byte offset of its record in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reading {
+    pub tenths: i16,
+    pub offset: u64,
+}
+
+/// Hotter first; of equal readings the earlier one in the file.
+fn hotter(a: &Reading, b: &Reading) -> Ordering {
+    b.tenths.cmp(&a.tenths).then(a.offset.cmp(&b.offset))
+}
+
+/// Colder first; of equal readings the earlier one in the file.
+fn colder(a: &Reading, b: &Reading) -> Ordering {
+    a.tenths.cmp(&b.tenths).then(a.offset.cmp(&b.offset))
+}
+
+/// Inserts `reading` into `top`, sorted by `order`, keeping at most `k`.
+fn insert(
+    top: &mut Vec<Reading>,
+    k: usize,
+    reading: Reading,
+    order: fn(&Reading, &Reading) -> Ordering,
+) {
+    let idx = top.partition_point(|r| order(r, &reading) == Ordering::Less);
+    if idx < k {
+        top.truncate(k - 1);
+        top.insert(idx, reading);
+    }
+}
+
+/// The `k` hottest and coldest readings, ties broken by file offset so that
+/// the result does not depend on how the file was split into chunks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extremes {
+    k: usize,
+    /// Hottest first.
+    pub hottest: Vec<Reading>,
+    /// Coldest first.
+    pub coldest: Vec<Reading>,
+    /// Readings must be hotter than this to enter `hottest`.
+    hot_floor: i16,
+    /// Readings must be colder than this to enter `coldest`.
+    cold_ceiling: i16,
+}
+
+impl Extremes {
+    pub fn new(k: usize) -> Self {
+        assert!(k != 0, "Top-k of zero readings");
+        Self {
+            k,
+            hottest: Vec::new(),
+            coldest: Vec::new(),
+            hot_floor: i16::MIN,
+            cold_ceiling: i16::MAX,
+        }
+    }
+
+    pub fn k(&self) -> usize { self.k }
+
+    /// Adds a reading. Its offset must be greater than those of all readings
+    /// added or merged before, so that it never replaces an equal one; merges
+    /// have no such restriction.
+    #[inline(always)]
+    pub fn update(&mut self, tenths: i16, offset: u64) {
+        if tenths > self.hot_floor {
+            insert(
+                &mut self.hottest,
+                self.k,
+                Reading { tenths, offset },
+                hotter,
+            );
+            if self.hottest.len() == self.k {
+                self.hot_floor = self.hottest[self.k - 1].tenths;
+            }
+        }
+        if tenths < self.cold_ceiling {
+            insert(
+                &mut self.coldest,
+                self.k,
+                Reading { tenths, offset },
+                colder,
+            );
+            if self.coldest.len() == self.k {
+                self.cold_ceiling = self.coldest[self.k - 1].tenths;
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        assert_eq!(self.k, other.k, "Merging top-k of different k");
+        for &reading in &other.hottest {
+            insert(&mut self.hottest, self.k, reading, hotter);
+        }
+        for &reading in &other.coldest {
+            insert(&mut self.coldest, self.k, reading, colder);
+        }
+        if self.hottest.len() == self.k {
+            self.hot_floor = self.hottest[self.k - 1].tenths;
+        }
+        if self.coldest.len() == self.k {
+            self.cold_ceiling = self.coldest[self.k - 1].tenths;
+        }
+    }
+}
+
+/// [`StateI`] extended with the station's [`Extremes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtremeState {
+    pub state: StateI,
+    pub extremes: Extremes,
+}
+
+impl ExtremeState {
+    pub fn merge(&mut self, other: &Self) {
+        self.state.merge(&other.state);
+        self.extremes.merge(&other.extremes);
+    }
+}
+
+/// The `k` hottest and coldest readings of all stations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobalExtremes<'a> {
+    /// Hottest first.
+    pub hottest: Vec<(&'a str, Reading)>,
+    /// Coldest first.
+    pub coldest: Vec<(&'a str, Reading)>,
+}
+
+impl<'a> GlobalExtremes<'a> {
+    /// Picks the `k` extremes of the stations in `all` from their own top-k,
+    /// which hold every global one.
+    pub fn new(all: &'a [(String, ExtremeState)]) -> Self {
+        let top = |pick: fn(&Extremes) -> &Vec<Reading>,
+                   order: fn(&Reading, &Reading) -> Ordering| {
+            let mut readings: Vec<(&str, Reading)> = all
+                .iter()
+                .flat_map(|(name, state)| {
+                    pick(&state.extremes)
+                        .iter()
+                        .map(move |&reading| (name.as_str(), reading))
+                })
+                .collect();
+            readings.sort_unstable_by(|a, b| order(&a.1, &b.1));
+            readings.truncate(all.first().map_or(0, |(_, state)| state.extremes.k));
+            readings
+        };
+        Self {
+            hottest: top(|e| &e.hottest, hotter),
+            coldest: top(|e| &e.coldest, colder),
+        }
+    }
+}
+
+/// A [`FingerprintTable`] with the [`Extremes`] of every slot kept next to it,
+/// updated from the same parsed value.
+pub(crate) struct ExtremesTable<const SIZE: usize> {
+    table: FingerprintTable<SIZE>,
+    extremes: Vec<Extremes>,
+}
+
+impl<const SIZE: usize> ExtremesTable<SIZE> {
+    pub(crate) fn new(k: usize, normalization: Normalization) -> Self {
+        Self {
+            table: FingerprintTable::with_normalization(normalization),
+            extremes: vec![Extremes::new(k); SIZE],
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn update(&mut self, name: &[u8], padded_name: &[u8], value: i16, offset: u64) {
+        let idx = self.table.find_or_insert(name, padded_name);
+        self.table.update_slot(idx, value);
+        self.extremes[idx].update(value, offset);
+    }
+
+    pub(crate) fn into_result(mut self) -> Vec<(String, ExtremeState)> {
+        self.table
+            .into_result_with_slots()
+            .into_iter()
+            .map(|(idx, name, state)| {
+                let extremes = std::mem::replace(&mut self.extremes[idx], Extremes::new(1));
+                (name, ExtremeState { state, extremes })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extremes_keep_earliest_ties() {
+        let values = [5, 9, 1, 9, -3, 7, -3, 9, 1];
+        let fill = |values: &[i16], first_offset: u64| {
+            let mut extremes = Extremes::new(3);
+            for (i, &v) in values.iter().enumerate() {
+                extremes.update(v, first_offset + i as u64 * 10);
+            }
+            extremes
+        };
+        let whole = fill(&values, 0);
+        let offsets =
+            |top: &[Reading]| top.iter().map(|r| (r.tenths, r.offset)).collect::<Vec<_>>();
+        assert_eq!(
+            [(9, 10), (9, 30), (9, 70)],
+            offsets(&whole.hottest).as_slice()
+        );
+        assert_eq!(
+            [(-3, 40), (-3, 60), (1, 20)],
+            offsets(&whole.coldest).as_slice()
+        );
+
+        for split in 1..values.len() {
+            // Later chunks may finish first, merge them in any order
+            let mut merged = fill(&values[split..], split as u64 * 10);
+            merged.merge(&fill(&values[..split], 0));
+            assert_eq!(whole.hottest, merged.hottest, "split at {split}");
+            assert_eq!(whole.coldest, merged.coldest, "split at {split}");
+        }
+    }
+
+    #[test]
+    fn test_global_extremes() {
+        let state = |values: &[(i16, u64)]| {
+            let mut extremes = Extremes::new(2);
+            let mut state = StateI::default();
+            for &(v, offset) in values {
+                extremes.update(v, offset);
+                state.update(v);
+            }
+            ExtremeState { state, extremes }
+        };
+        let all = vec![
+            ("Lima".to_owned(), state(&[(250, 0), (300, 20), (-10, 40)])),
+            ("Oslo".to_owned(), state(&[(300, 10), (-50, 30), (-50, 50)])),
+        ];
+        let global = GlobalExtremes::new(&all);
+        let top = |top: &[(&str, Reading)]| {
+            top.iter()
+                .map(|(n, r)| (n.to_string(), r.tenths, r.offset))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            [("Oslo".to_owned(), 300, 10), ("Lima".to_owned(), 300, 20)],
+            top(&global.hottest).as_slice()
+        );
+        assert_eq!(
+            [("Oslo".to_owned(), -50, 30), ("Oslo".to_owned(), -50, 50)],
+            top(&global.coldest).as_slice()
+        );
+    }
+}
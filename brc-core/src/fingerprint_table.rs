@@ -1,10 +1,13 @@
+use std::simd::Simd;
 use std::simd::cmp::SimdOrd;
-use std::simd::{Simd, i64x8};
 
-use crate::{StateF, StateI};
+use crate::{Accumulator, Normalization, StateI};
 
+/// The first and the last eight bytes of `name`, zero-extended for names
+/// shorter than eight bytes, read from `padded_name`, which starts with `name`
+/// and holds at least eight bytes.
 #[inline(always)]
-fn fingerprint(name: &[u8], padded_name: &[u8]) -> u64 {
+pub(crate) fn name_words(name: &[u8], padded_name: &[u8]) -> (u64, u64) {
     const MASK: [u64; 9] = [
         0,
         0xff,
@@ -25,64 +28,105 @@ fn fingerprint(name: &[u8], padded_name: &[u8]) -> u64 {
             .try_into()
             .unwrap(),
     ) & mask;
-    first ^ last.rotate_left(29) ^ (len as u64).wrapping_mul(0x9e3779b97f4a7c15)
+    (first, last)
 }
 
-struct FingerprintSlot {
-    hash: u64,
-    state: StateI,
-    name_id: u16,
+#[inline(always)]
+pub(crate) fn fingerprint(name: &[u8], padded_name: &[u8]) -> u64 {
+    let (first, last) = name_words(name, padded_name);
+    first ^ last.rotate_left(29) ^ (name.len() as u64).wrapping_mul(0x9e3779b97f4a7c15)
 }
 
-impl Default for FingerprintSlot {
-    fn default() -> Self {
-        Self {
-            hash: 0,
-            state: StateI::default(),
-            name_id: 0,
-        }
-    }
+/// The fingerprint of `name` as stored in table slots: zero is reserved as the
+/// empty-slot marker, without collapsing fingerprints that differ only in their
+/// low bit.
+#[inline(always)]
+pub(crate) fn slot_hash(name: &[u8], padded_name: &[u8]) -> u64 {
+    let fingerprint = fingerprint(name, padded_name);
+    if fingerprint == 0 { 1 } else { fingerprint }
+}
+
+/// Keeps one slot free so that probing for an absent station terminates, and
+/// fails loudly instead when the input has more stations than the table can
+/// hold.
+#[inline(always)]
+fn assert_has_free_slot<const SIZE: usize>(stations: usize) {
+    assert!(
+        stations + 1 < SIZE && stations < u16::MAX as usize,
+        "Fingerprint table of {SIZE} slots is full"
+    );
+}
+
+#[derive(Default)]
+struct FingerprintSlot<A> {
+    hash: u64,
+    state: A,
+    name_id: u16,
+    /// Slot holding the state of this name: the slot itself, or for a name
+    /// that is not normalized, the slot of its normalized form.
+    target: u32,
 }
 
 /// A compact table for the challenge's small station cardinality. Hash equality
 /// is treated as key equality, deliberately trading general-purpose collision
 /// handling for avoiding one variable-length name comparison on every record.
-pub(crate) struct FingerprintTable<const SIZE: usize> {
-    slots: Vec<FingerprintSlot>,
+pub(crate) struct FingerprintTable<const SIZE: usize, A = StateI> {
+    slots: Vec<FingerprintSlot<A>>,
     names: Vec<String>,
+    normalization: Normalization,
+    /// Slots that redirect to the slot of their normalized name.
+    aliases: usize,
 }
 
-impl<const SIZE: usize> FingerprintTable<SIZE> {
-    pub(crate) fn new() -> Self {
+impl<const SIZE: usize, A: Accumulator> FingerprintTable<SIZE, A> {
+    pub(crate) fn new() -> Self { Self::with_normalization(Normalization::None) }
+
+    /// A table that keys stations by their name after `normalization`. The
+    /// first record of a spelling that normalization changes inserts the
+    /// normalized name and an alias slot pointing to it, so later records of
+    /// that spelling cost the same single probe as any other.
+    pub(crate) fn with_normalization(normalization: Normalization) -> Self {
         assert!(SIZE.is_power_of_two());
         Self {
             slots: (0..SIZE).map(|_| FingerprintSlot::default()).collect(),
             names: Vec::with_capacity(512),
+            normalization,
+            aliases: 0,
         }
     }
 
+    pub(crate) fn normalization(&self) -> Normalization { self.normalization }
+
+    /// Name of the station in `slot_idx`, as returned by
+    /// [`FingerprintTable::find_or_insert`].
+    pub(crate) fn name(&self, slot_idx: usize) -> &str {
+        &self.names[self.slots[slot_idx].name_id as usize]
+    }
+
     #[inline(always)]
     pub(crate) fn insert_or_update(&mut self, name: &[u8], padded_name: &[u8], value: i16) {
-        let raw_fingerprint = fingerprint(name, padded_name);
-        let hash = if raw_fingerprint == 0 {
-            1
-        } else {
-            raw_fingerprint
-        };
+        let hash = slot_hash(name, padded_name);
         let mut idx = (hash ^ (hash >> 32)) as usize & (SIZE - 1);
 
         loop {
             let slot = &mut self.slots[idx];
             if slot.hash == hash {
-                slot.state.update(value);
+                if slot.target as usize == idx {
+                    slot.state.update(value);
+                } else {
+                    let target = slot.target as usize;
+                    self.slots[target].state.update(value);
+                }
                 return;
             }
             if slot.hash == 0 {
-                slot.hash = hash;
-                slot.state = StateI::new(value);
-                self.names
-                    .push(std::str::from_utf8(name).unwrap().to_owned());
-                slot.name_id = (self.names.len() - 1) as u16;
+                let (target, new) = self.insert(name, hash, idx);
+                let state = &mut self.slots[target].state;
+                if new {
+                    *state = A::new(value);
+                } else {
+                    state.update(value);
+                }
                 return;
             }
             idx = (idx + 1) & (SIZE - 1);
@@ -91,64 +135,201 @@ impl<const SIZE: usize> FingerprintTable<SIZE> {
 
     #[inline(always)]
     pub(crate) fn find_or_insert(&mut self, name: &[u8], padded_name: &[u8]) -> usize {
-        let fingerprint = fingerprint(name, padded_name);
+        self.find_or_insert_hash(name, slot_hash(name, padded_name))
+    }
 
-        // Reserve zero as the empty-slot marker without collapsing fingerprints
-        // that differ only in their low bit.
-        let hash = if fingerprint == 0 { 1 } else { fingerprint };
+    /// [`FingerprintTable::find_or_insert`] for the [`slot_hash`] of `name`.
+    #[inline(always)]
+    pub(crate) fn find_or_insert_hash(&mut self, name: &[u8], hash: u64) -> usize {
         let mut idx = (hash ^ (hash >> 32)) as usize & (SIZE - 1);
 
         loop {
-            let slot = &mut self.slots[idx];
+            let slot = &self.slots[idx];
             if slot.hash == hash {
-                return idx;
+                return slot.target as usize;
             }
             if slot.hash == 0 {
-                slot.hash = hash;
-                self.names
-                    .push(std::str::from_utf8(name).unwrap().to_owned());
-                slot.name_id = (self.names.len() - 1) as u16;
-                return idx;
+                return self.insert(name, hash, idx).0;
             }
             idx = (idx + 1) & (SIZE - 1);
         }
     }
 
+    /// Occupies the empty slot `idx` with `name`, whose hash is `hash`, and
+    /// returns the slot that holds its state and whether that slot is new. A
+    /// name that normalization changes becomes an alias of the slot of its
+    /// normalized form, inserted first if absent.
+    #[cold]
+    fn insert(&mut self, name: &[u8], hash: u64, idx: usize) -> (usize, bool) {
+        let name = std::str::from_utf8(name).unwrap();
+        let Some(normalized) = self.normalization.normalize(name) else {
+            self.occupy(idx, hash, name.to_owned());
+            return (idx, true);
+        };
+        let mut padded_name = normalized.as_bytes().to_vec();
+        padded_name.resize(normalized.len() + 8, 0);
+        let normalized_hash = slot_hash(normalized.as_bytes(), &padded_name);
+        let (target, new) = match self.probe(normalized_hash) {
+            Ok(found) => (found, false),
+            Err(empty) => {
+                self.occupy(empty, normalized_hash, normalized);
+                (empty, true)
+            },
+        };
+        // The normalized name may have taken `idx`
+        if let Err(alias) = self.probe(hash) {
+            assert_has_free_slot::<SIZE>(self.names.len() + self.aliases);
+            self.slots[alias] = FingerprintSlot {
+                hash,
+                state: A::default(),
+                name_id: self.slots[target].name_id,
+                target: target as u32,
+            };
+            self.aliases += 1;
+        }
+        (target, new)
+    }
+
+    /// Slot of `hash` if present, or else the empty slot that ends its probe
+    /// sequence.
+    fn probe(&self, hash: u64) -> Result<usize, usize> {
+        let mut idx = (hash ^ (hash >> 32)) as usize & (SIZE - 1);
+        loop {
+            match self.slots[idx].hash {
+                h if h == hash => return Ok(idx),
+                0 => return Err(idx),
+                _ => idx = (idx + 1) & (SIZE - 1),
+            }
+        }
+    }
+
+    /// Fills the empty slot `idx` with the station `name`, whose hash is
+    /// `hash`.
+    fn occupy(&mut self, idx: usize, hash: u64, name: String) {
+        assert_has_free_slot::<SIZE>(self.names.len() + self.aliases);
+        self.names.push(name);
+        let slot = &mut self.slots[idx];
+        slot.hash = hash;
+        slot.name_id = (self.names.len() - 1) as u16;
+        slot.target = idx as u32;
+    }
+
     #[inline(always)]
     pub(crate) fn update_slot(&mut self, slot_idx: usize, value: i16) {
         self.slots[slot_idx].state.update(value);
     }
 
-    pub(crate) fn into_result(mut self) -> Vec<(String, StateF)> {
+    pub(crate) fn into_result(mut self) -> Vec<(String, A)> {
         let mut result = Vec::with_capacity(self.names.len());
-        for slot in self.slots {
-            if slot.hash != 0 {
+        for (idx, slot) in self.slots.into_iter().enumerate() {
+            if slot.hash != 0 && slot.target as usize == idx {
                 let name = std::mem::take(&mut self.names[slot.name_id as usize]);
-                result.push((name, slot.state.to_f64()));
+                result.push((name, slot.state));
+            }
+        }
+        result
+    }
+
+    /// [`FingerprintTable::into_result`] with the slot index of every station,
+    /// for tables that keep more per-slot state next to this one.
+    pub(crate) fn into_result_with_slots(mut self) -> Vec<(usize, String, A)> {
+        let mut result = Vec::with_capacity(self.names.len());
+        for (idx, slot) in self.slots.into_iter().enumerate() {
+            if slot.hash != 0 && slot.target as usize == idx {
+                let name = std::mem::take(&mut self.names[slot.name_id as usize]);
+                result.push((idx, name, slot.state));
             }
         }
         result
     }
 }
 
-struct SimdFingerprintSlot {
-    hash: u64,
-    name_id: u16,
+/// The table operations of the buffer loops shared by the `*_table` parsers,
+/// so that a [`FilteredTable`](crate::station_filter::FilteredTable) can run
+/// in them in place of a [`FingerprintTable`].
+pub(crate) trait StationTable {
+    /// Slot of `name`, inserted if new, to pass to
+    /// [`StationTable::update_slot`].
+    fn find_or_insert(&mut self, name: &[u8], padded_name: &[u8]) -> usize;
+
+    fn update_slot(&mut self, slot_idx: usize, value: i16);
+
+    #[inline(always)]
+    fn insert_or_update(&mut self, name: &[u8], padded_name: &[u8], value: i16) {
+        let slot_idx = self.find_or_insert(name, padded_name);
+        self.update_slot(slot_idx, value);
+    }
 }
 
-impl Default for SimdFingerprintSlot {
-    fn default() -> Self {
-        Self {
-            hash: 0,
-            name_id: 0,
+impl<const SIZE: usize, A: Accumulator> StationTable for FingerprintTable<SIZE, A> {
+    #[inline(always)]
+    fn find_or_insert(&mut self, name: &[u8], padded_name: &[u8]) -> usize {
+        FingerprintTable::find_or_insert(self, name, padded_name)
+    }
+
+    #[inline(always)]
+    fn update_slot(&mut self, slot_idx: usize, value: i16) {
+        FingerprintTable::update_slot(self, slot_idx, value)
+    }
+
+    #[inline(always)]
+    fn insert_or_update(&mut self, name: &[u8], padded_name: &[u8], value: i16) {
+        FingerprintTable::insert_or_update(self, name, padded_name, value)
+    }
+}
+
+/// A set of station fingerprints, probed like [`FingerprintTable`] so that a
+/// membership test usually costs a single probe.
+pub(crate) struct FingerprintSet {
+    hashes: Vec<u64>,
+}
+
+impl FingerprintSet {
+    pub(crate) fn new<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Self {
+        let hashes: Vec<u64> = names
+            .into_iter()
+            .map(|name| {
+                let mut padded_name = name.as_bytes().to_vec();
+                padded_name.resize(name.len() + 8, 0);
+                slot_hash(name.as_bytes(), &padded_name)
+            })
+            .collect();
+        let mut set = Self {
+            hashes: vec![0; (2 * hashes.len()).next_power_of_two().max(2)],
+        };
+        for hash in hashes {
+            let idx = set.find(hash);
+            set.hashes[idx] = hash;
         }
+        set
     }
+
+    #[inline(always)]
+    fn find(&self, hash: u64) -> usize {
+        let mask = self.hashes.len() - 1;
+        let mut idx = (hash ^ (hash >> 32)) as usize & mask;
+        while self.hashes[idx] != hash && self.hashes[idx] != 0 {
+            idx = (idx + 1) & mask;
+        }
+        idx
+    }
+
+    /// Whether the set holds the [`slot_hash`] `hash`.
+    #[inline(always)]
+    pub(crate) fn contains(&self, hash: u64) -> bool { self.hashes[self.find(hash)] == hash }
 }
 
-/// Fingerprint lookup with eight independent aggregation stripes per station.
-/// A batch always writes one value to each stripe, making SIMD scatter indices
-/// unique even when multiple lanes contain the same station.
-pub(crate) struct SimdFingerprintTable<const SIZE: usize> {
+#[derive(Default)]
+struct SimdFingerprintSlot {
+    hash: u64,
+    name_id: u16,
+}
+
+/// Fingerprint lookup with `STRIPES` independent aggregation stripes per
+/// station, one per SIMD lane of a batch. A batch always writes one value to
+/// each stripe, making SIMD scatter indices unique even when multiple lanes
+/// contain the same station.
+pub(crate) struct SimdFingerprintTable<const SIZE: usize, const STRIPES: usize = 8> {
     slots: Vec<SimdFingerprintSlot>,
     names: Vec<String>,
     mins: Vec<i64>,
@@ -157,27 +338,22 @@ pub(crate) struct SimdFingerprintTable<const SIZE: usize> {
     sums: Vec<i64>,
 }
 
-impl<const SIZE: usize> SimdFingerprintTable<SIZE> {
+impl<const SIZE: usize, const STRIPES: usize> SimdFingerprintTable<SIZE, STRIPES> {
     pub(crate) fn new() -> Self {
         assert!(SIZE.is_power_of_two());
         Self {
             slots: (0..SIZE).map(|_| SimdFingerprintSlot::default()).collect(),
             names: Vec::with_capacity(512),
-            mins: Vec::with_capacity(512 * 8),
-            maxs: Vec::with_capacity(512 * 8),
-            counts: Vec::with_capacity(512 * 8),
-            sums: Vec::with_capacity(512 * 8),
+            mins: Vec::with_capacity(512 * STRIPES),
+            maxs: Vec::with_capacity(512 * STRIPES),
+            counts: Vec::with_capacity(512 * STRIPES),
+            sums: Vec::with_capacity(512 * STRIPES),
         }
     }
 
     #[inline(always)]
     pub(crate) fn find_or_insert(&mut self, name: &[u8], padded_name: &[u8]) -> usize {
-        let raw_fingerprint = fingerprint(name, padded_name);
-        let hash = if raw_fingerprint == 0 {
-            1
-        } else {
-            raw_fingerprint
-        };
+        let hash = slot_hash(name, padded_name);
         let mut idx = (hash ^ (hash >> 32)) as usize & (SIZE - 1);
 
         loop {
@@ -186,15 +362,16 @@ impl<const SIZE: usize> SimdFingerprintTable<SIZE> {
                 return slot.name_id as usize;
             }
             if slot.hash == 0 {
+                assert_has_free_slot::<SIZE>(self.names.len());
                 let name_id = self.names.len();
                 slot.hash = hash;
                 slot.name_id = name_id as u16;
                 self.names
                     .push(std::str::from_utf8(name).unwrap().to_owned());
-                self.mins.extend([i64::MAX; 8]);
-                self.maxs.extend([i64::MIN; 8]);
-                self.counts.extend([0; 8]);
-                self.sums.extend([0; 8]);
+                self.mins.extend([i64::MAX; STRIPES]);
+                self.maxs.extend([i64::MIN; STRIPES]);
+                self.counts.extend([0; STRIPES]);
+                self.sums.extend([0; STRIPES]);
                 return name_id;
             }
             idx = (idx + 1) & (SIZE - 1);
@@ -202,23 +379,29 @@ impl<const SIZE: usize> SimdFingerprintTable<SIZE> {
     }
 
     #[inline(always)]
-    pub(crate) fn update_batch(&mut self, name_ids: [usize; 8], values: [i16; 8]) {
-        let indices =
-            Simd::<usize, 8>::from_array(std::array::from_fn(|lane| name_ids[lane] * 8 + lane));
-        let values = i64x8::from_array(values.map(i64::from));
-        let mins = i64x8::gather_or_default(&self.mins, indices).simd_min(values);
-        let maxs = i64x8::gather_or_default(&self.maxs, indices).simd_max(values);
-        let counts = i64x8::gather_or_default(&self.counts, indices) + i64x8::splat(1);
-        let sums = i64x8::gather_or_default(&self.sums, indices) + values;
+    pub(crate) fn update_batch(&mut self, name_ids: [usize; STRIPES], values: [i16; STRIPES]) {
+        let indices = Simd::<usize, STRIPES>::from_array(std::array::from_fn(|lane| {
+            name_ids[lane] * STRIPES + lane
+        }));
+        let values = Simd::<i64, STRIPES>::from_array(values.map(i64::from));
+        let mins = Simd::gather_or_default(&self.mins, indices).simd_min(values);
+        let maxs = Simd::gather_or_default(&self.maxs, indices).simd_max(values);
+        let counts = Simd::gather_or_default(&self.counts, indices) + Simd::splat(1);
+        let sums = Simd::gather_or_default(&self.sums, indices) + values;
         mins.scatter(&mut self.mins, indices);
         maxs.scatter(&mut self.maxs, indices);
         counts.scatter(&mut self.counts, indices);
         sums.scatter(&mut self.sums, indices);
     }
 
-    pub(crate) fn update_partial(&mut self, name_ids: [usize; 8], values: [i16; 8], len: usize) {
+    pub(crate) fn update_partial(
+        &mut self,
+        name_ids: [usize; STRIPES],
+        values: [i16; STRIPES],
+        len: usize,
+    ) {
         for lane in 0..len {
-            let idx = name_ids[lane] * 8 + lane;
+            let idx = name_ids[lane] * STRIPES + lane;
             let value = values[lane] as i64;
             self.mins[idx] = self.mins[idx].min(value);
             self.maxs[idx] = self.maxs[idx].max(value);
@@ -227,24 +410,119 @@ impl<const SIZE: usize> SimdFingerprintTable<SIZE> {
         }
     }
 
-    pub(crate) fn into_result(self) -> Vec<(String, StateF)> {
+    pub(crate) fn into_result(self) -> Vec<(String, StateI)> {
         let mut result = Vec::with_capacity(self.names.len());
         for (name_id, name) in self.names.into_iter().enumerate() {
-            let start = name_id * 8;
+            let start = name_id * STRIPES;
             let mut state = StateI::default();
-            for lane in start..start + 8 {
+            for lane in start..start + STRIPES {
                 if self.counts[lane] != 0 {
                     let lane_state = StateI {
                         min: self.mins[lane] as i16,
                         max: self.maxs[lane] as i16,
-                        count: self.counts[lane] as u32,
+                        count: self.counts[lane] as u64,
                         sum: self.sums[lane],
                     };
                     state.merge(&lane_state);
                 }
             }
-            result.push((name, state.to_f64()));
+            result.push((name, state));
         }
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records of the synthetic stream, more than `u32::MAX`.
+    const RECORDS: u64 = 5_000_000_000;
+
+    fn padded(name: &str) -> Vec<u8> {
+        let mut padded_name = name.as_bytes().to_vec();
+        padded_name.resize(name.len() + 8, 0);
+        padded_name
+    }
+
+    #[test]
+    fn test_normalized_spellings_share_a_slot() {
+        let (decomposed, precomposed) = ("Chis\u{326}ina\u{306}u", "Chi\u{219}in\u{103}u");
+        let mut table = FingerprintTable::<64>::with_normalization(Normalization::Nfc);
+        // The first record of a spelling takes either insertion path
+        table.insert_or_update(decomposed.as_bytes(), &padded(decomposed), 10);
+        let slot = table.find_or_insert(precomposed.as_bytes(), &padded(precomposed));
+        table.update_slot(slot, -10);
+        assert_eq!(
+            slot,
+            table.find_or_insert(decomposed.as_bytes(), &padded(decomposed))
+        );
+        table.insert_or_update(decomposed.as_bytes(), &padded(decomposed), 30);
+        table.insert_or_update(b"Oslo", &padded("Oslo"), 0);
+
+        let mut result = table.into_result();
+        crate::sort_result(&mut result);
+        assert_eq!(2, result.len());
+        assert_eq!(precomposed, result[0].0);
+        assert_eq!("-1.0/1.0/3.0", result[0].1.to_string());
+
+        let mut raw = FingerprintTable::<64>::new();
+        raw.insert_or_update(decomposed.as_bytes(), &padded(decomposed), 10);
+        raw.insert_or_update(precomposed.as_bytes(), &padded(precomposed), 10);
+        assert_eq!(2, raw.into_result().len());
+    }
+
+    #[test]
+    fn test_merge_beyond_u32_counts() {
+        // Every chunk of the stream holds the same records of one station, is
+        // aggregated by a table and merged like the per-thread results.
+        const CHUNK: u64 = 1000;
+        let name = padded("Oslo");
+        let mut table = FingerprintTable::<1024>::new();
+        for i in 0..CHUNK {
+            table.insert_or_update(b"Oslo", &name, -((i % 100) as i16));
+        }
+        let chunk = table.into_result();
+        let mut total = chunk[0].1.clone();
+        for _ in 1..RECORDS / CHUNK {
+            total.merge(&chunk[0].1);
+        }
+        assert_eq!(RECORDS, total.count);
+        assert_eq!(-49500 * (RECORDS / CHUNK) as i64, total.sum);
+        assert_eq!("-9.9/-4.9/0.0", total.to_string());
+    }
+
+    /// Pushes the whole stream through one table, record by record. Run with
+    /// `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_stream_beyond_u32_counts() {
+        let name = padded("Oslo");
+        let mut table = FingerprintTable::<1024>::new();
+        for i in 0..RECORDS {
+            table.insert_or_update(b"Oslo", &name, -((i % 100) as i16));
+        }
+        let result = table.into_result();
+        assert_eq!(RECORDS, result[0].1.count);
+        assert_eq!(-4950 * (RECORDS / 100) as i64, result[0].1.sum);
+        assert_eq!("-9.9/-4.9/0.0", result[0].1.to_string());
+    }
+
+    /// Counts more than `u32::MAX` records in every stripe through
+    /// `update_batch`. Run like [`test_stream_beyond_u32_counts`].
+    #[test]
+    #[ignore]
+    fn test_simd_stripes_beyond_u32_counts() {
+        let batches = u32::MAX as u64 + 1;
+        let name = padded("Oslo");
+        let mut table = SimdFingerprintTable::<1024, 8>::new();
+        let id = table.find_or_insert(b"Oslo", &name);
+        for _ in 0..batches {
+            table.update_batch([id; 8], [-5, 5, 10, 0, 0, 0, 0, 0]);
+        }
+        let result = table.into_result();
+        assert_eq!(8 * batches, result[0].1.count);
+        assert_eq!(10 * batches as i64, result[0].1.sum);
+        assert_eq!("-0.5/0.1/1.0", result[0].1.to_string());
+    }
+}
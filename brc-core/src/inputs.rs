@@ -0,0 +1,181 @@
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+
+use crate::station_filter::glob_to_regex;
+
+/// Expands the input arguments into the files to parse, in order. An input
+/// is a file, a directory, standing for the files directly in it sorted by
+/// name, or a glob with `*`, `?` and `[...]` in any path component, standing
+/// for the matching files sorted by path. Hidden files are only picked up by
+/// naming them or by a pattern starting with `.`.
+pub fn expand_inputs<S: AsRef<str>>(inputs: &[S]) -> Result<Vec<PathBuf>, String> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for input in inputs {
+        let input = input.as_ref();
+        let path = Path::new(input);
+        let expanded = if path.is_dir() {
+            let mut entries = list_dir(path, |name| !name.starts_with('.'))?;
+            entries.retain(|p| p.is_file());
+            entries
+        } else if path.exists() || !input.contains(['*', '?', '[']) {
+            if !path.is_file() {
+                return Err(format!("No such file: {input}"));
+            }
+            vec![path.to_path_buf()]
+        } else {
+            let matches = expand_glob(path)?;
+            if matches.is_empty() {
+                return Err(format!("No files match `{input}`"));
+            }
+            matches
+        };
+        for file in expanded {
+            if files.contains(&file) {
+                return Err(format!("{} is given more than once", file.display()));
+            }
+            files.push(file);
+        }
+    }
+    if files.is_empty() {
+        return Err("No input files".to_owned());
+    }
+    Ok(files)
+}
+
+/// The entries of `dir` whose names pass `keep`, sorted.
+fn list_dir(dir: &Path, keep: impl Fn(&str) -> bool) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Unable to read directory {}: {e}", dir.display()))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| format!("Unable to read directory {}: {e}", dir.display()))?;
+        if entry.file_name().to_str().is_some_and(&keep) {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort_unstable();
+    Ok(paths)
+}
+
+/// The files matching `pattern`, component by component.
+fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut candidates = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let name = match component {
+            Component::Normal(name) => name.to_string_lossy(),
+            _ => {
+                for candidate in &mut candidates {
+                    candidate.push(component);
+                }
+                continue;
+            },
+        };
+        if !name.contains(['*', '?', '[']) {
+            for candidate in &mut candidates {
+                candidate.push(name.as_ref());
+            }
+            continue;
+        }
+        let regex = glob_to_regex(&name)
+            .and_then(|regex| Regex::new(&regex).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Invalid glob `{}`: {e}", pattern.display()))?;
+        let hidden = name.starts_with('.');
+        let mut matches = Vec::new();
+        for dir in candidates
+            .iter()
+            .filter(|dir| dir.as_os_str().is_empty() || dir.is_dir())
+        {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            for path in list_dir(dir, |n| {
+                regex.is_match(n) && (hidden || !n.starts_with('.'))
+            })? {
+                // Keep the pattern's relative form rather than `./`
+                matches.push(
+                    path.strip_prefix("./")
+                        .map(Path::to_path_buf)
+                        .unwrap_or(path),
+                );
+            }
+        }
+        candidates = matches;
+    }
+    candidates.retain(|p| p.is_file());
+    candidates.sort_unstable();
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_inputs() {
+        let root = std::env::temp_dir().join(format!("brc-inputs-{}", std::process::id()));
+        for dir in ["a", "b", "c/sub"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "a/01.txt",
+            "a/02.txt",
+            "a/.hidden",
+            "b/01.txt",
+            "b/02.csv",
+            "c/03.txt",
+        ] {
+            std::fs::write(root.join(file), "Oslo;1.0\n").unwrap();
+        }
+        let expand = |inputs: &[&str]| {
+            let inputs: Vec<String> = inputs
+                .iter()
+                .map(|i| root.join(i).to_string_lossy().into_owned())
+                .collect();
+            expand_inputs(&inputs).map(|files| {
+                files
+                    .iter()
+                    .map(|f| {
+                        f.strip_prefix(&root)
+                            .unwrap()
+                            .to_string_lossy()
+                            .into_owned()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(
+            Ok(vec!["a/01.txt".to_owned(), "a/02.txt".to_owned()]),
+            expand(&["a"])
+        );
+        // Directories are not descended into
+        assert_eq!(Ok(vec!["c/03.txt".to_owned()]), expand(&["c"]));
+        assert_eq!(
+            Ok(vec![
+                "b/01.txt".to_owned(),
+                "a/01.txt".to_owned(),
+                "a/02.txt".to_owned()
+            ]),
+            expand(&["b/01.txt", "a/*.txt"])
+        );
+        assert_eq!(
+            Ok(vec![
+                "a/01.txt".to_owned(),
+                "a/02.txt".to_owned(),
+                "b/01.txt".to_owned()
+            ]),
+            expand(&["[ab]/0?.txt"])
+        );
+        assert_eq!(Ok(vec!["a/.hidden".to_owned()]), expand(&["*/.h*"]));
+        assert!(expand(&["a", "a/01.txt"]).is_err());
+        assert!(expand(&["d/*.txt"]).is_err());
+        assert!(expand(&["a/03.txt"]).is_err());
+        assert!(expand(&[]).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Name of the environment variable that forces a specific kernel, e.g.
+/// `BRC_KERNEL=avx2`. Useful to compare kernels on a single host.
+pub const KERNEL_ENV_VAR: &str = "BRC_KERNEL";
+
+/// Instruction-set level a multi-versioned hot loop is compiled for.
+///
+/// The crate is built for the target's baseline CPU, so the binary runs on
+/// every host of the architecture. Hot loops are additionally compiled for the
+/// wider levels below and the best one supported by the running CPU is picked
+/// at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kernel {
+    /// Target baseline on architectures without dedicated kernels.
+    Portable,
+    /// AArch64 baseline: Advanced SIMD, available on every AArch64 host.
+    Neon,
+    /// x86-64 baseline.
+    Sse2,
+    /// x86-64-v3: AVX2, BMI1/2, LZCNT and POPCNT.
+    Avx2,
+    /// x86-64-v4: AVX-512 F/BW/VL on top of [`Kernel::Avx2`].
+    Avx512,
+}
+
+impl Kernel {
+    /// All kernels, in increasing order of preference.
+    pub const ALL: [Kernel; 5] = [
+        Kernel::Portable,
+        Kernel::Neon,
+        Kernel::Sse2,
+        Kernel::Avx2,
+        Kernel::Avx512,
+    ];
+
+    /// The kernel every host of the target architecture can run.
+    pub const fn baseline() -> Kernel {
+        if cfg!(target_arch = "x86_64") {
+            Kernel::Sse2
+        } else if cfg!(target_arch = "aarch64") {
+            Kernel::Neon
+        } else {
+            Kernel::Portable
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Kernel::Portable => "portable",
+            Kernel::Neon => "neon",
+            Kernel::Sse2 => "sse2",
+            Kernel::Avx2 => "avx2",
+            Kernel::Avx512 => "avx512",
+        }
+    }
+
+    /// Whether the running CPU can execute this kernel.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Kernel::Portable => !cfg!(any(target_arch = "x86_64", target_arch = "aarch64")),
+            Kernel::Neon => cfg!(target_arch = "aarch64"),
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse2 => true,
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => {
+                is_x86_feature_detected!("avx2")
+                    && is_x86_feature_detected!("bmi1")
+                    && is_x86_feature_detected!("bmi2")
+                    && is_x86_feature_detected!("lzcnt")
+                    && is_x86_feature_detected!("popcnt")
+            },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx512 => {
+                Kernel::Avx2.is_supported()
+                    && is_x86_feature_detected!("avx512f")
+                    && is_x86_feature_detected!("avx512bw")
+                    && is_x86_feature_detected!("avx512vl")
+            },
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
+
+    /// Kernels supported by the running CPU, in increasing order of
+    /// preference.
+    pub fn supported() -> Vec<Kernel> {
+        Kernel::ALL
+            .into_iter()
+            .filter(|k| k.is_supported())
+            .collect()
+    }
+
+    /// The most capable kernel supported by the running CPU.
+    pub fn detect() -> Kernel {
+        Kernel::ALL
+            .into_iter()
+            .rev()
+            .find(|k| k.is_supported())
+            .unwrap_or(Kernel::baseline())
+    }
+
+    /// The kernel used by the multi-versioned implementations: the one named
+    /// by a non-empty [`KERNEL_ENV_VAR`], otherwise [`Kernel::detect`]. The
+    /// choice is made once per process.
+    pub fn selected() -> Kernel {
+        static SELECTED: OnceLock<Kernel> = OnceLock::new();
+        *SELECTED.get_or_init(|| match std::env::var(KERNEL_ENV_VAR) {
+            Ok(name) if !name.is_empty() => {
+                let kernel = Kernel::from_str(&name).unwrap_or_else(|e| panic!("{e}"));
+                assert!(
+                    kernel.is_supported(),
+                    "{KERNEL_ENV_VAR}={kernel} is not supported by this CPU, supported: {:?}",
+                    Kernel::supported()
+                        .iter()
+                        .map(|k| k.name())
+                        .collect::<Vec<_>>()
+                );
+                kernel
+            },
+            _ => Kernel::detect(),
+        })
+    }
+}
+
+impl Display for Kernel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str(self.name()) }
+}
+
+impl FromStr for Kernel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Kernel::ALL
+            .into_iter()
+            .find(|k| k.name() == s)
+            .ok_or_else(|| {
+                let names = Kernel::ALL.map(Kernel::name);
+                format!("Unknown kernel `{s}`, expected one of {names:?}")
+            })
+    }
+}
+
+/// Defines `$name(kernel, table, buffer, valid_len)`, which compiles `$body`
+/// once per instruction-set level and runs the copy matching `kernel`.
+/// Optional `avx512: $avx512, neon: $neon` bodies replace `$body` on
+/// [`Kernel::Avx512`] and [`Kernel::Neon`] for loops with dedicated paths; the
+/// AVX-512 one must itself be a `#[target_feature]` function with a subset of
+/// the AVX-512 features below.
+///
+/// `$body` and everything it calls in the hot loop must be
+/// `#[inline(always)]` so that it is inlined into, and compiled with the
+/// features of, each `#[target_feature]` wrapper. The caller must only pass
+/// kernels for which [`Kernel::is_supported`] holds. `$table` and the bodies
+/// may refer to the `SIZE` const parameter of the generated function and to one
+/// extra const parameter declared as `fn $name<const N: usize>(...)`, or,
+/// declared as `fn $name<T: Bound>(...)`, to a type parameter instead of
+/// `SIZE`.
+macro_rules! multiversion {
+    (
+        $(#[$meta:meta])* fn $name:ident $(<const $param:ident: usize>)? (&mut $table:ty)
+            => $body:path
+    ) => {
+        multiversion! {
+            $(#[$meta])* fn $name $(<const $param: usize>)? (&mut $table)
+                => $body, avx512: $body, neon: $body
+        }
+    };
+    (
+        $(#[$meta:meta])* fn $name:ident $(<const $param:ident: usize>)? (&mut $table:ty)
+            => $body:path,
+        avx512: $avx512:path,
+        neon: $neon:path
+    ) => {
+        multiversion! {
+            @define $(#[$meta])* fn $name [const SIZE: usize $(, const $param: usize)?]
+                [SIZE $(, $param)?] (&mut $table) => $body, avx512: $avx512, neon: $neon
+        }
+    };
+    (
+        $(#[$meta:meta])* fn $name:ident<$type:ident: $bound:path>(&mut $table:ty)
+            => $body:path
+    ) => {
+        multiversion! {
+            $(#[$meta])* fn $name<$type: $bound>(&mut $table)
+                => $body, avx512: $body, neon: $body
+        }
+    };
+    (
+        $(#[$meta:meta])* fn $name:ident<$type:ident: $bound:path>(&mut $table:ty)
+            => $body:path,
+        avx512: $avx512:path,
+        neon: $neon:path
+    ) => {
+        multiversion! {
+            @define $(#[$meta])* fn $name [$type: $bound] [$type] (&mut $table)
+                => $body, avx512: $avx512, neon: $neon
+        }
+    };
+    (
+        @define $(#[$meta:meta])* fn $name:ident [$($generics:tt)*] [$($arguments:tt)*]
+            (&mut $table:ty) => $body:path,
+        avx512: $avx512:path,
+        neon: $neon:path
+    ) => {
+        $(#[$meta])*
+        #[inline]
+        fn $name<$($generics)*>(
+            kernel: $crate::kernel::Kernel,
+            table: &mut $table,
+            buffer: &[u8],
+            valid_len: usize,
+        ) {
+            #[cfg(target_arch = "x86_64")]
+            #[target_feature(enable = "avx2,bmi1,bmi2,lzcnt,popcnt")]
+            fn avx2<$($generics)*>(table: &mut $table, buffer: &[u8], valid_len: usize) {
+                $body(table, buffer, valid_len)
+            }
+
+            #[cfg(target_arch = "x86_64")]
+            #[target_feature(enable = "avx512f,avx512bw,avx512vl,avx2,bmi1,bmi2,lzcnt,popcnt")]
+            fn avx512<$($generics)*>(table: &mut $table, buffer: &[u8], valid_len: usize) {
+                $avx512(table, buffer, valid_len)
+            }
+
+            debug_assert!(kernel.is_supported(), "{kernel} is not supported");
+            match kernel {
+                // SAFETY: the kernel was checked against the running CPU.
+                #[cfg(target_arch = "x86_64")]
+                $crate::kernel::Kernel::Avx2 => unsafe {
+                    avx2::<$($arguments)*>(table, buffer, valid_len)
+                },
+                #[cfg(target_arch = "x86_64")]
+                $crate::kernel::Kernel::Avx512 => unsafe {
+                    avx512::<$($arguments)*>(table, buffer, valid_len)
+                },
+                // NEON is part of the AArch64 baseline, no wrapper is needed.
+                #[cfg(target_arch = "aarch64")]
+                $crate::kernel::Kernel::Neon => $neon(table, buffer, valid_len),
+                _ => $body(table, buffer, valid_len),
+            }
+        }
+    };
+}
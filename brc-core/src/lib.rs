@@ -1,27 +1,88 @@
 #![feature(portable_simd)]
 
+mod accumulator;
+mod adaptive;
+mod chunk_index;
+mod collation;
+mod columnar;
+mod extremes;
 mod fingerprint_table;
+mod inputs;
+#[macro_use]
+mod kernel;
+mod metadata;
+mod metrics;
+mod output;
+mod progress;
+mod records;
+mod registry;
+mod sampling;
+mod station_dictionary;
+mod station_filter;
 mod station_name;
 mod table;
+mod thresholds;
+mod timestamps;
 
-use std::fmt::Display;
 use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::simd::cmp::SimdPartialEq;
 use std::simd::num::SimdInt;
-use std::simd::{Select, i64x8, u8x32, u8x64};
+use std::simd::{Select, Simd, i64x8, u8x32, u8x64};
 use std::str::FromStr;
+use std::sync::Arc;
 
 use rustc_hash::FxHashMap;
 
-use crate::fingerprint_table::{FingerprintTable, SimdFingerprintTable};
+pub use crate::accumulator::{Accumulator, Histogram, LastValue, finalize_result};
+pub use crate::adaptive::{
+    AdaptiveChoice, InputProfile, RecordFormat, choose_implementation, profile_input,
+};
+pub use crate::chunk_index::{ChunkIndex, DEFAULT_INDEX_STRIDE, FileFingerprint, IndexedChunk};
+pub use crate::collation::{Collation, Normalization};
+pub use crate::columnar::{
+    BlockStats, ColumnarFile, ColumnarWriter, DEFAULT_BLOCK_RECORDS, TranscodeSummary, is_columnar,
+    parse_columnar,
+};
+use crate::extremes::ExtremesTable;
+pub use crate::extremes::{ExtremeState, Extremes, GlobalExtremes, Reading};
+use crate::fingerprint_table::{FingerprintTable, SimdFingerprintTable, StationTable};
+pub use crate::inputs::expand_inputs;
+pub use crate::kernel::{KERNEL_ENV_VAR, Kernel};
+pub use crate::metadata::{Rollup, StationMetadata};
+use crate::metrics::MultiTable;
+pub use crate::metrics::{MultiState, Schema};
+pub use crate::output::{format_output, round_mean, round_tenths, write_tenths};
+pub use crate::progress::{
+    CancellationToken, Cancelled, Progress, ProgressTracker, ThreadProgress, TrackedReader,
+};
+pub use crate::records::{RECORD_PADDING, Records};
+pub use crate::registry::{
+    Implementation, InputFormat, ParseFn, find_implementation, implementations,
+};
+pub use crate::sampling::{ChunkSample, Estimate, Z_95};
+use crate::station_dictionary::DictionaryTable;
+pub use crate::station_dictionary::{StationDictionary, UnknownStations};
+use crate::station_filter::FilteredTable;
+pub use crate::station_filter::{StationFilter, read_station_list};
 use crate::table::Table;
-
+use crate::thresholds::ThresholdTable;
+pub use crate::thresholds::{Exceedances, ThresholdState, Thresholds, parse_threshold};
+use crate::timestamps::TimeSeriesTable;
+pub use crate::timestamps::{
+    Granularity, TIMESTAMP_LEN, TimeSeriesState, Timestamp, parse_timestamp,
+};
+
+/// Aggregated measurements in degrees. The sum is compensated with Neumaier's
+/// variant of Kahan summation, so it stays accurate over billions of
+/// measurements; the parsers themselves aggregate exactly in [`StateI`].
 #[derive(Debug)]
 pub struct StateF {
     min: f64,
     max: f64,
-    count: u32,
+    count: u64,
     sum: f64,
+    /// Rounding error lost by `sum`, to be added back to it.
+    compensation: f64,
 }
 
 impl Default for StateF {
@@ -31,38 +92,56 @@ impl Default for StateF {
             max: f64::MIN,
             count: 0,
             sum: 0.0,
+            compensation: 0.0,
         }
     }
 }
 
-impl Display for StateF {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let avg = self.sum / (self.count as f64);
-        write!(f, "{:.1}/{avg:.1}/{:.1}", self.min, self.max)
-    }
-}
-
 impl StateF {
-    fn update(&mut self, v: f64) {
+    pub fn update(&mut self, v: f64) {
         self.min = self.min.min(v);
         self.max = self.max.max(v);
         self.count += 1;
-        self.sum += v;
+        self.add_to_sum(v);
     }
 
     pub fn merge(&mut self, other: &Self) {
         self.min = self.min.min(other.min);
         self.max = self.max.max(other.max);
-        self.count += other.count;
-        self.sum += other.sum;
+        self.count = checked_count(self.count, other.count);
+        self.add_to_sum(other.sum);
+        self.compensation += other.compensation;
+    }
+
+    /// The compensated sum of the measurements.
+    pub fn sum(&self) -> f64 { self.sum + self.compensation }
+
+    pub fn mean(&self) -> f64 { self.sum() / self.count as f64 }
+
+    #[inline]
+    fn add_to_sum(&mut self, v: f64) {
+        let sum = self.sum + v;
+        // Whichever operand is smaller lost its low bits to the addition
+        if self.sum.abs() >= v.abs() {
+            self.compensation += (self.sum - sum) + v;
+        } else {
+            self.compensation += (v - sum) + self.sum;
+        }
+        self.sum = sum;
     }
 }
 
-#[derive(Debug, Clone)]
+/// Adds measurement counts, failing loudly instead of wrapping around.
+#[inline]
+fn checked_count(a: u64, b: u64) -> u64 {
+    a.checked_add(b).expect("Measurement count overflows u64")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StateI {
     min: i16,
     max: i16,
-    count: u32,
+    count: u64,
     sum: i64,
 }
 
@@ -77,13 +156,6 @@ impl Default for StateI {
     }
 }
 
-impl Display for StateI {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let avg = self.sum as f64 / 10.0f64 / (self.count as f64);
-        write!(f, "{:.1}/{avg:.1}/{:.1}", self.min, self.max)
-    }
-}
-
 impl StateI {
     pub fn new(v: i16) -> StateI {
         StateI {
@@ -104,8 +176,11 @@ impl StateI {
     pub fn merge(&mut self, other: &Self) {
         self.min = self.min.min(other.min);
         self.max = self.max.max(other.max);
-        self.count += other.count;
-        self.sum += other.sum;
+        self.count = checked_count(self.count, other.count);
+        self.sum = self
+            .sum
+            .checked_add(other.sum)
+            .expect("Measurement sum overflows i64");
     }
 
     pub fn to_f64(&self) -> StateF {
@@ -114,11 +189,12 @@ impl StateI {
             max: self.max as f64 / 10.0f64,
             count: self.count,
             sum: self.sum as f64 / 10.0f64,
+            compensation: 0.0,
         }
     }
 }
 
-pub fn sort_result(all: &mut Vec<(String, StateF)>) { all.sort_unstable_by(|a, b| a.0.cmp(&b.0)); }
+pub fn sort_result<S>(all: &mut [(String, S)]) { all.sort_unstable_by(|a, b| a.0.cmp(&b.0)); }
 
 /// Converts a slice of bytes to a string slice.
 #[inline]
@@ -153,7 +229,11 @@ fn naive_line_by_line0<R: Read + Seek, F>(
 
     let mut s: String = String::with_capacity(MAX_LINE_LENGTH_IN_BYTES);
     while offset <= end_inclusive as usize {
-        let read_bytes = rdr.read_line(&mut s).expect("Unable to read line");
+        let read_bytes = match rdr.read_line(&mut s) {
+            Ok(read_bytes) => read_bytes,
+            Err(e) if Cancelled::is(&e) => break,
+            Err(e) => panic!("Unable to read line: {e}"),
+        };
         // Check whether we reached EOF
         if read_bytes == 0 {
             break;
@@ -187,7 +267,7 @@ pub fn naive_line_by_line_dummy<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     _should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut dummy_result: usize = 0;
     naive_line_by_line0(
         rdr,
@@ -198,8 +278,10 @@ pub fn naive_line_by_line_dummy<R: Read + Seek>(
         end_inclusive,
     );
 
-    let mut s = StateF::default();
-    s.count = dummy_result as u32;
+    let s = StateI {
+        count: dummy_result as u64,
+        ..Default::default()
+    };
     vec![("dummy".to_string(), s)]
 }
 
@@ -209,13 +291,15 @@ const DEFAULT_HASHMAP_CAPACITY: usize = 10000;
 /// aggregates temperature per station.
 ///
 /// The method uses [`byte_to_string`], [`parse_f64`] and
-/// [`std::collections::HashMap`] from standard library.
+/// [`std::collections::HashMap`] from standard library. Like every other
+/// implementation it aggregates tenths of a degree, so measurements with more
+/// fractional digits are rounded to one.
 pub fn naive_line_by_line<R: Read + Seek>(
     rdr: BufReader<R>,
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut hs = std::collections::HashMap::with_capacity(DEFAULT_HASHMAP_CAPACITY);
     naive_line_by_line0(
         rdr,
@@ -223,14 +307,12 @@ pub fn naive_line_by_line<R: Read + Seek>(
             // Convert bytes to str
             let station_name: &str = byte_to_string(name);
             let measurement: &str = byte_to_string(t);
-            // Parse measurement as f64
-            let value = parse_f64(measurement);
+            // Parse measurement as f64 and round it to tenths
+            let value = (parse_f64(measurement) * 10.0).round() as i16;
             // Insert new state or update existing
             match hs.get_mut(station_name) {
                 None => {
-                    let mut s = StateF::default();
-                    s.update(value);
-                    hs.insert(station_name.to_string(), s);
+                    hs.insert(station_name.to_string(), StateI::new(value));
                 },
                 Some(prev) => prev.update(value),
             }
@@ -239,7 +321,7 @@ pub fn naive_line_by_line<R: Read + Seek>(
         end_inclusive,
     );
 
-    let mut all: Vec<(String, StateF)> = hs.into_iter().collect();
+    let mut all: Vec<(String, StateI)> = hs.into_iter().collect();
     if should_sort {
         sort_result(&mut all);
     }
@@ -315,7 +397,7 @@ pub fn naive_line_by_line_v2<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut hs: FxHashMap<String, StateI> =
         FxHashMap::with_capacity_and_hasher(DEFAULT_HASHMAP_CAPACITY, Default::default());
     naive_line_by_line0(
@@ -325,9 +407,7 @@ pub fn naive_line_by_line_v2<R: Read + Seek>(
             let value = to_scaled_integer(t);
             match hs.get_mut(station_name) {
                 None => {
-                    let mut s = StateI::new(value);
-                    s.update(value);
-                    hs.insert(station_name.to_string(), s);
+                    hs.insert(station_name.to_string(), StateI::new(value));
                 },
                 Some(prev) => prev.update(value),
             }
@@ -335,9 +415,9 @@ pub fn naive_line_by_line_v2<R: Read + Seek>(
         start,
         end_inclusive,
     );
-    let mut all: Vec<(String, StateF)> = hs
+    let mut all: Vec<(String, StateI)> = hs
         .into_iter()
-        .map(|(k, v)| (k.clone(), v.to_f64()))
+        .map(|(k, v)| (k.clone(), v.clone()))
         .collect();
     if should_sort {
         sort_result(&mut all);
@@ -366,8 +446,27 @@ fn seek_backward_to_newline<'a, R: Read + Seek>(
             .expect("Failed to seek back from current position");
     }
 
-    let valid_buffer = &buf[0..=j];
-    valid_buffer
+    &buf[0..=j]
+}
+
+/// Reads the next buffer of a chunk with `remaining` bytes left into
+/// `storage` and returns how many of them belong to the chunk. The read stops
+/// near the end of the chunk, but asks for at least the capacity of `rdr` so
+/// that it goes to `storage` directly instead of through the reader's buffer.
+fn read_chunk<R: Read>(rdr: &mut BufReader<R>, storage: &mut [u8], remaining: usize) -> usize {
+    let len = remaining.max(rdr.capacity()).min(storage.len());
+    read_buffer(rdr, &mut storage[..len]).min(remaining)
+}
+
+/// [`Read::read`] for the parse loops, which stop on a read of zero bytes:
+/// that is the end of the input or a [`Cancelled`] run, which
+/// [`ProgressTracker::run`] reports.
+fn read_buffer<R: Read>(rdr: &mut R, buf: &mut [u8]) -> usize {
+    match rdr.read(buf) {
+        Ok(read) => read,
+        Err(e) if Cancelled::is(&e) => 0,
+        Err(e) => panic!("Unable to read chunk: {e}"),
+    }
 }
 
 const INIT_HASH_VALUE: u64 = 0x517cc1b727220a95;
@@ -380,8 +479,7 @@ const fn get_semicolon_mask(w: i64) -> i64 {
     // XOR with 0x3b3b3b3b3b3b3b3b to set it to zero in all places where it occurs
     let x = w ^ 0x3b3b3b3b3b3b3b3b;
     // And use old school quick check whether a word has zero, check http://www.graphics.stanford.edu/~seander/bithacks.html#ZeroInWord
-    let mask = (x - 0x0101010101010101) & (!x & (0x8080808080808080u64 as i64));
-    mask
+    (x - 0x0101010101010101) & (!x & (0x8080808080808080u64 as i64))
 }
 
 #[inline]
@@ -391,7 +489,7 @@ const fn get_decimal_separator_pos(value: i64) -> u32 { i64::trailing_zeros(!val
 /// branches created by Quan Anh Mai.
 ///
 /// https://github.com/gunnarmorling/1brc/blob/db064194be375edc02d6dbcd21268ad40f7e2869/src/main/java/dev/morling/onebrc/CalculateAverage_thomaswue.java#L308
-#[inline]
+#[inline(always)]
 pub const fn to_scaled_integer_branchless(value: i64) -> (i16, i16) {
     let decimal_sep_pos = get_decimal_separator_pos(value) as i32;
     let shift: i32 = 28 - decimal_sep_pos;
@@ -490,13 +588,13 @@ where
     if should_calculate_hash {
         hash = INIT_HASH_VALUE;
     }
-    while i < n - (BUF_SIZE + MAX_MEASUREMENT_LEN) {
+    while i + BUF_SIZE + MAX_MEASUREMENT_LEN < n {
         b0.copy_from_slice(&valid_buffer[i..i + BUF_SIZE]);
         let qw0 = i64::from_le_bytes(b0);
         let sp0 = get_semicolon_pos(qw0);
         if sp0 != 8 {
             if should_calculate_hash {
-                hash = hash ^ clear_bytes_after(qw0 as u64, sp0 as usize);
+                hash ^= clear_bytes_after(qw0 as u64, sp0 as usize);
             }
 
             let end_exclusive = i + sp0 as usize;
@@ -520,7 +618,7 @@ where
         } else {
             i += 8;
             if should_calculate_hash {
-                hash = hash ^ (qw0 as u64);
+                hash ^= qw0 as u64;
             }
         }
     }
@@ -541,6 +639,20 @@ where
 fn process_buffer_memchr_i64<F>(processor: &mut F, buffer: &[u8], valid_len: usize)
 where
     F: FnMut(&[u8], &[u8], i16),
+{
+    process_buffer_memchr_i64_indexed(
+        &mut |_, name, padded_name, value| processor(name, padded_name, value),
+        buffer,
+        valid_len,
+    );
+}
+
+/// [`process_buffer_memchr_i64`] that also passes the index of every record in
+/// `buffer` to `processor`.
+#[inline(always)]
+fn process_buffer_memchr_i64_indexed<F>(processor: &mut F, buffer: &[u8], valid_len: usize)
+where
+    F: FnMut(usize, &[u8], &[u8], i16),
 {
     let valid_buffer = &buffer[..valid_len];
     let mut next_name_idx = 0;
@@ -555,11 +667,73 @@ where
         );
         let (value, record_tail_len) = to_scaled_integer_branchless(packed);
 
-        processor(name, padded_name, value);
+        processor(next_name_idx, name, padded_name, value);
         next_name_idx = measurement_idx + record_tail_len as usize;
     }
 }
 
+/// Parses the record starting at `name_idx`, which must end before `end`, the
+/// same way as [`process_buffer_memchr_i64`] and returns the start of the next
+/// record.
+#[inline(always)]
+fn process_record_memchr_i64<F>(
+    processor: &mut F,
+    buffer: &[u8],
+    name_idx: usize,
+    end: usize,
+) -> usize
+where
+    F: FnMut(&[u8], &[u8], i16),
+{
+    let semicolon_idx = name_idx
+        + memchr::memchr(b';', &buffer[name_idx..end]).expect("Record without a semicolon");
+    let measurement_idx = semicolon_idx + 1;
+    let packed = i64::from_le_bytes(
+        buffer[measurement_idx..measurement_idx + 8]
+            .try_into()
+            .unwrap(),
+    );
+    let (value, record_tail_len) = to_scaled_integer_branchless(packed);
+
+    processor(&buffer[name_idx..semicolon_idx], &buffer[name_idx..], value);
+    measurement_idx + record_tail_len as usize
+}
+
+/// Splits the buffer into `CURSORS` ranges at newline boundaries and advances
+/// one cursor per range in lockstep, as the fastest Java entries do, so that
+/// the loads and mispredicted branches of independent records overlap. Once
+/// the shortest range is exhausted the remaining cursors finish one by one.
+#[inline(always)]
+fn process_buffer_interleaved_i64<const CURSORS: usize, F>(
+    processor: &mut F,
+    buffer: &[u8],
+    valid_len: usize,
+) where
+    F: FnMut(&[u8], &[u8], i16),
+{
+    assert!(CURSORS > 0);
+    let mut cursors = [0_usize; CURSORS];
+    let mut ends = [valid_len; CURSORS];
+    for k in 1..CURSORS {
+        let split = (valid_len * k / CURSORS).max(cursors[k - 1]);
+        let boundary =
+            memchr::memchr(b'\n', &buffer[split..valid_len]).map_or(valid_len, |i| split + i + 1);
+        ends[k - 1] = boundary;
+        cursors[k] = boundary;
+    }
+
+    while cursors.iter().zip(&ends).all(|(cursor, end)| cursor < end) {
+        for k in 0..CURSORS {
+            cursors[k] = process_record_memchr_i64(processor, buffer, cursors[k], ends[k]);
+        }
+    }
+    for k in 0..CURSORS {
+        while cursors[k] < ends[k] {
+            cursors[k] = process_record_memchr_i64(processor, buffer, cursors[k], ends[k]);
+        }
+    }
+}
+
 /// Starts a SIMD delimiter search at each station name. Most names find their
 /// semicolon in the first vector; longer names continue in 32-byte steps.
 #[inline(always)]
@@ -567,19 +741,57 @@ fn process_buffer_std_simd_i64<F>(processor: &mut F, buffer: &[u8], valid_len: u
 where
     F: FnMut(&[u8], &[u8], i16),
 {
-    const LANES: usize = 32;
-    let semicolons = u8x32::splat(b';');
+    process_buffer_semicolon_windows(processor, buffer, valid_len, 32, |window| {
+        let matches = u8x32::from_slice(window)
+            .simd_eq(u8x32::splat(b';'))
+            .to_bitmask();
+        (matches != 0).then(|| matches.trailing_zeros() as usize)
+    });
+}
+
+/// Offset of the first `;` among the 16 bytes of `window`. NEON has no
+/// `movemask`; narrowing the compare result to four bits per byte (`shrn`)
+/// gives a 64-bit mask whose trailing zeros locate the first match.
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn find_semicolon_neon(window: &[u8]) -> Option<usize> {
+    use std::arch::aarch64::*;
+
+    assert!(window.len() >= 16);
+    // SAFETY: `window` has at least 16 readable bytes and NEON is part of the
+    // AArch64 baseline.
+    let nibbles = unsafe {
+        let hits = vceqq_u8(vld1q_u8(window.as_ptr()), vdupq_n_u8(b';'));
+        vget_lane_u64::<0>(vreinterpret_u64_u8(vshrn_n_u16::<4>(vreinterpretq_u16_u8(
+            hits,
+        ))))
+    };
+    (nibbles != 0).then(|| nibbles.trailing_zeros() as usize / 4)
+}
+
+/// Finds each semicolon by scanning windows of `window_len` bytes with
+/// `find`, which returns the offset of the first `;` in a window, and decodes
+/// the measurement branchlessly.
+#[inline(always)]
+fn process_buffer_semicolon_windows<F, S>(
+    processor: &mut F,
+    buffer: &[u8],
+    valid_len: usize,
+    window_len: usize,
+    mut find: S,
+) where
+    F: FnMut(&[u8], &[u8], i16),
+    S: FnMut(&[u8]) -> Option<usize>,
+{
     let mut next_name_idx = 0;
 
     while next_name_idx < valid_len {
         let mut search_idx = next_name_idx;
         let semicolon_idx = loop {
-            let chunk = u8x32::from_slice(&buffer[search_idx..search_idx + LANES]);
-            let matches = chunk.simd_eq(semicolons).to_bitmask();
-            if matches != 0 {
-                break search_idx + matches.trailing_zeros() as usize;
+            if let Some(offset) = find(&buffer[search_idx..search_idx + window_len]) {
+                break search_idx + offset;
             }
-            search_idx += LANES;
+            search_idx += window_len;
         };
 
         let measurement_idx = semicolon_idx + 1;
@@ -618,11 +830,35 @@ fn parse_temperatures_x8(packed: [i64; 8]) -> [i16; 8] {
 }
 
 #[inline(always)]
-fn process_buffer_memchr_simd_temperature<const TABLE_SIZE: usize>(
-    table: &mut FingerprintTable<TABLE_SIZE>,
+fn process_buffer_memchr_simd_temperature<T: StationTable>(
+    table: &mut T,
+    buffer: &[u8],
+    valid_len: usize,
+) {
+    process_buffer_temperature_batches(table, buffer, valid_len, parse_temperatures_x8);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn process_buffer_memchr_simd_temperature_neon<T: StationTable>(
+    table: &mut T,
     buffer: &[u8],
     valid_len: usize,
 ) {
+    process_buffer_temperature_batches(table, buffer, valid_len, parse_temperatures_x8_neon);
+}
+
+/// Finds records with `memchr` and parses their temperatures eight at a time
+/// with `parse`.
+#[inline(always)]
+fn process_buffer_temperature_batches<T: StationTable, P>(
+    table: &mut T,
+    buffer: &[u8],
+    valid_len: usize,
+    parse: P,
+) where
+    P: Fn([i64; 8]) -> [i16; 8],
+{
     const LANES: usize = 8;
     let valid_buffer = &buffer[..valid_len];
     let mut slots = [0_usize; LANES];
@@ -646,7 +882,7 @@ fn process_buffer_memchr_simd_temperature<const TABLE_SIZE: usize>(
         next_name_idx = measurement_idx + record_tail_len;
 
         if batch_len == LANES {
-            let temperatures = parse_temperatures_x8(packed);
+            let temperatures = parse(packed);
             for lane in 0..LANES {
                 table.update_slot(slots[lane], temperatures[lane]);
             }
@@ -655,7 +891,7 @@ fn process_buffer_memchr_simd_temperature<const TABLE_SIZE: usize>(
     }
 
     if batch_len != 0 {
-        let temperatures = parse_temperatures_x8(packed);
+        let temperatures = parse(packed);
         for lane in 0..batch_len {
             table.update_slot(slots[lane], temperatures[lane]);
         }
@@ -668,6 +904,30 @@ fn process_buffer_memchr_full_simd<const TABLE_SIZE: usize>(
     buffer: &[u8],
     valid_len: usize,
 ) {
+    process_buffer_striped_batches(table, buffer, valid_len, parse_temperatures_x8);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn process_buffer_memchr_full_simd_neon<const TABLE_SIZE: usize>(
+    table: &mut SimdFingerprintTable<TABLE_SIZE>,
+    buffer: &[u8],
+    valid_len: usize,
+) {
+    process_buffer_striped_batches(table, buffer, valid_len, parse_temperatures_x8_neon);
+}
+
+/// Finds records with `memchr` and feeds them to the eight-stripe table in
+/// batches, with temperatures parsed by `parse`.
+#[inline(always)]
+fn process_buffer_striped_batches<const TABLE_SIZE: usize, P>(
+    table: &mut SimdFingerprintTable<TABLE_SIZE>,
+    buffer: &[u8],
+    valid_len: usize,
+    parse: P,
+) where
+    P: Fn([i64; 8]) -> [i16; 8],
+{
     const LANES: usize = 8;
     let valid_buffer = &buffer[..valid_len];
     let mut name_ids = [0_usize; LANES];
@@ -690,158 +950,708 @@ fn process_buffer_memchr_full_simd<const TABLE_SIZE: usize>(
         next_name_idx = measurement_idx + record_tail_len;
 
         if batch_len == LANES {
-            table.update_batch(name_ids, parse_temperatures_x8(packed));
+            table.update_batch(name_ids, parse(packed));
             batch_len = 0;
         }
     }
 
     if batch_len != 0 {
-        table.update_partial(name_ids, parse_temperatures_x8(packed), batch_len);
+        table.update_partial(name_ids, parse(packed), batch_len);
     }
 }
 
-#[inline]
-fn process_buffer_as_i64_as_java0<F>(processor: &mut F, valid_buffer: &[u8])
-where
-    F: FnMut(&[u8], i16, u64),
-{
-    const BUF_SIZE: usize = std::mem::size_of::<i64>();
-
-    let n = valid_buffer.len();
-    let mut i: usize = 0;
-    let mut next_name_idx = 0;
-    let mut b0: [u8; BUF_SIZE] = [0_u8; BUF_SIZE];
-
-    #[inline]
-    const fn get_mask(lc: usize) -> u64 {
-        const MASK: [u64; 9] = [
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0xFFFFFFFFFFFFFFFF,
-        ];
-        MASK[lc]
-    }
-    let mut hash: u64 = INIT_HASH_VALUE;
-    while i < n - 3 * BUF_SIZE {
-        let qw0 = {
-            b0.copy_from_slice(&valid_buffer[i..i + BUF_SIZE]);
-            i64::from_le_bytes(b0)
-        };
-        let m0 = get_semicolon_mask(qw0);
-
-        let qw1 = {
-            b0.copy_from_slice(&valid_buffer[i + BUF_SIZE..i + 2 * BUF_SIZE]);
-            i64::from_le_bytes(b0)
-        };
-        let m1 = get_semicolon_mask(qw1);
+/// Parses `N` temperatures in i32 lanes. Each word holds the four bytes that
+/// follow the optional minus sign, i.e. `D.D\n` or `DD.D`, and `signs` is -1
+/// for negative values and 0 otherwise.
+#[inline(always)]
+fn parse_temperatures_i32<const N: usize>(words: [u32; N], signs: [i32; N]) -> [i16; N] {
+    let words = Simd::<i32, N>::from_array(words.map(|w| w as i32));
+    let signs = Simd::<i32, N>::from_array(signs);
+    let digit_mask = Simd::splat(0x0f);
+    let dot_at_1 = ((words >> 8) & Simd::splat(0xff)).simd_eq(Simd::splat(b'.' as i32));
+    // Shift `D.D` into `0D.D` so that hundreds, tens and units always sit in
+    // bytes 0, 1 and 3.
+    let aligned = dot_at_1.select(words << 8, words);
+    let hundreds = aligned & digit_mask;
+    let tens = (aligned >> 8) & digit_mask;
+    let units = (aligned >> 24) & digit_mask;
+    let absolute = hundreds * Simd::splat(100) + tens * Simd::splat(10) + units;
+    ((absolute ^ signs) - signs).cast::<i16>().to_array()
+}
 
-        // https://github.com/gunnarmorling/1brc/blob/main/src/main/java/dev/morling/onebrc/CalculateAverage_thomaswue.java#L201
-        if (m0 | m1) != 0 {
-            let letter_count1 = i64::trailing_zeros(m0) >> 3; // value between 1 and 8
-            let letter_count2 = i64::trailing_zeros(m1) >> 3; // value between 0 and 8
+/// [`parse_temperatures_x8`] for NEON, which has no 64-bit lane multiply:
+/// the minus sign is shifted out of each word and the rest is parsed by
+/// [`parse_temperatures_i32`].
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn parse_temperatures_x8_neon(packed: [i64; 8]) -> [i16; 8] {
+    let negative = packed.map(|v| v as u8 == b'-');
+    let words = std::array::from_fn(|i| (packed[i] >> (8 * negative[i] as u32)) as u32);
+    parse_temperatures_i32(words, negative.map(|n| -(n as i32)))
+}
 
-            hash = hash ^ clear_bytes_after(qw0 as u64, letter_count1 as usize);
+/// Number of bytes classified by one delimiter extraction step.
+const DELIMITER_BLOCK_LEN: usize = 64;
 
-            let len_mask = get_mask(letter_count1 as usize);
+/// Bitmask of the `;` and `\n` bytes among the first `len` bytes of `block`.
+#[inline(always)]
+fn delimiter_mask(block: &[u8], len: usize) -> u64 {
+    let bytes = u8x64::from_slice(&block[..DELIMITER_BLOCK_LEN]);
+    let mask =
+        (bytes.simd_eq(u8x64::splat(b';')) | bytes.simd_eq(u8x64::splat(b'\n'))).to_bitmask();
+    if len < DELIMITER_BLOCK_LEN {
+        mask & ((1 << len) - 1)
+    } else {
+        mask
+    }
+}
 
-            let total_offset = letter_count1 as u64 + (letter_count2 as u64 & len_mask);
-            // println!("i: {i}, qw0: {qw0:#08X}, m0: {m0:#08X}, qw1: {qw1:#08X}, m1:
-            // {m1:#08X}, total_offset: {total_offset}");
+/// Writes the buffer offsets of the delimiters among the first `len` bytes of
+/// `block`, which starts at offset `base`, to `out` and returns their number.
+#[inline(always)]
+fn extract_delimiters(block: &[u8], base: u32, len: usize, out: &mut [u32]) -> usize {
+    let mut mask = delimiter_mask(block, len);
+    let mut n = 0;
+    while mask != 0 {
+        out[n] = base + mask.trailing_zeros();
+        mask &= mask - 1;
+        n += 1;
+    }
+    n
+}
 
-            let end_exclusive = i + total_offset as usize;
-            let name = &valid_buffer[next_name_idx..end_exclusive];
+/// Same as [`extract_delimiters`], but turns the 64-byte compare mask into
+/// offsets with `vpcompressd`, sixteen candidate positions at a time. The
+/// compressed vectors are stored whole, so `out` needs room for
+/// [`DELIMITER_BLOCK_LEN`] offsets.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512bw")]
+fn extract_delimiters_avx512(block: &[u8], base: u32, len: usize, out: &mut [u32]) -> usize {
+    use std::arch::x86_64::*;
+
+    assert!(block.len() >= DELIMITER_BLOCK_LEN && out.len() >= DELIMITER_BLOCK_LEN);
+    // SAFETY: `block` has at least 64 readable bytes.
+    let bytes = unsafe { _mm512_loadu_si512(block.as_ptr().cast()) };
+    let mut mask = _mm512_cmpeq_epi8_mask(bytes, _mm512_set1_epi8(b';' as i8))
+        | _mm512_cmpeq_epi8_mask(bytes, _mm512_set1_epi8(b'\n' as i8));
+    if len < DELIMITER_BLOCK_LEN {
+        mask &= (1 << len) - 1;
+    }
 
-            let start_measurement_idx: usize = end_exclusive + 1;
-            b0.copy_from_slice(
-                &valid_buffer[start_measurement_idx..start_measurement_idx + BUF_SIZE],
-            );
-            let qw1 = i64::from_le_bytes(b0);
-            let (v, len) = to_scaled_integer_branchless(qw1);
+    let mut offsets = _mm512_add_epi32(
+        _mm512_set1_epi32(base as i32),
+        _mm512_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15),
+    );
+    let mut n = 0;
+    for quarter in 0..4 {
+        let quarter_mask = (mask >> (16 * quarter)) as u16;
+        let compressed = _mm512_maskz_compress_epi32(quarter_mask, offsets);
+        // SAFETY: at most 48 offsets precede the last quarter, so 16 more
+        // always fit into the 64 asserted above.
+        unsafe { _mm512_storeu_si512(out[n..].as_mut_ptr().cast(), compressed) };
+        n += quarter_mask.count_ones() as usize;
+        offsets = _mm512_add_epi32(offsets, _mm512_set1_epi32(16));
+    }
+    n
+}
 
-            next_name_idx = start_measurement_idx + len as usize;
-            processor(name, v, hash);
+/// Loads the first 64 bytes of `block` into four NEON vectors.
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn load_block_neon(block: &[u8]) -> [std::arch::aarch64::uint8x16_t; 4] {
+    assert!(block.len() >= 64);
+    // SAFETY: `block` has at least 64 readable bytes and NEON is part of the
+    // AArch64 baseline.
+    std::array::from_fn(|i| unsafe { std::arch::aarch64::vld1q_u8(block.as_ptr().add(16 * i)) })
+}
 
-            i = next_name_idx;
+/// Folds the four 16-byte compare results of a 64-byte block into the 64-bit
+/// mask `movemask` gives on x86. NEON has no `movemask`, so each byte is
+/// weighted with its bit position within the byte and three rounds of
+/// pairwise additions sum the weights.
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn fold_mask_neon(hits: [std::arch::aarch64::uint8x16_t; 4]) -> u64 {
+    use std::arch::aarch64::*;
+
+    const WEIGHTS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+
+    // SAFETY: NEON is part of the AArch64 baseline.
+    unsafe {
+        let weights = vld1q_u8(WEIGHTS.as_ptr());
+        let [a, b, c, d] = hits.map(|h| vandq_u8(h, weights));
+        let sum = vpaddq_u8(vpaddq_u8(a, b), vpaddq_u8(c, d));
+        let sum = vpaddq_u8(sum, sum);
+        vgetq_lane_u64::<0>(vreinterpretq_u64_u8(sum))
+    }
+}
 
-            hash = INIT_HASH_VALUE;
-        } else {
-            i += 16;
-            hash = hash ^ (qw0 as u64);
-            hash = hash ^ (qw1 as u64);
+/// NEON version of [`delimiter_mask`].
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn delimiter_mask_neon(block: &[u8], len: usize) -> u64 {
+    use std::arch::aarch64::*;
+
+    // SAFETY: NEON is part of the AArch64 baseline.
+    let hits = unsafe {
+        let (semicolons, newlines) = (vdupq_n_u8(b';'), vdupq_n_u8(b'\n'));
+        load_block_neon(block)
+            .map(|bytes| vorrq_u8(vceqq_u8(bytes, semicolons), vceqq_u8(bytes, newlines)))
+    };
+    let mut mask = fold_mask_neon(hits);
+    if len < DELIMITER_BLOCK_LEN {
+        mask &= (1 << len) - 1;
+    }
+    mask
+}
 
-            while i < n - BUF_SIZE {
-                let qw0 = {
-                    b0.copy_from_slice(&valid_buffer[i..i + BUF_SIZE]);
-                    i64::from_le_bytes(b0)
-                };
-                let m0 = get_semicolon_mask(qw0);
-                if m0 != 0 {
-                    break;
-                } else {
-                    i += 8;
-                }
-            }
-        }
+/// Same as [`extract_delimiters`], with the mask built by
+/// [`delimiter_mask_neon`].
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn extract_delimiters_neon(block: &[u8], base: u32, len: usize, out: &mut [u32]) -> usize {
+    let mut mask = delimiter_mask_neon(block, len);
+    let mut n = 0;
+    while mask != 0 {
+        out[n] = base + mask.trailing_zeros();
+        mask &= mask - 1;
+        n += 1;
     }
-    // Handle remaining
-    process_buffer_as_bytes(processor, valid_buffer, i, n, next_name_idx, false);
+    n
 }
 
-#[inline]
-fn process_buffer_as_i64_unsafe<F>(processor: &mut F, valid_buffer: &[u8])
-where
-    F: FnMut(&[u8], i16, u64),
+/// Walks the buffer in 64-byte blocks, turns each block into delimiter
+/// offsets with `extract`, and feeds the records to the 16-stripe table in
+/// batches of sixteen. Records alternate `;` and `\n`, so offsets are consumed
+/// in pairs and a trailing semicolon is carried into the next block.
+#[inline(always)]
+fn process_buffer_delimiters_x16<const TABLE_SIZE: usize, E>(
+    table: &mut SimdFingerprintTable<TABLE_SIZE, 16>,
+    buffer: &[u8],
+    valid_len: usize,
+    mut extract: E,
+) where
+    E: FnMut(&[u8], u32, usize, &mut [u32]) -> usize,
 {
-    let n = valid_buffer.len();
-    let mut i: usize = 0;
+    const LANES: usize = 16;
+    let mut offsets = [0_u32; DELIMITER_BLOCK_LEN + 1];
+    let mut carried = 0;
+    let mut name_ids = [0_usize; LANES];
+    let mut words = [0_u32; LANES];
+    let mut signs = [0_i32; LANES];
+    let mut batch_len = 0;
     let mut next_name_idx = 0;
-    let mut ptr: *const u8 = valid_buffer.as_ptr();
-
-    while i < n - 16 {
-        let qw0 = unsafe { (ptr as *const i64).read_unaligned() };
-        let sp0 = get_semicolon_pos(qw0);
-        // println!("i: {i}, qw0: {qw0:#08X}, sp0: {sp0}");
-        if sp0 != 8 {
-            let end_exclusive = i + sp0 as usize;
-            let name = &valid_buffer[next_name_idx..end_exclusive];
-            ptr = unsafe { ptr.add((sp0 + 1) as usize) };
-            // println!("ptr: {ptr:?}");
 
-            let start_measurement_idx: usize = end_exclusive + 1;
-            let qw1 = unsafe { (ptr as *const i64).read_unaligned() };
-            // println!("i: {i}, qw0: {qw1:#08X}");
-            let (v, len) = to_scaled_integer_branchless(qw1);
-
-            next_name_idx = start_measurement_idx + len as usize;
-            processor(name, v, 0);
+    let mut block_start = 0;
+    while block_start < valid_len {
+        let len = (valid_len - block_start).min(DELIMITER_BLOCK_LEN);
+        let n = carried
+            + extract(
+                &buffer[block_start..],
+                block_start as u32,
+                len,
+                &mut offsets[carried..],
+            );
 
-            ptr = unsafe { ptr.add(len as usize) };
+        let mut i = 0;
+        while i + 1 < n {
+            let semicolon_idx = offsets[i] as usize;
+            let measurement_idx = semicolon_idx + 1;
+            let negative = buffer[measurement_idx] == b'-';
+            let digits_idx = measurement_idx + negative as usize;
+
+            let name = &buffer[next_name_idx..semicolon_idx];
+            name_ids[batch_len] = table.find_or_insert(name, &buffer[next_name_idx..]);
+            words[batch_len] =
+                u32::from_le_bytes(buffer[digits_idx..digits_idx + 4].try_into().unwrap());
+            signs[batch_len] = -(negative as i32);
+            batch_len += 1;
+            next_name_idx = offsets[i + 1] as usize + 1;
+
+            if batch_len == LANES {
+                table.update_batch(name_ids, parse_temperatures_i32(words, signs));
+                batch_len = 0;
+            }
+            i += 2;
+        }
 
-            i = next_name_idx;
-        } else {
-            ptr = unsafe { ptr.add(8) };
-            i += 8;
+        carried = n - i;
+        if carried != 0 {
+            offsets[0] = offsets[i];
         }
+        block_start += DELIMITER_BLOCK_LEN;
+    }
+
+    if batch_len != 0 {
+        table.update_partial(name_ids, parse_temperatures_i32(words, signs), batch_len);
     }
-    // Handle remaining
-    process_buffer_as_bytes(processor, valid_buffer, i, n, next_name_idx, false);
 }
 
-pub const DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER: usize = 128 * 1024 * 1024;
+#[inline(always)]
+fn process_buffer_bitmask_x16<const TABLE_SIZE: usize>(
+    table: &mut SimdFingerprintTable<TABLE_SIZE, 16>,
+    buffer: &[u8],
+    valid_len: usize,
+) {
+    process_buffer_delimiters_x16(table, buffer, valid_len, extract_delimiters);
+}
 
-/// Reads from provided buffered reader in large chunks, parses it line by line,
-/// finds station name and temperature and calls processor with found byte
-/// slices.
-///
-/// This is around 2.7 times faster than [`naive_line_by_line`].
-#[inline]
-fn parse_large_chunks_as_bytes0<R: Read + Seek, F>(
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512bw")]
+fn process_buffer_compress_x16<const TABLE_SIZE: usize>(
+    table: &mut SimdFingerprintTable<TABLE_SIZE, 16>,
+    buffer: &[u8],
+    valid_len: usize,
+) {
+    process_buffer_delimiters_x16(table, buffer, valid_len, |block, base, len, out| {
+        extract_delimiters_avx512(block, base, len, out)
+    });
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn process_buffer_neon_x16<const TABLE_SIZE: usize>(
+    table: &mut SimdFingerprintTable<TABLE_SIZE, 16>,
+    buffer: &[u8],
+    valid_len: usize,
+) {
+    process_buffer_delimiters_x16(table, buffer, valid_len, extract_delimiters_neon);
+}
+
+#[inline(always)]
+fn process_buffer_memchr_table<T: StationTable>(table: &mut T, buffer: &[u8], valid_len: usize) {
+    process_buffer_memchr_i64(
+        &mut |name, padded_name, value| table.insert_or_update(name, padded_name, value),
+        buffer,
+        valid_len,
+    );
+}
+
+#[inline(always)]
+fn process_buffer_interleaved_table<const TABLE_SIZE: usize, const CURSORS: usize>(
+    table: &mut FingerprintTable<TABLE_SIZE>,
+    buffer: &[u8],
+    valid_len: usize,
+) {
+    process_buffer_interleaved_i64::<CURSORS, _>(
+        &mut |name, padded_name, value| table.insert_or_update(name, padded_name, value),
+        buffer,
+        valid_len,
+    );
+}
+
+#[inline(always)]
+fn process_buffer_std_simd_table<T: StationTable>(table: &mut T, buffer: &[u8], valid_len: usize) {
+    process_buffer_std_simd_i64(
+        &mut |name, padded_name, value| table.insert_or_update(name, padded_name, value),
+        buffer,
+        valid_len,
+    );
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn process_buffer_std_simd_table_neon<T: StationTable>(
+    table: &mut T,
+    buffer: &[u8],
+    valid_len: usize,
+) {
+    process_buffer_semicolon_windows(
+        &mut |name, padded_name, value| table.insert_or_update(name, padded_name, value),
+        buffer,
+        valid_len,
+        16,
+        find_semicolon_neon,
+    );
+}
+
+/// Number of bytes covered by one [`StructuralIndex`].
+const STRUCTURAL_BLOCK_LEN: usize = 64 * 1024;
+
+const STRUCTURAL_INDEX_WORDS: usize = STRUCTURAL_BLOCK_LEN / 64;
+
+/// simdjson-style structural index of one block: bit `i` of word `w` is set
+/// when byte `64 * w + i` of the block is a `;`, respectively a `\n`.
+struct StructuralIndex {
+    semicolons: [u64; STRUCTURAL_INDEX_WORDS],
+    newlines: [u64; STRUCTURAL_INDEX_WORDS],
+    words: usize,
+}
+
+impl StructuralIndex {
+    fn new() -> Self {
+        Self {
+            semicolons: [0; STRUCTURAL_INDEX_WORDS],
+            newlines: [0; STRUCTURAL_INDEX_WORDS],
+            words: 0,
+        }
+    }
+
+    /// Stage one: classifies the first `len` bytes of `block` 64 bytes at a
+    /// time with `classify`, which returns the `;` and `\n` masks of 64
+    /// bytes. `block` must be readable up to the next multiple of 64.
+    #[inline(always)]
+    fn build<C>(&mut self, block: &[u8], len: usize, classify: C)
+    where
+        C: Fn(&[u8]) -> (u64, u64),
+    {
+        debug_assert!(len <= STRUCTURAL_BLOCK_LEN);
+        self.words = len.div_ceil(64);
+        for w in 0..self.words {
+            (self.semicolons[w], self.newlines[w]) = classify(&block[64 * w..64 * w + 64]);
+        }
+        if !len.is_multiple_of(64) {
+            let tail_mask = (1 << (len % 64)) - 1;
+            self.semicolons[self.words - 1] &= tail_mask;
+            self.newlines[self.words - 1] &= tail_mask;
+        }
+    }
+}
+
+/// The fingerprint table of the structural parser with its index, which is
+/// reused for every block instead of being zeroed per buffer.
+struct StructuralTable<T> {
+    table: T,
+    index: StructuralIndex,
+}
+
+impl<T: StationTable> StructuralTable<T> {
+    fn new(table: T) -> Self {
+        Self {
+            table,
+            index: StructuralIndex::new(),
+        }
+    }
+}
+
+/// The `;` and `\n` masks of 64 bytes, for [`StructuralIndex::build`].
+#[inline(always)]
+fn classify_structural(bytes: &[u8]) -> (u64, u64) {
+    let bytes = u8x64::from_slice(bytes);
+    (
+        bytes.simd_eq(u8x64::splat(b';')).to_bitmask(),
+        bytes.simd_eq(u8x64::splat(b'\n')).to_bitmask(),
+    )
+}
+
+/// NEON version of [`classify_structural`], sharing the loads of both masks.
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn classify_structural_neon(bytes: &[u8]) -> (u64, u64) {
+    use std::arch::aarch64::*;
+
+    let block = load_block_neon(bytes);
+    // SAFETY: NEON is part of the AArch64 baseline.
+    let (semicolons, newlines) = unsafe {
+        let (semicolons, newlines) = (vdupq_n_u8(b';'), vdupq_n_u8(b'\n'));
+        (
+            block.map(|bytes| vceqq_u8(bytes, semicolons)),
+            block.map(|bytes| vceqq_u8(bytes, newlines)),
+        )
+    };
+    (fold_mask_neon(semicolons), fold_mask_neon(newlines))
+}
+
+/// Iterates the set bits of a bitmap in increasing order with `tzcnt`.
+struct BitCursor<'a> {
+    words: &'a [u64],
+    word_idx: usize,
+    bits: u64,
+}
+
+impl<'a> BitCursor<'a> {
+    #[inline(always)]
+    fn new(words: &'a [u64]) -> Self {
+        Self {
+            words,
+            word_idx: 0,
+            bits: words.first().copied().unwrap_or(0),
+        }
+    }
+
+    #[inline(always)]
+    fn next_position(&mut self) -> Option<usize> {
+        while self.bits == 0 {
+            self.word_idx += 1;
+            self.bits = *self.words.get(self.word_idx)?;
+        }
+        let position = 64 * self.word_idx + self.bits.trailing_zeros() as usize;
+        self.bits &= self.bits - 1;
+        Some(position)
+    }
+}
+
+#[inline(always)]
+fn process_buffer_structural_table<T: StationTable>(
+    table: &mut StructuralTable<T>,
+    buffer: &[u8],
+    valid_len: usize,
+) {
+    process_buffer_structural(table, buffer, valid_len, classify_structural);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn process_buffer_structural_table_neon<T: StationTable>(
+    table: &mut StructuralTable<T>,
+    buffer: &[u8],
+    valid_len: usize,
+) {
+    process_buffer_structural(table, buffer, valid_len, classify_structural_neon);
+}
+
+/// Two-stage parsing: [`StructuralIndex::build`] classifies a whole
+/// [`STRUCTURAL_BLOCK_LEN`] block with `classify`, then the `;` and `\n`
+/// bitmaps are walked in lockstep to cut name and measurement slices. A record
+/// whose semicolon is in one block and newline in the next is finished in the
+/// next block.
+#[inline(always)]
+fn process_buffer_structural<T: StationTable, C>(
+    structural: &mut StructuralTable<T>,
+    buffer: &[u8],
+    valid_len: usize,
+    classify: C,
+) where
+    C: Fn(&[u8]) -> (u64, u64),
+{
+    let StructuralTable { table, index } = structural;
+    let mut next_name_idx = 0;
+    let mut pending_semicolon = None;
+
+    let mut block_start = 0;
+    while block_start < valid_len {
+        let len = (valid_len - block_start).min(STRUCTURAL_BLOCK_LEN);
+        index.build(&buffer[block_start..], len, &classify);
+
+        let mut semicolons = BitCursor::new(&index.semicolons[..index.words]);
+        let mut newlines = BitCursor::new(&index.newlines[..index.words]);
+        while let Some(semicolon_idx) = pending_semicolon
+            .take()
+            .or_else(|| Some(block_start + semicolons.next_position()?))
+        {
+            let Some(newline_idx) = newlines.next_position() else {
+                pending_semicolon = Some(semicolon_idx);
+                break;
+            };
+            let newline_idx = block_start + newline_idx;
+
+            let name = &buffer[next_name_idx..semicolon_idx];
+            let measurement_idx = semicolon_idx + 1;
+            let packed = i64::from_le_bytes(
+                buffer[measurement_idx..measurement_idx + 8]
+                    .try_into()
+                    .unwrap(),
+            );
+            let (value, _) = to_scaled_integer_branchless(packed);
+            table.insert_or_update(name, &buffer[next_name_idx..], value);
+            next_name_idx = newline_idx + 1;
+        }
+        block_start += STRUCTURAL_BLOCK_LEN;
+    }
+}
+
+multiversion! {
+    /// Runs [`process_buffer_memchr_table`] compiled for `kernel`.
+    fn dispatch_memchr_table<T: StationTable>(&mut T) => process_buffer_memchr_table
+}
+
+multiversion! {
+    /// Runs [`process_buffer_interleaved_table`] compiled for `kernel`.
+    fn dispatch_interleaved_table<const CURSORS: usize>(&mut FingerprintTable<SIZE>)
+        => process_buffer_interleaved_table::<SIZE, CURSORS>
+}
+
+multiversion! {
+    /// Runs [`process_buffer_std_simd_table`] compiled for `kernel`, with a
+    /// `shrn` semicolon search on [`Kernel::Neon`].
+    fn dispatch_std_simd_table<T: StationTable>(&mut T) => process_buffer_std_simd_table,
+        avx512: process_buffer_std_simd_table,
+        neon: process_buffer_std_simd_table_neon
+}
+
+multiversion! {
+    /// Runs [`process_buffer_memchr_simd_temperature`] compiled for `kernel`,
+    /// parsing temperatures in i32 lanes on [`Kernel::Neon`].
+    fn dispatch_simd_temperature_table<T: StationTable>(&mut T) => process_buffer_memchr_simd_temperature,
+        avx512: process_buffer_memchr_simd_temperature,
+        neon: process_buffer_memchr_simd_temperature_neon
+}
+
+multiversion! {
+    /// Runs [`process_buffer_memchr_full_simd`] compiled for `kernel`, parsing
+    /// temperatures in i32 lanes on [`Kernel::Neon`].
+    fn dispatch_full_simd_table(&mut SimdFingerprintTable<SIZE>) => process_buffer_memchr_full_simd,
+        avx512: process_buffer_memchr_full_simd,
+        neon: process_buffer_memchr_full_simd_neon
+}
+
+multiversion! {
+    /// Runs [`process_buffer_structural_table`] compiled for `kernel`, with
+    /// NEON compare masks on [`Kernel::Neon`].
+    fn dispatch_structural_table<T: StationTable>(&mut StructuralTable<T>) => process_buffer_structural_table,
+        avx512: process_buffer_structural_table,
+        neon: process_buffer_structural_table_neon
+}
+
+multiversion! {
+    /// Runs the 16-lane pipeline compiled for `kernel`, with `vpcompressd`
+    /// delimiter extraction on [`Kernel::Avx512`] and NEON compare masks on
+    /// [`Kernel::Neon`].
+    fn dispatch_avx512_table(&mut SimdFingerprintTable<SIZE, 16>) => process_buffer_bitmask_x16,
+        avx512: process_buffer_compress_x16,
+        neon: process_buffer_neon_x16
+}
+
+#[inline]
+fn process_buffer_as_i64_as_java0<F>(processor: &mut F, valid_buffer: &[u8])
+where
+    F: FnMut(&[u8], i16, u64),
+{
+    const BUF_SIZE: usize = std::mem::size_of::<i64>();
+
+    let n = valid_buffer.len();
+    let mut i: usize = 0;
+    let mut next_name_idx = 0;
+    let mut b0: [u8; BUF_SIZE] = [0_u8; BUF_SIZE];
+
+    #[inline]
+    const fn get_mask(lc: usize) -> u64 {
+        const MASK: [u64; 9] = [
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0xFFFFFFFFFFFFFFFF,
+        ];
+        MASK[lc]
+    }
+    let mut hash: u64 = INIT_HASH_VALUE;
+    while i + 3 * BUF_SIZE < n {
+        let qw0 = {
+            b0.copy_from_slice(&valid_buffer[i..i + BUF_SIZE]);
+            i64::from_le_bytes(b0)
+        };
+        let m0 = get_semicolon_mask(qw0);
+
+        let qw1 = {
+            b0.copy_from_slice(&valid_buffer[i + BUF_SIZE..i + 2 * BUF_SIZE]);
+            i64::from_le_bytes(b0)
+        };
+        let m1 = get_semicolon_mask(qw1);
+
+        // https://github.com/gunnarmorling/1brc/blob/main/src/main/java/dev/morling/onebrc/CalculateAverage_thomaswue.java#L201
+        if (m0 | m1) != 0 {
+            let letter_count1 = i64::trailing_zeros(m0) >> 3; // value between 1 and 8
+            let letter_count2 = i64::trailing_zeros(m1) >> 3; // value between 0 and 8
+
+            hash ^= clear_bytes_after(qw0 as u64, letter_count1 as usize);
+
+            let len_mask = get_mask(letter_count1 as usize);
+
+            let total_offset = letter_count1 as u64 + (letter_count2 as u64 & len_mask);
+            // println!("i: {i}, qw0: {qw0:#08X}, m0: {m0:#08X}, qw1: {qw1:#08X}, m1:
+            // {m1:#08X}, total_offset: {total_offset}");
+
+            let end_exclusive = i + total_offset as usize;
+            let name = &valid_buffer[next_name_idx..end_exclusive];
+
+            let start_measurement_idx: usize = end_exclusive + 1;
+            b0.copy_from_slice(
+                &valid_buffer[start_measurement_idx..start_measurement_idx + BUF_SIZE],
+            );
+            let qw1 = i64::from_le_bytes(b0);
+            let (v, len) = to_scaled_integer_branchless(qw1);
+
+            next_name_idx = start_measurement_idx + len as usize;
+            processor(name, v, hash);
+
+            i = next_name_idx;
+
+            hash = INIT_HASH_VALUE;
+        } else {
+            i += 16;
+            hash ^= qw0 as u64;
+            hash ^= qw1 as u64;
+
+            while i < n - BUF_SIZE {
+                let qw0 = {
+                    b0.copy_from_slice(&valid_buffer[i..i + BUF_SIZE]);
+                    i64::from_le_bytes(b0)
+                };
+                let m0 = get_semicolon_mask(qw0);
+                if m0 != 0 {
+                    break;
+                } else {
+                    i += 8;
+                }
+            }
+        }
+    }
+    // Handle remaining, with the hashes of the loop above so that the table
+    // finds the stations it already holds
+    process_buffer_as_bytes(
+        &mut |name: &[u8], v: i16, _: u64| processor(name, v, calculate_hash_as_java(name)),
+        valid_buffer,
+        i,
+        n,
+        next_name_idx,
+        false,
+    );
+}
+
+#[inline]
+fn process_buffer_as_i64_unsafe<F>(processor: &mut F, valid_buffer: &[u8])
+where
+    F: FnMut(&[u8], i16, u64),
+{
+    let n = valid_buffer.len();
+    let mut i: usize = 0;
+    let mut next_name_idx = 0;
+    let mut ptr: *const u8 = valid_buffer.as_ptr();
+
+    while i + 16 < n {
+        let qw0 = i64::from_le(unsafe { (ptr as *const i64).read_unaligned() });
+        let sp0 = get_semicolon_pos(qw0);
+        // println!("i: {i}, qw0: {qw0:#08X}, sp0: {sp0}");
+        if sp0 != 8 {
+            let end_exclusive = i + sp0 as usize;
+            let name = &valid_buffer[next_name_idx..end_exclusive];
+            ptr = unsafe { ptr.add((sp0 + 1) as usize) };
+            // println!("ptr: {ptr:?}");
+
+            let start_measurement_idx: usize = end_exclusive + 1;
+            let qw1 = i64::from_le(unsafe { (ptr as *const i64).read_unaligned() });
+            // println!("i: {i}, qw0: {qw1:#08X}");
+            let (v, len) = to_scaled_integer_branchless(qw1);
+
+            next_name_idx = start_measurement_idx + len as usize;
+            processor(name, v, 0);
+
+            ptr = unsafe { ptr.add(len as usize) };
+
+            i = next_name_idx;
+        } else {
+            ptr = unsafe { ptr.add(8) };
+            i += 8;
+        }
+    }
+    // Handle remaining
+    process_buffer_as_bytes(processor, valid_buffer, i, n, next_name_idx, false);
+}
+
+pub const DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER: usize = 128 * 1024 * 1024;
+
+/// Reads from provided buffered reader in large chunks, parses it line by line,
+/// finds station name and temperature and calls processor with found byte
+/// slices.
+///
+/// This is around 2.7 times faster than [`naive_line_by_line`].
+#[inline]
+fn parse_large_chunks_as_bytes0<R: Read + Seek, F>(
     mut rdr: BufReader<R>,
     mut processor: F,
     start: u64,
@@ -855,9 +1665,9 @@ fn parse_large_chunks_as_bytes0<R: Read + Seek, F>(
     rdr.seek(SeekFrom::Start(start)).unwrap();
 
     let mut vec: Vec<u8> = vec![0; buffer_size];
-    let mut buf = vec.as_mut_slice();
+    let buf = vec.as_mut_slice();
     while offset <= end_incl_usize {
-        let mut read_bytes = rdr.read(&mut buf).expect("Unable to read line");
+        let mut read_bytes = read_buffer(&mut rdr, buf);
         if read_bytes == 0 {
             break;
         }
@@ -865,7 +1675,7 @@ fn parse_large_chunks_as_bytes0<R: Read + Seek, F>(
         if remaining < buf.len() {
             read_bytes = remaining;
         }
-        let valid_buffer = seek_backward_to_newline(&mut rdr, &buf, read_bytes);
+        let valid_buffer = seek_backward_to_newline(&mut rdr, buf, read_bytes);
         process_buffer_as_bytes(
             &mut processor,
             valid_buffer,
@@ -895,9 +1705,9 @@ fn parse_large_chunks_as_i64_0<R: Read + Seek, F>(
     rdr.seek(SeekFrom::Start(start)).unwrap();
 
     let mut vec: Vec<u8> = vec![0; buffer_size];
-    let mut buf = vec.as_mut_slice();
+    let buf = vec.as_mut_slice();
     while offset <= end_incl_usize {
-        let mut read_bytes = rdr.read(&mut buf).expect("Unable to read line");
+        let mut read_bytes = read_buffer(&mut rdr, buf);
         if read_bytes == 0 {
             break;
         }
@@ -906,7 +1716,7 @@ fn parse_large_chunks_as_i64_0<R: Read + Seek, F>(
             read_bytes = remaining;
         }
 
-        let valid_buffer = seek_backward_to_newline(&mut rdr, &buf, read_bytes);
+        let valid_buffer = seek_backward_to_newline(&mut rdr, buf, read_bytes);
         // println!("Read {read_bytes}, valid_buffer: {}", valid_buffer.len());
         process_buffer_as_i64(&mut processor, valid_buffer, should_calculate_hash);
         offset += valid_buffer.len();
@@ -927,9 +1737,9 @@ fn parse_large_chunks_as_i64_unsafe_0<R: Read + Seek, F>(
     rdr.seek(SeekFrom::Start(start)).unwrap();
 
     let mut vec: Vec<u8> = vec![0; buffer_size];
-    let mut buf = vec.as_mut_slice();
+    let buf = vec.as_mut_slice();
     while offset <= end_incl_usize {
-        let mut read_bytes = rdr.read(&mut buf).expect("Unable to read line");
+        let mut read_bytes = read_buffer(&mut rdr, buf);
         if read_bytes == 0 {
             break;
         }
@@ -937,7 +1747,7 @@ fn parse_large_chunks_as_i64_unsafe_0<R: Read + Seek, F>(
         if remaining < buf.len() {
             read_bytes = remaining;
         }
-        let valid_buffer = seek_backward_to_newline(&mut rdr, &buf, read_bytes);
+        let valid_buffer = seek_backward_to_newline(&mut rdr, buf, read_bytes);
         process_buffer_as_i64_unsafe(&mut processor, valid_buffer);
         offset += valid_buffer.len();
     }
@@ -957,9 +1767,9 @@ fn parse_large_chunks_as_i64_as_java0<R: Read + Seek, F>(
     rdr.seek(SeekFrom::Start(start)).unwrap();
 
     let mut vec: Vec<u8> = vec![0; buffer_size];
-    let mut buf = vec.as_mut_slice();
+    let buf = vec.as_mut_slice();
     while offset <= end_incl_usize {
-        let mut read_bytes = rdr.read(&mut buf).expect("Unable to read line");
+        let mut read_bytes = read_buffer(&mut rdr, buf);
         if read_bytes == 0 {
             break;
         }
@@ -967,7 +1777,7 @@ fn parse_large_chunks_as_i64_as_java0<R: Read + Seek, F>(
         if remaining < buf.len() {
             read_bytes = remaining;
         }
-        let valid_buffer = seek_backward_to_newline(&mut rdr, &buf, read_bytes);
+        let valid_buffer = seek_backward_to_newline(&mut rdr, buf, read_bytes);
         process_buffer_as_i64_as_java0(&mut processor, valid_buffer);
         offset += valid_buffer.len();
     }
@@ -978,7 +1788,7 @@ pub fn parse_large_chunks_as_bytes_dummy<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     _should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut dummy_result: usize = 0;
     parse_large_chunks_as_bytes0(
         rdr,
@@ -990,8 +1800,10 @@ pub fn parse_large_chunks_as_bytes_dummy<R: Read + Seek>(
         DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
     );
 
-    let mut s = StateF::default();
-    s.count = dummy_result as u32;
+    let s = StateI {
+        count: dummy_result as u64,
+        ..Default::default()
+    };
     vec![("dummy".to_string(), s)]
 }
 
@@ -1006,7 +1818,7 @@ pub fn parse_large_chunks_as_bytes<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut hs: FxHashMap<Vec<u8>, StateI> =
         FxHashMap::with_capacity_and_hasher(DEFAULT_HASHMAP_CAPACITY, Default::default());
     parse_large_chunks_as_bytes0(
@@ -1021,9 +1833,9 @@ pub fn parse_large_chunks_as_bytes<R: Read + Seek>(
         end_inclusive,
         DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
     );
-    let mut all: Vec<(String, StateF)> = hs
+    let mut all: Vec<(String, StateI)> = hs
         .into_iter()
-        .map(|(k, v)| (byte_to_string_unsafe(k.as_slice()).to_string(), v.to_f64()))
+        .map(|(k, v)| (byte_to_string_unsafe(k.as_slice()).to_string(), v.clone()))
         .collect();
     if should_sort {
         sort_result(&mut all);
@@ -1036,7 +1848,7 @@ pub fn parse_large_chunks_as_i64_dummy<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     _should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut dummy_result: usize = 0;
     parse_large_chunks_as_i64_0(
         rdr,
@@ -1049,8 +1861,10 @@ pub fn parse_large_chunks_as_i64_dummy<R: Read + Seek>(
         false,
     );
 
-    let mut s = StateF::default();
-    s.count = dummy_result as u32;
+    let s = StateI {
+        count: dummy_result as u64,
+        ..Default::default()
+    };
     vec![("dummy".to_string(), s)]
 }
 
@@ -1065,7 +1879,7 @@ pub fn parse_large_chunks_as_i64<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut hs: FxHashMap<Vec<u8>, StateI> =
         FxHashMap::with_capacity_and_hasher(DEFAULT_HASHMAP_CAPACITY, Default::default());
     parse_large_chunks_as_i64_0(
@@ -1081,9 +1895,9 @@ pub fn parse_large_chunks_as_i64<R: Read + Seek>(
         DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
         false,
     );
-    let mut all: Vec<(String, StateF)> = hs
+    let mut all: Vec<(String, StateI)> = hs
         .into_iter()
-        .map(|(k, v)| (byte_to_string_unsafe(k.as_slice()).to_string(), v.to_f64()))
+        .map(|(k, v)| (byte_to_string_unsafe(k.as_slice()).to_string(), v.clone()))
         .collect();
     if should_sort {
         sort_result(&mut all);
@@ -1093,29 +1907,69 @@ pub fn parse_large_chunks_as_i64<R: Read + Seek>(
 
 /// Vectorized semicolon discovery plus branchless packed temperature parsing,
 /// backed by a compact station fingerprint table.
+///
+/// Like the other `*_table` implementations, the buffer loop runs in the
+/// build for [`Kernel::selected`].
 pub fn parse_large_chunks_memchr_table<R: Read + Seek>(
-    mut rdr: BufReader<R>,
+    rdr: BufReader<R>,
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
-    const TABLE_SIZE: usize = 16384;
+) -> Vec<(String, StateI)> {
+    parse_large_chunks_memchr_table_sized::<16384, R>(rdr, start, end_inclusive, should_sort)
+}
+
+/// [`parse_large_chunks_memchr_table`] with a table of `TABLE_SIZE` slots, a
+/// power of two. The table panics when the input has `TABLE_SIZE - 1` or more
+/// stations.
+pub fn parse_large_chunks_memchr_table_sized<const TABLE_SIZE: usize, R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+) -> Vec<(String, StateI)> {
     let mut table = FingerprintTable::<TABLE_SIZE>::new();
+    let kernel = Kernel::selected();
+    // Padding permits safe fixed-width loads for the final name and measurement.
+    parse_buffers::<8, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        dispatch_memchr_table(kernel, &mut table, buffer, valid_len)
+    });
+
+    let mut all = table.into_result();
+    if should_sort {
+        sort_result(&mut all);
+    }
+    all
+}
+
+/// Reads `[start, end_inclusive]` of `rdr` in buffers of up to
+/// [`DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER`] bytes cut after their last
+/// newline, each followed by `PADDING` bytes, and passes every buffer with the
+/// length of its complete records and its offset in the file to `process`, in
+/// file order. This is the chunk loop of the `*_table` parsers and of the
+/// aggregations built on them.
+fn parse_buffers<const PADDING: usize, R: Read + Seek, P>(
+    mut rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    mut process: P,
+) where
+    P: FnMut(&[u8], usize, u64),
+{
     let end_incl_usize = end_inclusive as usize;
     let mut offset = start as usize;
     rdr.seek(SeekFrom::Start(start)).unwrap();
 
-    // Padding permits safe fixed-width loads for the final name and measurement.
-    let mut storage = vec![0; DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER + 8];
+    let mut storage = vec![0; DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER + PADDING];
     while offset <= end_incl_usize {
-        let mut read_bytes = rdr
-            .read(&mut storage[..DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER])
-            .expect("Unable to read chunk");
+        let read_bytes = read_chunk(
+            &mut rdr,
+            &mut storage[..DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER],
+            end_incl_usize - offset + 1,
+        );
         if read_bytes == 0 {
             break;
         }
-        let remaining = end_incl_usize - offset + 1;
-        read_bytes = read_bytes.min(remaining);
         let valid_len = seek_backward_to_newline(
             &mut rdr,
             &storage[..DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER],
@@ -1123,13 +1977,28 @@ pub fn parse_large_chunks_memchr_table<R: Read + Seek>(
         )
         .len();
 
-        process_buffer_memchr_i64(
-            &mut |name, padded_name, value| table.insert_or_update(name, padded_name, value),
-            &storage,
-            valid_len,
-        );
+        process(&storage, valid_len, offset as u64);
         offset += valid_len;
     }
+}
+
+/// [`parse_large_chunks_memchr_table`] aggregating every station with a custom
+/// [`Accumulator`] instead of [`StateI`], on the same buffer loop and kernel.
+/// Results of chunks are merged with [`Accumulator::merge`] in file order and
+/// then reported through [`finalize_result`].
+pub fn parse_large_chunks_accumulate<A: Accumulator, R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+) -> Vec<(String, A)> {
+    const TABLE_SIZE: usize = 16384;
+    let mut table = FingerprintTable::<TABLE_SIZE, A>::new();
+    let kernel = Kernel::selected();
+    // Padding permits safe fixed-width loads for the final name and measurement.
+    parse_buffers::<8, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        dispatch_memchr_table(kernel, &mut table, buffer, valid_len)
+    });
 
     let mut all = table.into_result();
     if should_sort {
@@ -1138,45 +2007,299 @@ pub fn parse_large_chunks_memchr_table<R: Read + Seek>(
     all
 }
 
-/// Portable-SIMD semicolon discovery plus branchless packed temperature
-/// parsing, backed by the compact station fingerprint table.
-pub fn parse_large_chunks_std_simd_table<R: Read + Seek>(
-    mut rdr: BufReader<R>,
+/// The multiversioned table parsers that [`parse_large_chunks_filtered`] can
+/// restrict to a [`StationFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableParser {
+    Memchr,
+    StdSimd,
+    SimdTemperature,
+    Structural,
+}
+
+impl TableParser {
+    pub const ALL: [TableParser; 4] = [
+        TableParser::Memchr,
+        TableParser::StdSimd,
+        TableParser::SimdTemperature,
+        TableParser::Structural,
+    ];
+
+    /// Name of the unfiltered parser, e.g. `parse_large_chunks_memchr_table`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            TableParser::Memchr => "parse_large_chunks_memchr_table",
+            TableParser::StdSimd => "parse_large_chunks_std_simd_table",
+            TableParser::SimdTemperature => "parse_large_chunks_simd_temperature_table",
+            TableParser::Structural => "parse_large_chunks_structural_table",
+        }
+    }
+}
+
+/// The table parser `parser` restricted to the stations `filter` passes, see
+/// [`StationFilter`]. It runs the same buffer loop and kernel as the
+/// unfiltered parser, with a [`FilteredTable`] in place of its table.
+/// Stations are keyed, and filtered, by their name after `normalization`.
+pub fn parse_large_chunks_filtered<R: Read + Seek>(
+    rdr: BufReader<R>,
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+    filter: &StationFilter,
+    normalization: Normalization,
+    parser: TableParser,
+) -> Vec<(String, StateI)> {
     const TABLE_SIZE: usize = 16384;
-    const SIMD_PADDING: usize = 64;
-    let mut table = FingerprintTable::<TABLE_SIZE>::new();
-    let end_incl_usize = end_inclusive as usize;
-    let mut offset = start as usize;
-    rdr.seek(SeekFrom::Start(start)).unwrap();
+    // Covers the loads of every parser, the widest being the 64-byte compares
+    // of the structural index.
+    const PADDING: usize = 64;
+    let mut table = FilteredTable::<TABLE_SIZE>::new(filter, normalization);
+    let kernel = Kernel::selected();
+    match parser {
+        TableParser::Memchr => {
+            parse_buffers::<PADDING, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+                dispatch_memchr_table(kernel, &mut table, buffer, valid_len)
+            });
+        },
+        TableParser::StdSimd => {
+            parse_buffers::<PADDING, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+                dispatch_std_simd_table(kernel, &mut table, buffer, valid_len)
+            });
+        },
+        TableParser::SimdTemperature => {
+            parse_buffers::<PADDING, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+                dispatch_simd_temperature_table(kernel, &mut table, buffer, valid_len)
+            });
+        },
+        TableParser::Structural => {
+            let mut structural = StructuralTable::new(table);
+            parse_buffers::<PADDING, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+                dispatch_structural_table(kernel, &mut structural, buffer, valid_len)
+            });
+            table = structural.table;
+        },
+    }
 
-    let mut storage = vec![0; DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER + SIMD_PADDING];
-    while offset <= end_incl_usize {
-        let mut read_bytes = rdr
-            .read(&mut storage[..DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER])
-            .expect("Unable to read chunk");
-        if read_bytes == 0 {
-            break;
-        }
-        let remaining = end_incl_usize - offset + 1;
-        read_bytes = read_bytes.min(remaining);
-        let valid_len = seek_backward_to_newline(
-            &mut rdr,
-            &storage[..DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER],
-            read_bytes,
+    let mut all = table.into_result();
+    if should_sort {
+        sort_result(&mut all);
+    }
+    all
+}
+
+/// [`parse_large_chunks_memchr_table`] that also counts the readings beyond
+/// `thresholds` and their longest runs, see [`ThresholdState`]. Buffers are
+/// processed in file order, so runs are exact within the chunk; results of
+/// consecutive chunks must be merged in file order too. Spellings that
+/// `normalization` makes equal share one slot from their first record, so
+/// their runs follow the file order as well.
+pub fn parse_large_chunks_thresholds<R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+    thresholds: Thresholds,
+    normalization: Normalization,
+) -> Vec<(String, ThresholdState)> {
+    const TABLE_SIZE: usize = 16384;
+    let mut table = ThresholdTable::<TABLE_SIZE>::new(thresholds, normalization);
+    // Padding permits safe fixed-width loads for the final name and measurement.
+    parse_buffers::<8, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        process_buffer_memchr_i64(
+            &mut |name, padded_name, value| table.update(name, padded_name, value),
+            buffer,
+            valid_len,
         )
-        .len();
+    });
+
+    let mut all = table.into_result();
+    if should_sort {
+        sort_result(&mut all);
+    }
+    all
+}
+
+/// [`parse_large_chunks_memchr_table`] that also keeps the `k` hottest and
+/// coldest readings of every station with the byte offsets of their records in
+/// the file, see [`Extremes`]. Results of any chunks can be merged in any
+/// order; [`GlobalExtremes`] picks the extremes of all stations from them.
+/// Stations are keyed by their name after `normalization`.
+pub fn parse_large_chunks_extremes<R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+    k: usize,
+    normalization: Normalization,
+) -> Vec<(String, ExtremeState)> {
+    const TABLE_SIZE: usize = 16384;
+    let mut table = ExtremesTable::<TABLE_SIZE>::new(k, normalization);
+    // Padding permits safe fixed-width loads for the final name and measurement.
+    parse_buffers::<8, R, _>(rdr, start, end_inclusive, |buffer, valid_len, offset| {
+        process_buffer_memchr_i64_indexed(
+            &mut |idx, name, padded_name, value| {
+                table.update(name, padded_name, value, offset + idx as u64)
+            },
+            buffer,
+            valid_len,
+        )
+    });
+
+    let mut all = table.into_result();
+    if should_sort {
+        sort_result(&mut all);
+    }
+    all
+}
+
+/// Parses `name;YYYY-MM-DDTHH:MM:SSZ;temperature\n` records like
+/// [`process_buffer_memchr_i64`], passing the parsed [`Timestamp`] along.
+///
+/// # Panics
+///
+/// On a record without a valid timestamp, with its index in `buffer`.
+#[inline(always)]
+fn process_buffer_timestamped<F>(processor: &mut F, buffer: &[u8], valid_len: usize)
+where
+    F: FnMut(&[u8], &[u8], &Timestamp, i16),
+{
+    let mut next_name_idx = 0;
+    while next_name_idx < valid_len {
+        let semicolon_idx = next_name_idx
+            + memchr::memchr(b';', &buffer[next_name_idx..valid_len])
+                .expect("Record without a semicolon");
+        let timestamp_idx = semicolon_idx + 1;
+        let measurement_idx = timestamp_idx + TIMESTAMP_LEN + 1;
+        assert!(
+            measurement_idx < valid_len && buffer[measurement_idx - 1] == b';',
+            "Record at {next_name_idx} has no timestamp column"
+        );
+        let timestamp = parse_timestamp(&buffer[timestamp_idx..]).unwrap_or_else(|| {
+            panic!(
+                "Invalid timestamp `{}` in record at {next_name_idx}",
+                String::from_utf8_lossy(&buffer[timestamp_idx..measurement_idx - 1])
+            )
+        });
+        let packed = i64::from_le_bytes(
+            buffer[measurement_idx..measurement_idx + 8]
+                .try_into()
+                .unwrap(),
+        );
+        let (value, record_tail_len) = to_scaled_integer_branchless(packed);
 
-        process_buffer_std_simd_i64(
-            &mut |name, padded_name, value| table.insert_or_update(name, padded_name, value),
-            &storage,
+        processor(
+            &buffer[next_name_idx..semicolon_idx],
+            &buffer[next_name_idx..],
+            &timestamp,
+            value,
+        );
+        next_name_idx = measurement_idx + record_tail_len as usize;
+    }
+}
+
+/// [`parse_large_chunks_memchr_table`] for records with a timestamp column,
+/// `station;2026-10-17T12:00:00Z;12.3`, that also aggregates every station per
+/// `granularity` bucket, see [`TimeSeriesState`]. Results of any chunks can be
+/// merged in any order. Stations are keyed by their name after
+/// `normalization`.
+///
+/// # Panics
+///
+/// On a record without a valid `YYYY-MM-DDTHH:MM:SSZ` timestamp.
+pub fn parse_large_chunks_time_series<R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+    granularity: Granularity,
+    normalization: Normalization,
+) -> Vec<(String, TimeSeriesState)> {
+    const TABLE_SIZE: usize = 16384;
+    let mut table = TimeSeriesTable::<TABLE_SIZE>::new(granularity, normalization);
+    // Padding permits safe fixed-width loads for the final name and measurement.
+    parse_buffers::<8, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        process_buffer_timestamped(
+            &mut |name, padded_name, timestamp, value| {
+                table.update(name, padded_name, timestamp, value)
+            },
+            buffer,
             valid_len,
+        )
+    });
+
+    let mut all = table.into_result();
+    if should_sort {
+        sort_result(&mut all);
+    }
+    all
+}
+
+/// Parses `name;value;...;value\n` records with one value per column of
+/// `table`'s schema, each decoded like the temperature in
+/// [`process_buffer_memchr_i64`].
+///
+/// # Panics
+///
+/// On a record with another number of values, with its index in `buffer`.
+#[inline(always)]
+fn process_buffer_metrics<const SIZE: usize>(
+    table: &mut MultiTable<SIZE>,
+    buffer: &[u8],
+    valid_len: usize,
+) {
+    let columns = table.columns();
+    let mut next_name_idx = 0;
+    while next_name_idx < valid_len {
+        let semicolon_idx = next_name_idx
+            + memchr::memchr(b';', &buffer[next_name_idx..valid_len])
+                .expect("Record without a semicolon");
+        let idx = table.find_or_insert(
+            &buffer[next_name_idx..semicolon_idx],
+            &buffer[next_name_idx..],
         );
-        offset += valid_len;
+        let mut measurement_idx = semicolon_idx + 1;
+        for column in 0..columns {
+            let packed = i64::from_le_bytes(
+                buffer[measurement_idx..measurement_idx + 8]
+                    .try_into()
+                    .unwrap(),
+            );
+            let (value, len) = to_scaled_integer_branchless(packed);
+            measurement_idx += len as usize;
+            let separator = if column + 1 == columns { b'\n' } else { b';' };
+            assert!(
+                buffer[measurement_idx - 1] == separator,
+                "Record at {next_name_idx} does not have {columns} values"
+            );
+            table.update(idx, column, value);
+        }
+        next_name_idx = measurement_idx;
     }
+}
+
+/// [`parse_large_chunks_memchr_table`] for records with several values,
+/// `station;12.3;45.0;-3.1`, aggregating every column of `schema` in the
+/// same pass and table slot, see [`MultiState`]. Results of any chunks can be
+/// merged in any order. Stations are keyed by their name after
+/// `normalization`.
+///
+/// # Panics
+///
+/// On a record without exactly one value per column.
+pub fn parse_large_chunks_metrics<R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+    schema: Arc<Schema>,
+    normalization: Normalization,
+) -> Vec<(String, MultiState)> {
+    const TABLE_SIZE: usize = 16384;
+    let mut table = MultiTable::<TABLE_SIZE>::new(schema, normalization);
+    // Padding permits safe fixed-width loads for the final name and measurement.
+    parse_buffers::<8, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        process_buffer_metrics(&mut table, buffer, valid_len)
+    });
 
     let mut all = table.into_result();
     if should_sort {
@@ -1185,41 +2308,98 @@ pub fn parse_large_chunks_std_simd_table<R: Read + Seek>(
     all
 }
 
-/// Vectorized delimiter discovery with eight temperatures parsed in parallel,
-/// backed by the compact station fingerprint table.
-pub fn parse_large_chunks_simd_temperature_table<R: Read + Seek>(
-    mut rdr: BufReader<R>,
+/// [`parse_large_chunks_memchr_table`] for a fixed, known station set: the
+/// [`StationDictionary`] maps names to dense ids without probing. Records of
+/// stations missing from the dictionary are handled according to `unknown`;
+/// the second element of the result is the number of skipped records.
+pub fn parse_large_chunks_dictionary<R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+    dictionary: &StationDictionary,
+    unknown: UnknownStations,
+) -> (Vec<(String, StateI)>, u64) {
+    let mut table = DictionaryTable::new(dictionary, unknown);
+    // Padding permits safe fixed-width loads for the final name and measurement.
+    parse_buffers::<8, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        process_buffer_memchr_i64(
+            &mut |name, padded_name, value| table.update(name, padded_name, value),
+            buffer,
+            valid_len,
+        )
+    });
+
+    let (mut all, skipped) = table.into_result();
+    if should_sort {
+        sort_result(&mut all);
+    }
+    (all, skipped)
+}
+
+/// [`parse_large_chunks_memchr_table`] with `CURSORS` records in flight: each
+/// buffer is split into `CURSORS` ranges that are parsed in lockstep. Any
+/// count from one up works here; the registry, and so `brc-app`, only offers
+/// two, three and four.
+pub fn parse_large_chunks_interleaved_table<const CURSORS: usize, R: Read + Seek>(
+    rdr: BufReader<R>,
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     const TABLE_SIZE: usize = 16384;
-    const PADDING: usize = 8;
     let mut table = FingerprintTable::<TABLE_SIZE>::new();
-    let end_incl_usize = end_inclusive as usize;
-    let mut offset = start as usize;
-    rdr.seek(SeekFrom::Start(start)).unwrap();
+    let kernel = Kernel::selected();
+    // Padding permits safe fixed-width loads for the final name and measurement.
+    parse_buffers::<8, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        dispatch_interleaved_table::<TABLE_SIZE, CURSORS>(kernel, &mut table, buffer, valid_len)
+    });
 
-    let mut storage = vec![0; DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER + PADDING];
-    while offset <= end_incl_usize {
-        let mut read_bytes = rdr
-            .read(&mut storage[..DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER])
-            .expect("Unable to read chunk");
-        if read_bytes == 0 {
-            break;
-        }
-        let remaining = end_incl_usize - offset + 1;
-        read_bytes = read_bytes.min(remaining);
-        let valid_len = seek_backward_to_newline(
-            &mut rdr,
-            &storage[..DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER],
-            read_bytes,
-        )
-        .len();
+    let mut all = table.into_result();
+    if should_sort {
+        sort_result(&mut all);
+    }
+    all
+}
 
-        process_buffer_memchr_simd_temperature(&mut table, &storage, valid_len);
-        offset += valid_len;
+/// Portable-SIMD semicolon discovery plus branchless packed temperature
+/// parsing, backed by the compact station fingerprint table.
+pub fn parse_large_chunks_std_simd_table<R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+) -> Vec<(String, StateI)> {
+    const TABLE_SIZE: usize = 16384;
+    const SIMD_PADDING: usize = 64;
+    let mut table = FingerprintTable::<TABLE_SIZE>::new();
+    let kernel = Kernel::selected();
+    parse_buffers::<SIMD_PADDING, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        dispatch_std_simd_table(kernel, &mut table, buffer, valid_len)
+    });
+
+    let mut all = table.into_result();
+    if should_sort {
+        sort_result(&mut all);
     }
+    all
+}
+
+/// Vectorized delimiter discovery with eight temperatures parsed in parallel,
+/// backed by the compact station fingerprint table.
+pub fn parse_large_chunks_simd_temperature_table<R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+) -> Vec<(String, StateI)> {
+    const TABLE_SIZE: usize = 16384;
+    const PADDING: usize = 8;
+    let mut table = FingerprintTable::<TABLE_SIZE>::new();
+    let kernel = Kernel::selected();
+    parse_buffers::<PADDING, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        dispatch_simd_temperature_table(kernel, &mut table, buffer, valid_len)
+    });
 
     let mut all = table.into_result();
     if should_sort {
@@ -1230,38 +2410,43 @@ pub fn parse_large_chunks_simd_temperature_table<R: Read + Seek>(
 
 /// Eight-wide SIMD temperature parsing and lane-striped SIMD aggregation.
 pub fn parse_large_chunks_full_simd_table<R: Read + Seek>(
-    mut rdr: BufReader<R>,
+    rdr: BufReader<R>,
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     const TABLE_SIZE: usize = 16384;
     const PADDING: usize = 8;
     let mut table = SimdFingerprintTable::<TABLE_SIZE>::new();
-    let end_incl_usize = end_inclusive as usize;
-    let mut offset = start as usize;
-    rdr.seek(SeekFrom::Start(start)).unwrap();
-
-    let mut storage = vec![0; DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER + PADDING];
-    while offset <= end_incl_usize {
-        let mut read_bytes = rdr
-            .read(&mut storage[..DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER])
-            .expect("Unable to read chunk");
-        if read_bytes == 0 {
-            break;
-        }
-        let remaining = end_incl_usize - offset + 1;
-        read_bytes = read_bytes.min(remaining);
-        let valid_len = seek_backward_to_newline(
-            &mut rdr,
-            &storage[..DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER],
-            read_bytes,
-        )
-        .len();
+    let kernel = Kernel::selected();
+    parse_buffers::<PADDING, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        dispatch_full_simd_table(kernel, &mut table, buffer, valid_len)
+    });
 
-        process_buffer_memchr_full_simd(&mut table, &storage, valid_len);
-        offset += valid_len;
+    let mut all = table.into_result();
+    if should_sort {
+        sort_result(&mut all);
     }
+    all
+}
+
+/// 64-byte delimiter classification, sixteen temperatures parsed in i32 lanes
+/// and a 16-stripe SIMD table. On [`Kernel::Avx512`] the delimiter offsets are
+/// extracted with `vpcompressd`; other kernels iterate the compare bitmask.
+pub fn parse_large_chunks_avx512_table<R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+) -> Vec<(String, StateI)> {
+    const TABLE_SIZE: usize = 16384;
+    // Covers the 64-byte block loads that start right before `valid_len`.
+    const PADDING: usize = DELIMITER_BLOCK_LEN;
+    let mut table = SimdFingerprintTable::<TABLE_SIZE, 16>::new();
+    let kernel = Kernel::selected();
+    parse_buffers::<PADDING, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        dispatch_avx512_table(kernel, &mut table, buffer, valid_len)
+    });
 
     let mut all = table.into_result();
     if should_sort {
@@ -1270,12 +2455,48 @@ pub fn parse_large_chunks_full_simd_table<R: Read + Seek>(
     all
 }
 
+/// simdjson-style two-stage parsing: `;` and `\n` bitmaps are built for a
+/// whole 64 KiB block with wide compares, then walked with `tzcnt` to feed
+/// name and measurement slices to the fingerprint table.
+pub fn parse_large_chunks_structural_table<R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+) -> Vec<(String, StateI)> {
+    parse_large_chunks_structural_table_sized::<16384, R>(rdr, start, end_inclusive, should_sort)
+}
+
+/// [`parse_large_chunks_structural_table`] with a table of `TABLE_SIZE` slots,
+/// a power of two. The table panics when the input has `TABLE_SIZE - 1` or
+/// more stations.
+pub fn parse_large_chunks_structural_table_sized<const TABLE_SIZE: usize, R: Read + Seek>(
+    rdr: BufReader<R>,
+    start: u64,
+    end_inclusive: u64,
+    should_sort: bool,
+) -> Vec<(String, StateI)> {
+    // Covers the 64-byte compares of the last word and the fingerprint loads.
+    const PADDING: usize = 64;
+    let mut table = StructuralTable::new(FingerprintTable::<TABLE_SIZE>::new());
+    let kernel = Kernel::selected();
+    parse_buffers::<PADDING, R, _>(rdr, start, end_inclusive, |buffer, valid_len, _| {
+        dispatch_structural_table(kernel, &mut table, buffer, valid_len)
+    });
+
+    let mut all = table.table.into_result();
+    if should_sort {
+        sort_result(&mut all);
+    }
+    all
+}
+
 pub fn parse_large_chunks_as_i64_v2<R: Read + Seek>(
     rdr: BufReader<R>,
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     const TABLE_SIZE: usize = 10000;
 
     let mut table: Table<TABLE_SIZE> = Table::new();
@@ -1289,7 +2510,7 @@ pub fn parse_large_chunks_as_i64_v2<R: Read + Seek>(
         DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
         true,
     );
-    let mut all: Vec<(String, StateF)> = table.to_result();
+    let mut all: Vec<(String, StateI)> = table.to_result();
     if should_sort {
         sort_result(&mut all);
     }
@@ -1299,7 +2520,7 @@ pub fn parse_large_chunks_as_i64_v2<R: Read + Seek>(
 pub fn parse_large_chunks_as_i64_mm(
     valid_buffer: &[u8],
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut hs: FxHashMap<String, StateI> =
         FxHashMap::with_capacity_and_hasher(DEFAULT_HASHMAP_CAPACITY, Default::default());
 
@@ -1316,9 +2537,9 @@ pub fn parse_large_chunks_as_i64_mm(
         valid_buffer,
     );
 
-    let mut all: Vec<(String, StateF)> = hs
+    let mut all: Vec<(String, StateI)> = hs
         .into_iter()
-        .map(|(k, v)| (k.clone(), v.to_f64()))
+        .map(|(k, v)| (k.clone(), v.clone()))
         .collect();
     if should_sort {
         sort_result(&mut all);
@@ -1331,7 +2552,7 @@ pub fn parse_large_chunks_as_i64_unsafe<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut hs: FxHashMap<Vec<u8>, StateI> =
         FxHashMap::with_capacity_and_hasher(DEFAULT_HASHMAP_CAPACITY, Default::default());
     parse_large_chunks_as_i64_unsafe_0(
@@ -1346,9 +2567,9 @@ pub fn parse_large_chunks_as_i64_unsafe<R: Read + Seek>(
         end_inclusive,
         DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
     );
-    let mut all: Vec<(String, StateF)> = hs
+    let mut all: Vec<(String, StateI)> = hs
         .into_iter()
-        .map(|(k, v)| (byte_to_string_unsafe(k.as_slice()).to_string(), v.to_f64()))
+        .map(|(k, v)| (byte_to_string_unsafe(k.as_slice()).to_string(), v.clone()))
         .collect();
     if should_sort {
         sort_result(&mut all);
@@ -1376,10 +2597,10 @@ fn parse_large_chunks_simd0<R: Read + Seek, F>(
     rdr.seek(SeekFrom::Start(start)).unwrap();
 
     let mut vec: Vec<u8> = vec![0; buffer_size];
-    let mut buf = vec.as_mut_slice();
+    let buf = vec.as_mut_slice();
 
     while offset <= end_incl_usize {
-        let mut read_bytes = rdr.read(&mut buf).expect("Unable to read line");
+        let mut read_bytes = read_buffer(&mut rdr, buf);
         if read_bytes == 0 {
             break;
         }
@@ -1390,7 +2611,7 @@ fn parse_large_chunks_simd0<R: Read + Seek, F>(
         let valid_buffer = {
             // Scan backward to find the first new line (0xA)
             let buf_to_scan_backward = &buf[0..read_bytes];
-            let idx = memchr::memrchr(b'\n', &buf_to_scan_backward).unwrap();
+            let idx = memchr::memrchr(b'\n', buf_to_scan_backward).unwrap();
             let i: usize = buf_to_scan_backward.len() - 1 - idx;
             let j: usize = read_bytes - 1 - i;
             assert!(j < read_bytes, "j: {j}, read_bytes: {read_bytes}");
@@ -1403,11 +2624,11 @@ fn parse_large_chunks_simd0<R: Read + Seek, F>(
             &buf_to_scan_backward[0..=j]
         };
         let mut next_name_idx = 0;
-        for it in memchr::memchr_iter(b';', &valid_buffer) {
+        for it in memchr::memchr_iter(b';', valid_buffer) {
             let name = &valid_buffer[next_name_idx..it];
 
             let inner_buf = &valid_buffer[it + 1..];
-            let idx = memchr::memchr(b'\n', &inner_buf).unwrap();
+            let idx = memchr::memchr(b'\n', inner_buf).unwrap();
             let value = &inner_buf[..idx];
             // Call processor to handle the temperature for the station
             processor(name, value);
@@ -1442,7 +2663,7 @@ pub fn parse_large_chunks_simd_dummy<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     _should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut dummy_result: usize = 0;
     parse_large_chunks_simd0(
         rdr,
@@ -1454,8 +2675,10 @@ pub fn parse_large_chunks_simd_dummy<R: Read + Seek>(
         DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
     );
 
-    let mut s = StateF::default();
-    s.count = dummy_result as u32;
+    let s = StateI {
+        count: dummy_result as u64,
+        ..Default::default()
+    };
     vec![("dummy".to_string(), s)]
 }
 
@@ -1471,7 +2694,7 @@ pub fn parse_large_chunks_simd<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut hs: FxHashMap<Vec<u8>, StateI> =
         FxHashMap::with_capacity_and_hasher(DEFAULT_HASHMAP_CAPACITY, Default::default());
     parse_large_chunks_simd0(
@@ -1480,9 +2703,7 @@ pub fn parse_large_chunks_simd<R: Read + Seek>(
             let value = to_scaled_integer(t);
             match hs.get_mut(name) {
                 None => {
-                    let mut s = StateI::new(value);
-                    s.update(value);
-                    hs.insert(name.to_vec(), s);
+                    hs.insert(name.to_vec(), StateI::new(value));
                 },
                 Some(prev) => prev.update(value),
             }
@@ -1491,9 +2712,9 @@ pub fn parse_large_chunks_simd<R: Read + Seek>(
         end_inclusive,
         DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
     );
-    let mut all: Vec<(String, StateF)> = hs
+    let mut all: Vec<(String, StateI)> = hs
         .into_iter()
-        .map(|(k, v)| (byte_to_string_unsafe(k.as_slice()).to_string(), v.to_f64()))
+        .map(|(k, v)| (byte_to_string_unsafe(k.as_slice()).to_string(), v.clone()))
         .collect();
     if should_sort {
         sort_result(&mut all);
@@ -1515,10 +2736,10 @@ fn parse_large_chunks_simd1<R: Read + Seek, F>(
     rdr.seek(SeekFrom::Start(start)).unwrap();
 
     let mut vec: Vec<u8> = vec![0; buffer_size];
-    let mut buf = vec.as_mut_slice();
+    let buf = vec.as_mut_slice();
 
     while offset <= end_incl_usize {
-        let mut read_bytes = rdr.read(&mut buf).expect("Unable to read line");
+        let mut read_bytes = read_buffer(&mut rdr, buf);
         if read_bytes == 0 {
             break;
         }
@@ -1529,7 +2750,7 @@ fn parse_large_chunks_simd1<R: Read + Seek, F>(
         let valid_buffer = {
             // Scan backward to find the first new line (0xA)
             let buf_to_scan_backward = &buf[0..read_bytes];
-            let idx = memchr::memrchr(b'\n', &buf_to_scan_backward).unwrap();
+            let idx = memchr::memrchr(b'\n', buf_to_scan_backward).unwrap();
             let i: usize = buf_to_scan_backward.len() - 1 - idx;
             let j: usize = read_bytes - 1 - i;
             assert!(j < read_bytes, "j: {j}, read_bytes: {read_bytes}");
@@ -1598,7 +2819,7 @@ pub fn parse_large_chunks_simd_v2_dummy<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     _should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut dummy_result: usize = 0;
     parse_large_chunks_simd1(
         rdr,
@@ -1609,8 +2830,10 @@ pub fn parse_large_chunks_simd_v2_dummy<R: Read + Seek>(
         end_inclusive,
         DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
     );
-    let mut s = StateF::default();
-    s.count = dummy_result as u32;
+    let s = StateI {
+        count: dummy_result as u64,
+        ..Default::default()
+    };
     vec![("dummy".to_string(), s)]
 }
 
@@ -1619,7 +2842,7 @@ pub fn parse_large_chunks_simd_v2<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut hs: FxHashMap<Vec<u8>, StateI> =
         FxHashMap::with_capacity_and_hasher(DEFAULT_HASHMAP_CAPACITY, Default::default());
     parse_large_chunks_simd1(
@@ -1628,9 +2851,7 @@ pub fn parse_large_chunks_simd_v2<R: Read + Seek>(
             let value = to_scaled_integer(t);
             match hs.get_mut(name) {
                 None => {
-                    let mut s = StateI::new(value);
-                    s.update(value);
-                    hs.insert(name.to_vec(), s);
+                    hs.insert(name.to_vec(), StateI::new(value));
                 },
                 Some(prev) => prev.update(value),
             }
@@ -1639,9 +2860,9 @@ pub fn parse_large_chunks_simd_v2<R: Read + Seek>(
         end_inclusive,
         DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
     );
-    let mut all: Vec<(String, StateF)> = hs
+    let mut all: Vec<(String, StateI)> = hs
         .into_iter()
-        .map(|(k, v)| (byte_to_string_unsafe(k.as_slice()).to_string(), v.to_f64()))
+        .map(|(k, v)| (byte_to_string_unsafe(k.as_slice()).to_string(), v.clone()))
         .collect();
     if should_sort {
         sort_result(&mut all);
@@ -1687,7 +2908,7 @@ pub fn parse_large_chunks_simd_v1<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     let mut hs: FxHashMap<&[u8], StateI> =
         FxHashMap::with_capacity_and_hasher(DEFAULT_HASHMAP_CAPACITY, Default::default());
     let mut holder: Holder = {
@@ -1711,9 +2932,9 @@ pub fn parse_large_chunks_simd_v1<R: Read + Seek>(
         end_inclusive,
         DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
     );
-    let mut all: Vec<(String, StateF)> = hs
+    let mut all: Vec<(String, StateI)> = hs
         .into_iter()
-        .map(|(k, v)| (byte_to_string_unsafe(k).to_string(), v.to_f64()))
+        .map(|(k, v)| (byte_to_string_unsafe(k).to_string(), v.clone()))
         .collect();
     if should_sort {
         sort_result(&mut all);
@@ -1722,6 +2943,24 @@ pub fn parse_large_chunks_simd_v1<R: Read + Seek>(
 }
 
 #[inline]
+/// Hash of a station name as computed by [`process_buffer_as_i64_as_java0`],
+/// which only mixes in the first two words of names of at least 16 bytes and
+/// the word holding the end of the name.
+fn calculate_hash_as_java(name: &[u8]) -> u64 {
+    let word = |i: usize| {
+        let mut b0 = [0_u8; 8];
+        let end = name.len().min(i + 8);
+        b0[..end - i].copy_from_slice(&name[i..end]);
+        u64::from_le_bytes(b0)
+    };
+    let len = name.len();
+    if len < 16 {
+        INIT_HASH_VALUE ^ word(0)
+    } else {
+        INIT_HASH_VALUE ^ word(0) ^ word(8) ^ word(len - len % 8)
+    }
+}
+
 fn calculate_hash(name: &&[u8]) -> u64 {
     const BUF_SIZE: usize = std::mem::size_of::<i64>();
     let mut b0: [u8; BUF_SIZE] = [0_u8; BUF_SIZE];
@@ -1743,7 +2982,7 @@ fn calculate_hash(name: &&[u8]) -> u64 {
             }
         }
         let qw0 = i64::from_le_bytes(b0);
-        hash = hash ^ (qw0 as u64);
+        hash ^= qw0 as u64;
     }
     hash
 }
@@ -1753,7 +2992,7 @@ pub fn parse_large_chunks_as_i64_as_java<R: Read + Seek>(
     start: u64,
     end_inclusive: u64,
     should_sort: bool,
-) -> Vec<(String, StateF)> {
+) -> Vec<(String, StateI)> {
     const TABLE_SIZE: usize = 10000;
     let mut table: Table<TABLE_SIZE> = Table::new();
 
@@ -1766,7 +3005,7 @@ pub fn parse_large_chunks_as_i64_as_java<R: Read + Seek>(
         end_inclusive,
         DEFAULT_BUFFER_SIZE_FOR_LARGE_CHUNK_PARSER,
     );
-    let mut all: Vec<(String, StateF)> = table.to_result();
+    let mut all: Vec<(String, StateI)> = table.to_result();
     if should_sort {
         sort_result(&mut all);
     }
@@ -1775,6 +3014,8 @@ pub fn parse_large_chunks_as_i64_as_java<R: Read + Seek>(
 
 #[cfg(test)]
 mod tests {
+    use std::collections::{BTreeMap, HashMap};
+    use std::fmt::Write;
     use std::io::Cursor;
 
     use super::*;
@@ -1787,7 +3028,7 @@ mod tests {
             .collect::<Vec<String>>()
             .join("\n");
         // Add ending \n
-        content.push_str("\n");
+        content.push('\n');
         content
     }
 
@@ -1800,8 +3041,14 @@ mod tests {
         "Yaoundé",
         "Chișinău",
         "Nyugatifelsőszombatfalva",
-        "Llanfair­pwllgwyngyll­gogery­chwyrn­drobwll­llan­tysilio­gogo­goch",
-        "Taumata­whakatangihanga­koauau­o­tamatea­turi­pukaka­piki­maunga­horo­nuku­pokai­whenua",
+        concat!(
+            "Llanfair\u{AD}pwllgwyngyll\u{AD}gogery\u{AD}chwyrn\u{AD}drobwll\u{AD}",
+            "llan\u{AD}tysilio\u{AD}gogo\u{AD}goch"
+        ),
+        concat!(
+            "Taumata\u{AD}whakatangihanga\u{AD}koauau\u{AD}o\u{AD}tamatea\u{AD}turi\u{AD}",
+            "pukaka\u{AD}piki\u{AD}maunga\u{AD}horo\u{AD}nuku\u{AD}pokai\u{AD}whenua"
+        ),
         "✨🌟💫🎉🎊🚀🌍🛸🎨📚🎵🎸🎻🎹🎺🎷🧩🛴🚲🏖🏝🏞🏜🌋🏔",
         "Tromsø",
         "Hamilton",
@@ -1915,7 +3162,7 @@ mod tests {
     #[test]
     fn test_to_scaled_integer() {
         fn verify(i: i16) {
-            let f = i as f64 / 10 as f64;
+            let f = i as f64 / 10_f64;
             let s = format!("{:.1}", f);
             assert_eq!(i, to_scaled_integer(s.as_bytes()));
         }
@@ -1928,7 +3175,7 @@ mod tests {
     #[test]
     fn test_to_scaled_integer_v2() {
         fn verify(i: i16) {
-            let f = i as f64 / 10 as f64;
+            let f = i as f64 / 10_f64;
             let s = format!("{:.1}", f);
             println!("{s}: {}", to_scaled_integer_v2(s.as_bytes()));
             assert_eq!(i, to_scaled_integer_v2(s.as_bytes()));
@@ -2088,10 +3335,10 @@ mod tests {
     #[test]
     fn test_to_scaled_integer_branchless() {
         fn verify(expected_n: i16, next_line: &str) {
-            let f = expected_n as f64 / 10 as f64;
+            let f = expected_n as f64 / 10_f64;
             let number_with_newline = format!("{:.1}\n", f);
             let s = format!("{}{}", number_with_newline, next_line);
-            let slice = &s.as_str().as_bytes()[0..8];
+            let slice = &s.as_bytes()[0..8];
             let bytes: [u8; 8] = slice.try_into().unwrap();
             let (n, len) = to_scaled_integer_branchless(i64::from_le_bytes(bytes));
             assert_eq!(expected_n, n);
@@ -2125,4 +3372,818 @@ mod tests {
             106,
         );
     }
+
+    #[test]
+    fn test_parse_temperatures() {
+        let values: Vec<i16> = (-999..=999).collect();
+        for batch in values.chunks(16) {
+            let mut words = [0_u32; 16];
+            let mut signs = [0_i32; 16];
+            for (lane, v) in batch.iter().enumerate() {
+                let s = format!("{:.1}\nA;", *v as f64 / 10.0);
+                let digits = s.trim_start_matches('-').as_bytes();
+                words[lane] = u32::from_le_bytes(digits[..4].try_into().unwrap());
+                signs[lane] = -((*v < 0) as i32);
+            }
+            let parsed = parse_temperatures_i32(words, signs);
+            assert_eq!(batch, &parsed[..batch.len()]);
+        }
+
+        for batch in values.chunks(8) {
+            let mut packed = [0_i64; 8];
+            for (lane, v) in batch.iter().enumerate() {
+                let s = format!("{:.1}\nAbc;", *v as f64 / 10.0);
+                packed[lane] = i64::from_le_bytes(s.as_bytes()[..8].try_into().unwrap());
+            }
+            assert_eq!(batch, &parse_temperatures_x8(packed)[..batch.len()]);
+            #[cfg(target_arch = "aarch64")]
+            assert_eq!(batch, &parse_temperatures_x8_neon(packed)[..batch.len()]);
+        }
+    }
+
+    #[test]
+    fn test_extract_delimiters() {
+        let content = create_content(&STATIONS, &TEMPERATURES);
+        let mut buffer = content.as_bytes().to_vec();
+        // Delimiters in the padding must be ignored
+        buffer.resize(content.len() + DELIMITER_BLOCK_LEN, b';');
+
+        let mut expected = Vec::new();
+        let mut actual = Vec::new();
+        let mut out = [0_u32; DELIMITER_BLOCK_LEN];
+        for block_start in (0..content.len()).step_by(DELIMITER_BLOCK_LEN) {
+            let len = (content.len() - block_start).min(DELIMITER_BLOCK_LEN);
+            let n = extract_delimiters(&buffer[block_start..], block_start as u32, len, &mut out);
+            actual.extend_from_slice(&out[..n]);
+            expected.extend(
+                content.as_bytes()[block_start..block_start + len]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, b)| **b == b';' || **b == b'\n')
+                    .map(|(i, _)| (block_start + i) as u32),
+            );
+        }
+        assert_eq!(2 * STATIONS.len(), expected.len());
+        assert_eq!(expected, actual);
+
+        #[cfg(target_arch = "x86_64")]
+        if Kernel::Avx512.is_supported() {
+            actual.clear();
+            for block_start in (0..content.len()).step_by(DELIMITER_BLOCK_LEN) {
+                let len = (content.len() - block_start).min(DELIMITER_BLOCK_LEN);
+                // SAFETY: AVX-512 support was checked above.
+                let n = unsafe {
+                    extract_delimiters_avx512(
+                        &buffer[block_start..],
+                        block_start as u32,
+                        len,
+                        &mut out,
+                    )
+                };
+                actual.extend_from_slice(&out[..n]);
+            }
+            assert_eq!(expected, actual);
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        {
+            actual.clear();
+            for block_start in (0..content.len()).step_by(DELIMITER_BLOCK_LEN) {
+                let len = (content.len() - block_start).min(DELIMITER_BLOCK_LEN);
+                let n = extract_delimiters_neon(
+                    &buffer[block_start..],
+                    block_start as u32,
+                    len,
+                    &mut out,
+                );
+                actual.extend_from_slice(&out[..n]);
+            }
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn test_interleaved_cursors_cover_every_record() {
+        fn collect(parse: impl Fn(&mut dyn FnMut(&[u8], &[u8], i16))) -> Vec<(Vec<u8>, i16)> {
+            let mut records = Vec::new();
+            parse(&mut |name, _, value| records.push((name.to_vec(), value)));
+            records.sort();
+            records
+        }
+
+        for content in [
+            "",
+            "a;1.0\n",
+            "a;1.0\nbb;-2.5\n",
+            "a;1.0\nbb;-2.5\nccc;33.3\nd;0.0\n",
+        ] {
+            let mut buffer = content.as_bytes().to_vec();
+            buffer.resize(content.len() + 8, 0);
+            let expected =
+                collect(|mut p| process_buffer_memchr_i64(&mut p, &buffer, content.len()));
+            assert_eq!(content.lines().count(), expected.len());
+            assert_eq!(
+                expected,
+                collect(|mut p| process_buffer_interleaved_i64::<1, _>(
+                    &mut p,
+                    &buffer,
+                    content.len()
+                ))
+            );
+            assert_eq!(
+                expected,
+                collect(|mut p| process_buffer_interleaved_i64::<3, _>(
+                    &mut p,
+                    &buffer,
+                    content.len()
+                ))
+            );
+            assert_eq!(
+                expected,
+                collect(|mut p| process_buffer_interleaved_i64::<8, _>(
+                    &mut p,
+                    &buffer,
+                    content.len()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_large_chunks_filtered() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let parse = |filter: &StationFilter, parser| {
+            parse_large_chunks_filtered(
+                BufReader::new(Cursor::new(content)),
+                0,
+                (content.len() - 1) as u64,
+                true,
+                filter,
+                Normalization::None,
+                parser,
+            )
+        };
+        let expected = parse_large_chunks_as_i64(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+        );
+
+        let names: Vec<&str> = expected.iter().map(|(name, _)| name.as_str()).collect();
+        let filter = StationFilter::new()
+            .allow(names.iter().step_by(3).copied())
+            .deny([names[3]])
+            .glob("[A-S]*")
+            .unwrap();
+        let filtered: Vec<(String, StateI)> = expected
+            .iter()
+            .filter(|(name, _)| filter.accepts(name))
+            .cloned()
+            .collect();
+        assert!(!filtered.is_empty() && filtered.len() < names.len() / 3);
+        for parser in TableParser::ALL {
+            assert_eq!(expected, parse(&StationFilter::new(), parser), "{parser:?}");
+            assert_eq!(filtered, parse(&filter, parser), "{parser:?}");
+        }
+    }
+
+    /// Splits `content` at newlines into 2, 3 and 7 chunks, parses each with
+    /// `parse` and asserts that merging the results with `merge`, in file
+    /// order or in reverse, gives `all`.
+    fn assert_chunks_merge<S, P, M>(
+        content: &[u8],
+        all: &[(String, S)],
+        in_order: bool,
+        parse: P,
+        merge: M,
+    ) where
+        S: PartialEq + std::fmt::Debug,
+        P: Fn(usize, usize) -> Vec<(String, S)>,
+        M: Fn(&mut S, &S),
+    {
+        let newlines: Vec<usize> = memchr::memchr_iter(b'\n', content).collect();
+        for chunks in [2, 3, 7] {
+            let mut ranges = Vec::new();
+            let mut start = 0;
+            for i in 1..=chunks {
+                let end_inclusive = newlines[newlines.len() * i / chunks - 1];
+                ranges.push((start, end_inclusive));
+                start = end_inclusive + 1;
+            }
+            if !in_order {
+                ranges.reverse();
+            }
+            let mut merged: HashMap<String, S> = HashMap::new();
+            for (start, end_inclusive) in ranges {
+                for (name, state) in parse(start, end_inclusive) {
+                    match merged.get_mut(&name) {
+                        Some(prev) => merge(prev, &state),
+                        None => {
+                            merged.insert(name, state);
+                        },
+                    }
+                }
+            }
+            let mut merged: Vec<(String, S)> = merged.into_iter().collect();
+            sort_result(&mut merged);
+            assert_eq!(all, merged.as_slice(), "{chunks} chunks");
+        }
+    }
+
+    #[test]
+    fn test_parse_large_chunks_thresholds() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let thresholds = Thresholds::new().above(200).below(0);
+        let parse = |start: usize, end_inclusive: usize| {
+            parse_large_chunks_thresholds(
+                BufReader::new(Cursor::new(content)),
+                start as u64,
+                end_inclusive as u64,
+                true,
+                thresholds,
+                Normalization::None,
+            )
+        };
+        let all = parse(0, content.len() - 1);
+        let expected = parse_large_chunks_as_i64(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+        );
+        let states: Vec<(String, StateI)> = all
+            .iter()
+            .map(|(name, state)| (name.clone(), state.state.clone()))
+            .collect();
+        assert_eq!(expected, states);
+
+        // Recompute every station's readings in file order
+        let mut readings: std::collections::HashMap<&str, Vec<i16>> = Default::default();
+        for line in std::str::from_utf8(content).unwrap().lines() {
+            let (name, value) = line.split_once(';').unwrap();
+            readings
+                .entry(name)
+                .or_default()
+                .push(to_scaled_integer(value.as_bytes()));
+        }
+        let longest = |values: &[i16], exceeds: &dyn Fn(i16) -> bool| {
+            values
+                .split(|&v| !exceeds(v))
+                .map(|run| run.len() as u64)
+                .max()
+                .unwrap()
+        };
+        for (name, state) in &all {
+            let values = &readings[name.as_str()];
+            let above = |v| v > 200;
+            let below = |v| v < 0;
+            assert_eq!(
+                values.iter().filter(|&&v| above(v)).count() as u64,
+                state.above.count
+            );
+            assert_eq!(
+                values.iter().filter(|&&v| below(v)).count() as u64,
+                state.below.count
+            );
+            assert_eq!(longest(values, &above), state.above.longest_run, "{name}");
+            assert_eq!(longest(values, &below), state.below.longest_run, "{name}");
+        }
+        assert!(all.iter().any(|(_, state)| state.above.longest_run > 1));
+
+        // Chunks merged in file order give the same runs
+        assert_chunks_merge(content, &all, true, parse, ThresholdState::merge);
+    }
+
+    #[test]
+    fn test_parse_large_chunks_extremes() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let k = 4;
+        let parse = |start: usize, end_inclusive: usize| {
+            parse_large_chunks_extremes(
+                BufReader::new(Cursor::new(content)),
+                start as u64,
+                end_inclusive as u64,
+                true,
+                k,
+                Normalization::None,
+            )
+        };
+        let all = parse(0, content.len() - 1);
+
+        // Every kept offset points at a record of the station with that value
+        let text = std::str::from_utf8(content).unwrap();
+        for (name, state) in &all {
+            for reading in state.extremes.hottest.iter().chain(&state.extremes.coldest) {
+                let line = text[reading.offset as usize..].lines().next().unwrap();
+                let (station, value) = line.split_once(';').unwrap();
+                assert_eq!(
+                    (name.as_str(), reading.tenths),
+                    (station, to_scaled_integer(value.as_bytes()))
+                );
+            }
+        }
+
+        // Recompute the global extremes from all records
+        let mut records: Vec<(&str, Reading)> = Vec::new();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            let (name, value) = line.trim_end().split_once(';').unwrap();
+            let tenths = to_scaled_integer(value.as_bytes());
+            records.push((name, Reading { tenths, offset }));
+            offset += line.len() as u64;
+        }
+        records.sort_by_key(|(_, r)| (std::cmp::Reverse(r.tenths), r.offset));
+        let global = GlobalExtremes::new(&all);
+        assert_eq!(&records[..k], global.hottest.as_slice());
+        records.sort_by_key(|(_, r)| (r.tenths, r.offset));
+        assert_eq!(&records[..k], global.coldest.as_slice());
+
+        // Chunks merged in any order give the same extremes
+        assert_chunks_merge(content, &all, false, parse, ExtremeState::merge);
+    }
+
+    /// The sample with a timestamp column, one record every 23 minutes from
+    /// the start of 2024, crossing a leap day and a year.
+    fn timestamped_sample() -> Vec<u8> {
+        let content = include_str!("../test_resources/sample.txt");
+        let (mut year, mut month, mut day, mut minutes) = (2024, 1, 1, 0);
+        let mut timestamped = String::with_capacity(content.len() * 2);
+        for line in content.lines() {
+            let (name, value) = line.split_once(';').unwrap();
+            writeln!(
+                timestamped,
+                "{name};{year}-{month:02}-{day:02}T{:02}:{:02}:00Z;{value}",
+                minutes / 60,
+                minutes % 60
+            )
+            .unwrap();
+            minutes += 23;
+            if minutes >= 24 * 60 {
+                minutes -= 24 * 60;
+                let days = match month {
+                    2 if year % 4 == 0 => 29,
+                    2 => 28,
+                    4 | 6 | 9 | 11 => 30,
+                    _ => 31,
+                };
+                day += 1;
+                if day > days {
+                    (day, month) = (1, month + 1);
+                }
+                if month > 12 {
+                    (month, year) = (1, year + 1);
+                }
+            }
+        }
+        timestamped.into_bytes()
+    }
+
+    #[test]
+    fn test_parse_large_chunks_time_series() {
+        let content = timestamped_sample();
+        let text = std::str::from_utf8(&content).unwrap();
+        let parse = |start: usize, end_inclusive: usize, granularity: Granularity| {
+            parse_large_chunks_time_series(
+                BufReader::new(Cursor::new(&content)),
+                start as u64,
+                end_inclusive as u64,
+                true,
+                granularity,
+                Normalization::None,
+            )
+        };
+        let plain = include_bytes!("../test_resources/sample.txt");
+        let expected_states = parse_large_chunks_memchr_table(
+            BufReader::new(Cursor::new(plain)),
+            0,
+            (plain.len() - 1) as u64,
+            true,
+        );
+
+        for (granularity, label_len) in [
+            (Granularity::Hour, 13),
+            (Granularity::Day, 10),
+            (Granularity::Month, 7),
+        ] {
+            let all = parse(0, content.len() - 1, granularity);
+            let states: Vec<(String, StateI)> = all
+                .iter()
+                .map(|(name, state)| (name.clone(), state.state.clone()))
+                .collect();
+            assert_eq!(expected_states, states);
+
+            // Buckets of every station, labelled by the timestamp's prefix
+            let mut expected: HashMap<&str, BTreeMap<&str, StateI>> = HashMap::new();
+            for line in text.lines() {
+                let mut fields = line.split(';');
+                let (name, timestamp, value) = (
+                    fields.next().unwrap(),
+                    fields.next().unwrap(),
+                    to_scaled_integer(fields.next().unwrap().as_bytes()),
+                );
+                expected
+                    .entry(name)
+                    .or_default()
+                    .entry(&timestamp[..label_len])
+                    .and_modify(|state| state.update(value))
+                    .or_insert_with(|| StateI::new(value));
+            }
+            for (name, state) in &all {
+                let series = state.to_string();
+                let buckets: Vec<String> = expected[name.as_str()]
+                    .iter()
+                    .map(|(label, state)| format!("{label}={state}"))
+                    .collect();
+                assert_eq!(
+                    format!("{} series:{}", state.state, buckets.join(";")),
+                    series,
+                    "{granularity}"
+                );
+            }
+
+            // Chunks merged in any order give the same series
+            let parse = |start, end_inclusive| parse(start, end_inclusive, granularity);
+            assert_chunks_merge(&content, &all, false, parse, TimeSeriesState::merge);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid timestamp `2026-02-30T00:00:00Z`")]
+    fn test_parse_large_chunks_time_series_invalid() {
+        let content = b"Oslo;2026-02-28T00:00:00Z;1.0\nOslo;2026-02-30T00:00:00Z;2.0\n";
+        parse_large_chunks_time_series(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+            Granularity::Day,
+            Normalization::None,
+        );
+    }
+
+    #[test]
+    fn test_parse_large_chunks_metrics() {
+        let plain = include_str!("../test_resources/sample.txt");
+        let mut content = String::with_capacity(plain.len() * 2);
+        let mut expected: HashMap<&str, [StateI; 3]> = HashMap::new();
+        for (i, line) in plain.lines().enumerate() {
+            let (name, temperature) = line.split_once(';').unwrap();
+            let values = [
+                to_scaled_integer(temperature.as_bytes()),
+                (i * 37 % 1000) as i16,
+                (i * 53 % 1999) as i16 - 999,
+            ];
+            content.push_str(name);
+            for value in values {
+                content.push(';');
+                write_tenths(&mut content, value as i64).unwrap();
+            }
+            content.push('\n');
+            let states = expected.entry(name).or_default();
+            for (state, value) in states.iter_mut().zip(values) {
+                state.update(value);
+            }
+        }
+        let content = content.into_bytes();
+        let schema = Arc::new(Schema::parse("temperature,humidity,wind").unwrap());
+        let parse = |start: usize, end_inclusive: usize| {
+            parse_large_chunks_metrics(
+                BufReader::new(Cursor::new(&content)),
+                start as u64,
+                end_inclusive as u64,
+                true,
+                schema.clone(),
+                Normalization::None,
+            )
+        };
+        let all = parse(0, content.len() - 1);
+        assert_eq!(expected.len(), all.len());
+        for (name, state) in &all {
+            assert_eq!(expected[name.as_str()].as_slice(), state.columns.as_slice());
+        }
+        let temperatures = parse_large_chunks_memchr_table(
+            BufReader::new(Cursor::new(plain)),
+            0,
+            (plain.len() - 1) as u64,
+            true,
+        );
+        assert_eq!(
+            temperatures,
+            all.iter()
+                .map(|(name, state)| (name.clone(), state.column("temperature").unwrap().clone()))
+                .collect::<Vec<_>>()
+        );
+
+        // Chunks merged in any order give the same result
+        assert_chunks_merge(&content, &all, false, parse, MultiState::merge);
+    }
+
+    #[test]
+    #[should_panic(expected = "Record at 13 does not have 2 values")]
+    fn test_parse_large_chunks_metrics_value_count() {
+        let content = b"Oslo;1.0;2.0\nLima;3.0;4.0;5.0\n";
+        parse_large_chunks_metrics(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+            Arc::new(Schema::parse("temperature,humidity").unwrap()),
+            Normalization::None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Record at 17 does not have 3 values")]
+    fn test_parse_large_chunks_metrics_too_few_values() {
+        let content = b"Oslo;1.0;2.0;3.0\nLima;4.0;5.0\nRome;6.0;7.0;8.0\n";
+        parse_large_chunks_metrics(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+            Arc::new(Schema::parse("temperature,humidity,pressure").unwrap()),
+            Normalization::None,
+        );
+    }
+
+    #[test]
+    fn test_parse_large_chunks_accumulate() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let expected = parse_large_chunks_memchr_table(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+        );
+        let states = parse_large_chunks_accumulate::<StateI, _>(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+        );
+        assert_eq!(expected, finalize_result(states));
+
+        let histograms = parse_large_chunks_accumulate::<Histogram, _>(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+        );
+        assert_eq!(expected.len(), histograms.len());
+        for ((name, state), (histogram_name, histogram)) in expected.iter().zip(&histograms) {
+            assert_eq!(name, histogram_name);
+            assert_eq!(state.count, histogram.buckets().iter().sum::<u64>());
+            assert_ne!(0, histogram.count(state.max.div_euclid(10)));
+            assert_ne!(0, histogram.count(state.min.div_euclid(10)));
+        }
+    }
+
+    #[test]
+    fn test_parse_large_chunks_dictionary() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let expected = parse_large_chunks_as_i64(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+        );
+        let dictionary =
+            StationDictionary::new(expected.iter().map(|(name, _)| name.clone())).unwrap();
+        let (all, skipped) = parse_large_chunks_dictionary(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+            &dictionary,
+            UnknownStations::Reject,
+        );
+        assert_eq!(0, skipped);
+        assert_eq!(format_result(&expected), format_result(&all));
+
+        // Drop the first station from the dictionary and count its records instead
+        let first = &expected[0];
+        let dictionary =
+            StationDictionary::new(expected[1..].iter().map(|(name, _)| name.clone())).unwrap();
+        let (all, skipped) = parse_large_chunks_dictionary(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+            &dictionary,
+            UnknownStations::Count,
+        );
+        assert_eq!(first.1.count, skipped);
+        assert_eq!(format_result(&expected[1..]), format_result(&all));
+    }
+
+    #[test]
+    fn test_compensated_mean_error() {
+        // A million measurements between 0.0 and 99.9 from a fixed LCG, in
+        // chunks of ten thousand merged like per-thread results
+        const RECORDS: usize = 1_000_000;
+        const CHUNK: usize = 10_000;
+        let mut seed: u64 = 42;
+        let mut exact = StateI::default();
+        let mut compensated = StateF::default();
+        let mut plain_sum = 0.0_f64;
+        let mut chunk = StateF::default();
+        for i in 1..=RECORDS {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let tenths = ((seed >> 33) % 1000) as i16;
+            let value = tenths as f64 / 10.0;
+            exact.update(tenths);
+            chunk.update(value);
+            plain_sum += value;
+            if i % CHUNK == 0 {
+                compensated.merge(&chunk);
+                chunk = StateF::default();
+            }
+        }
+
+        let exact_mean = exact.sum as f64 / 10.0 / exact.count as f64;
+        let compensated_error = (compensated.mean() - exact_mean).abs();
+        let plain_error = (plain_sum / RECORDS as f64 - exact_mean).abs();
+        assert_eq!(exact.count, compensated.count);
+        assert!(
+            compensated_error <= f64::EPSILON * exact_mean,
+            "Compensated error {compensated_error:e} on a mean of {exact_mean}"
+        );
+        assert!(
+            compensated_error < plain_error,
+            "Compensated error {compensated_error:e} not below plain error {plain_error:e}"
+        );
+        assert_eq!(exact.to_string(), compensated.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Measurement count overflows u64")]
+    fn test_merge_overflow_panics() {
+        let mut state = StateI {
+            count: u64::MAX,
+            ..StateI::new(1)
+        };
+        state.merge(&StateI::new(1));
+    }
+
+    fn format_result(result: &[(String, StateI)]) -> Vec<String> {
+        result.iter().map(|(k, v)| format!("{k}={v}")).collect()
+    }
+
+    #[test]
+    fn test_results_are_identical_for_any_chunking() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let newlines: Vec<usize> = memchr::memchr_iter(b'\n', content).collect();
+        let reference = parse_large_chunks_as_i64(
+            BufReader::new(Cursor::new(content)),
+            0,
+            (content.len() - 1) as u64,
+            true,
+        );
+
+        for implementation in implementations::<Cursor<&[u8]>>() {
+            if implementation.name.ends_with("_dummy") || implementation.input != InputFormat::Text
+            {
+                continue;
+            }
+            for chunks in [1, 2, 3, 7] {
+                let mut merged: std::collections::HashMap<String, StateI> =
+                    std::collections::HashMap::new();
+                let mut start = 0;
+                for i in 1..=chunks {
+                    let end_inclusive = newlines[newlines.len() * i / chunks - 1];
+                    let rdr = BufReader::new(Cursor::new(&content[..]));
+                    let result =
+                        (implementation.parse)(rdr, start as u64, end_inclusive as u64, false);
+                    for (name, state) in result {
+                        merged
+                            .entry(name)
+                            .and_modify(|prev| prev.merge(&state))
+                            .or_insert(state);
+                    }
+                    start = end_inclusive + 1;
+                }
+                let mut all: Vec<(String, StateI)> = merged.into_iter().collect();
+                sort_result(&mut all);
+                assert_eq!(
+                    reference, all,
+                    "{} over {chunks} chunks",
+                    implementation.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_table_kernels_match_reference() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let rdr = BufReader::new(Cursor::new(content));
+        let expected = format_result(&parse_large_chunks_as_i64(
+            rdr,
+            0,
+            (content.len() - 1) as u64,
+            true,
+        ));
+
+        let mut storage = content.to_vec();
+        storage.resize(content.len() + 64, 0);
+        let kernels = Kernel::supported();
+        assert!(kernels.contains(&Kernel::baseline()));
+        for kernel in kernels {
+            let mut table = FingerprintTable::<16384>::new();
+            dispatch_memchr_table(kernel, &mut table, &storage, content.len());
+            let mut all = table.into_result();
+            sort_result(&mut all);
+            assert_eq!(
+                expected,
+                format_result(&all),
+                "memchr_table, kernel: {kernel}"
+            );
+
+            let mut table = FingerprintTable::<16384>::new();
+            dispatch_interleaved_table::<16384, 3>(kernel, &mut table, &storage, content.len());
+            let mut all = table.into_result();
+            sort_result(&mut all);
+            assert_eq!(
+                expected,
+                format_result(&all),
+                "interleaved_table, kernel: {kernel}"
+            );
+
+            let mut table = FingerprintTable::<16384>::new();
+            dispatch_std_simd_table(kernel, &mut table, &storage, content.len());
+            let mut all = table.into_result();
+            sort_result(&mut all);
+            assert_eq!(
+                expected,
+                format_result(&all),
+                "std_simd_table, kernel: {kernel}"
+            );
+
+            let mut table = FingerprintTable::<16384>::new();
+            dispatch_simd_temperature_table(kernel, &mut table, &storage, content.len());
+            let mut all = table.into_result();
+            sort_result(&mut all);
+            assert_eq!(
+                expected,
+                format_result(&all),
+                "simd_temperature_table, kernel: {kernel}"
+            );
+
+            let mut table = SimdFingerprintTable::<16384>::new();
+            dispatch_full_simd_table(kernel, &mut table, &storage, content.len());
+            let mut all = table.into_result();
+            sort_result(&mut all);
+            assert_eq!(
+                expected,
+                format_result(&all),
+                "full_simd_table, kernel: {kernel}"
+            );
+
+            let mut table = StructuralTable::new(FingerprintTable::<16384>::new());
+            dispatch_structural_table(kernel, &mut table, &storage, content.len());
+            let mut all = table.table.into_result();
+            sort_result(&mut all);
+            assert_eq!(
+                expected,
+                format_result(&all),
+                "structural_table, kernel: {kernel}"
+            );
+
+            let mut table = SimdFingerprintTable::<16384, 16>::new();
+            dispatch_avx512_table(kernel, &mut table, &storage, content.len());
+            let mut all = table.into_result();
+            sort_result(&mut all);
+            assert_eq!(
+                expected,
+                format_result(&all),
+                "avx512_table, kernel: {kernel}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_implementation() {
+        for implementation in implementations::<Cursor<&[u8]>>() {
+            let found = find_implementation::<Cursor<&[u8]>>(implementation.name).unwrap();
+            assert_eq!(implementation.name, found.name);
+        }
+        assert!(find_implementation::<Cursor<&[u8]>>("unknown").is_none());
+        assert_eq!(
+            Kernel::selected(),
+            find_implementation::<Cursor<&[u8]>>("parse_large_chunks_memchr_table")
+                .unwrap()
+                .kernel()
+        );
+    }
+
+    #[test]
+    fn test_kernel_from_str() {
+        for kernel in Kernel::ALL {
+            assert_eq!(kernel, Kernel::from_str(kernel.name()).unwrap());
+        }
+        assert!(Kernel::from_str("mmx").is_err());
+    }
 }
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::Accumulator;
+
+/// Station attributes loaded from a CSV file with a header row, e.g.
+/// `name,country,region,lat,lon`. The `name` column is required and must be
+/// unique; any other column, including ones like a climate zone, can be
+/// joined against results and used to roll them up with
+/// [`StationMetadata::roll_up`]. Fields may be quoted with `"`, doubling
+/// quotes inside.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationMetadata {
+    columns: Vec<String>,
+    /// Fields of every station, in the order of `columns`.
+    rows: HashMap<String, Vec<String>>,
+}
+
+/// Aggregates rolled up by a metadata column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rollup<A> {
+    /// Aggregate of every value of the column, unsorted.
+    pub groups: Vec<(String, A)>,
+    /// Stations of the result missing from the metadata, sorted; they are
+    /// not part of any group.
+    pub unmatched: Vec<String>,
+}
+
+impl StationMetadata {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read station metadata {}: {e}", path.display()))?;
+        Self::parse(&content).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn parse(csv: &str) -> Result<Self, String> {
+        let mut lines = csv
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty());
+        let (_, header) = lines.next().ok_or("Empty station metadata")?;
+        let columns = split_csv_line(header).map_err(|e| format!("line 1: {e}"))?;
+        let name_idx = columns
+            .iter()
+            .position(|c| c == "name")
+            .ok_or_else(|| format!("No `name` column in {columns:?}"))?;
+        let numeric: Vec<usize> = ["lat", "lon"]
+            .iter()
+            .filter_map(|c| columns.iter().position(|column| column == c))
+            .collect();
+
+        let mut rows = HashMap::new();
+        for (i, line) in lines {
+            let line_no = i + 1;
+            let fields = split_csv_line(line).map_err(|e| format!("line {line_no}: {e}"))?;
+            if fields.len() != columns.len() {
+                return Err(format!(
+                    "line {line_no}: expected {} fields, got {}",
+                    columns.len(),
+                    fields.len()
+                ));
+            }
+            for &idx in &numeric {
+                fields[idx].parse::<f64>().map_err(|e| {
+                    format!(
+                        "line {line_no}: invalid {} `{}`: {e}",
+                        columns[idx], fields[idx]
+                    )
+                })?;
+            }
+            let name = fields[name_idx].clone();
+            if rows.insert(name.clone(), fields).is_some() {
+                return Err(format!("line {line_no}: duplicate station `{name}`"));
+            }
+        }
+        Ok(Self { columns, rows })
+    }
+
+    pub fn columns(&self) -> &[String] { &self.columns }
+
+    pub fn len(&self) -> usize { self.rows.len() }
+
+    pub fn is_empty(&self) -> bool { self.rows.is_empty() }
+
+    fn column_idx(&self, column: &str) -> Result<usize, String> {
+        self.columns
+            .iter()
+            .position(|c| c == column)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown metadata column `{column}`, expected one of {:?}",
+                    self.columns
+                )
+            })
+    }
+
+    /// The `column` field of `station`, if the station is listed.
+    pub fn field(&self, station: &str, column: &str) -> Result<Option<&str>, String> {
+        let idx = self.column_idx(column)?;
+        Ok(self.rows.get(station).map(|fields| fields[idx].as_str()))
+    }
+
+    /// Joins `result` against the metadata and merges the aggregates of the
+    /// stations sharing a value of `column`.
+    pub fn roll_up<A: Accumulator>(
+        &self,
+        result: Vec<(String, A)>,
+        column: &str,
+    ) -> Result<Rollup<A>, String> {
+        let idx = self.column_idx(column)?;
+        let mut groups: HashMap<&str, A> = HashMap::new();
+        let mut unmatched = Vec::new();
+        for (name, state) in result {
+            match self.rows.get(&name) {
+                Some(fields) => {
+                    groups
+                        .entry(fields[idx].as_str())
+                        .and_modify(|group| group.merge(&state))
+                        .or_insert(state);
+                },
+                None => unmatched.push(name),
+            }
+        }
+        unmatched.sort_unstable();
+        Ok(Rollup {
+            groups: groups
+                .into_iter()
+                .map(|(group, state)| (group.to_owned(), state))
+                .collect(),
+            unmatched,
+        })
+    }
+}
+
+/// Splits one CSV line into fields, unquoting quoted ones.
+fn split_csv_line(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    },
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("unclosed quote".to_owned()),
+                }
+            }
+            if chars.peek().is_some_and(|&c| c != ',') {
+                return Err(format!("unexpected text after quoted field `{field}`"));
+            }
+        } else {
+            while let Some(c) = chars.next_if(|&c| c != ',') {
+                field.push(c);
+            }
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StateI, sort_result};
+
+    const METADATA: &str = concat!(
+        "name,country,region,lat,lon,zone\n",
+        "Oslo,Norway,Europe,59.91,10.75,Dfb\r\n",
+        "Bergen,Norway,Europe,60.39,5.32,Cfb\n",
+        "\n",
+        "\"Washington, D.C.\",United States,Americas,38.9,-77.04,Cfa\n",
+        "\"St. John's \"\"Fort\"\"\",Canada,Americas,47.56,-52.71,Dfb\n",
+    );
+
+    #[test]
+    fn test_parse_metadata() {
+        let metadata = StationMetadata::parse(METADATA).unwrap();
+        assert_eq!(4, metadata.len());
+        assert_eq!(
+            Ok(Some("United States")),
+            metadata.field("Washington, D.C.", "country")
+        );
+        assert_eq!(
+            Ok(Some("Dfb")),
+            metadata.field("St. John's \"Fort\"", "zone")
+        );
+        assert_eq!(Ok(None), metadata.field("Lima", "country"));
+        assert!(metadata.field("Oslo", "population").is_err());
+
+        assert!(StationMetadata::parse("country\nNorway\n").is_err());
+        assert!(StationMetadata::parse("name,lat\nOslo,north\n").is_err());
+        assert!(StationMetadata::parse("name,country\nOslo\n").is_err());
+        assert!(StationMetadata::parse("name\nOslo\nOslo\n").is_err());
+        assert!(StationMetadata::parse("name\n\"Oslo\n").is_err());
+    }
+
+    #[test]
+    fn test_roll_up() {
+        let metadata = StationMetadata::parse(METADATA).unwrap();
+        let result = vec![
+            ("Oslo".to_owned(), StateI::new(-50)),
+            ("Bergen".to_owned(), StateI::new(30)),
+            ("Lima".to_owned(), StateI::new(200)),
+            ("Washington, D.C.".to_owned(), StateI::new(150)),
+            ("St. John's \"Fort\"".to_owned(), StateI::new(10)),
+        ];
+
+        let mut rollup = metadata.roll_up(result.clone(), "country").unwrap();
+        sort_result(&mut rollup.groups);
+        assert_eq!(["Lima".to_owned()], rollup.unmatched.as_slice());
+        let groups: Vec<String> = rollup
+            .groups
+            .iter()
+            .map(|(group, state)| format!("{group}={state}"))
+            .collect();
+        assert_eq!(
+            [
+                "Canada=1.0/1.0/1.0",
+                "Norway=-5.0/-1.0/3.0",
+                "United States=15.0/15.0/15.0"
+            ],
+            groups.as_slice()
+        );
+
+        let mut rollup = metadata.roll_up(result.clone(), "zone").unwrap();
+        sort_result(&mut rollup.groups);
+        assert_eq!("Dfb", rollup.groups[2].0);
+        assert_eq!(2, rollup.groups[2].1.count);
+        assert!(metadata.roll_up(result, "continent").is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use crate::fingerprint_table::FingerprintTable;
+use crate::{Normalization, StateI};
+
+/// Names of the value columns of `name;value;value;...` records, e.g.
+/// `temperature,humidity,pressure`. Every column is in the format of the
+/// challenge's temperatures, one fractional digit within ±99.9.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schema {
+    columns: Vec<String>,
+}
+
+impl Schema {
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(columns: I) -> Result<Self, String> {
+        let columns: Vec<String> = columns.into_iter().map(Into::into).collect();
+        if columns.is_empty() {
+            return Err("A schema needs at least one value column".to_owned());
+        }
+        for (i, column) in columns.iter().enumerate() {
+            if column.is_empty() {
+                return Err(format!("Empty name of value column {i}"));
+            }
+            if columns[..i].contains(column) {
+                return Err(format!("Duplicate value column `{column}`"));
+            }
+        }
+        Ok(Self { columns })
+    }
+
+    /// Parses comma-separated column names.
+    pub fn parse(columns: &str) -> Result<Self, String> { Self::new(columns.split(',')) }
+
+    pub fn columns(&self) -> &[String] { &self.columns }
+}
+
+/// One [`StateI`] per value column of a [`Schema`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiState {
+    pub schema: Arc<Schema>,
+    /// In the order of the schema's columns.
+    pub columns: Vec<StateI>,
+}
+
+impl MultiState {
+    /// The state of `column`, if the schema has it.
+    pub fn column(&self, column: &str) -> Option<&StateI> {
+        let idx = self.schema.columns.iter().position(|c| c == column)?;
+        Some(&self.columns[idx])
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        assert_eq!(
+            self.schema, other.schema,
+            "Merging states of different schemas"
+        );
+        for (state, other) in self.columns.iter_mut().zip(&other.columns) {
+            state.merge(other);
+        }
+    }
+}
+
+/// A [`FingerprintTable`] aggregating the first value column, with the states
+/// of the other columns of every slot kept next to it.
+pub(crate) struct MultiTable<const SIZE: usize> {
+    schema: Arc<Schema>,
+    table: FingerprintTable<SIZE>,
+    /// `schema.columns.len() - 1` states per slot.
+    rest: Vec<StateI>,
+}
+
+impl<const SIZE: usize> MultiTable<SIZE> {
+    pub(crate) fn new(schema: Arc<Schema>, normalization: Normalization) -> Self {
+        let rest = vec![StateI::default(); SIZE * (schema.columns.len() - 1)];
+        Self {
+            schema,
+            table: FingerprintTable::with_normalization(normalization),
+            rest,
+        }
+    }
+
+    pub(crate) fn columns(&self) -> usize { self.schema.columns.len() }
+
+    #[inline(always)]
+    pub(crate) fn find_or_insert(&mut self, name: &[u8], padded_name: &[u8]) -> usize {
+        self.table.find_or_insert(name, padded_name)
+    }
+
+    /// Adds `value` to `column` of the slot `idx`.
+    #[inline(always)]
+    pub(crate) fn update(&mut self, idx: usize, column: usize, value: i16) {
+        if column == 0 {
+            self.table.update_slot(idx, value);
+        } else {
+            self.rest[idx * (self.schema.columns.len() - 1) + column - 1].update(value);
+        }
+    }
+
+    pub(crate) fn into_result(mut self) -> Vec<(String, MultiState)> {
+        let rest_len = self.schema.columns.len() - 1;
+        self.table
+            .into_result_with_slots()
+            .into_iter()
+            .map(|(idx, name, state)| {
+                let mut columns = Vec::with_capacity(rest_len + 1);
+                columns.push(state);
+                columns.extend(
+                    self.rest[idx * rest_len..(idx + 1) * rest_len]
+                        .iter_mut()
+                        .map(std::mem::take),
+                );
+                let state = MultiState {
+                    schema: self.schema.clone(),
+                    columns,
+                };
+                (name, state)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema() {
+        let schema = Schema::parse("temperature,humidity,pressure").unwrap();
+        assert_eq!(3, schema.columns().len());
+        assert_eq!("humidity", schema.columns()[1]);
+        assert!(Schema::parse("temperature,,pressure").is_err());
+        assert!(Schema::parse("temperature,temperature").is_err());
+        assert!(Schema::new(Vec::<String>::new()).is_err());
+    }
+
+    #[test]
+    fn test_multi_table() {
+        let schema = Arc::new(Schema::parse("temperature,humidity").unwrap());
+        let mut table = MultiTable::<64>::new(schema, Normalization::None);
+        for (name, values) in [
+            ("Oslo", [10, 500]),
+            ("Lima", [200, 900]),
+            ("Oslo", [-30, 700]),
+        ] {
+            let mut padded_name = name.as_bytes().to_vec();
+            padded_name.resize(name.len() + 8, 0);
+            let idx = table.find_or_insert(name.as_bytes(), &padded_name);
+            for (column, value) in values.into_iter().enumerate() {
+                table.update(idx, column, value);
+            }
+        }
+        let mut all = table.into_result();
+        crate::sort_result(&mut all);
+        let oslo = &all[1].1;
+        assert_eq!("Oslo", all[1].0);
+        assert_eq!(
+            "-3.0/-1.0/1.0",
+            oslo.column("temperature").unwrap().to_string()
+        );
+        assert_eq!(
+            "50.0/60.0/70.0",
+            oslo.column("humidity").unwrap().to_string()
+        );
+        assert_eq!(None, oslo.column("pressure"));
+
+        let mut merged = all[0].1.clone();
+        merged.merge(oslo);
+        assert_eq!(3, merged.columns[1].count);
+        assert_eq!((-30, 900), (merged.columns[0].min, merged.columns[1].max));
+    }
+}
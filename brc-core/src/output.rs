@@ -0,0 +1,299 @@
+use std::fmt::{Display, Write};
+
+use crate::{
+    Estimate, Exceedances, ExtremeState, GlobalExtremes, MultiState, Reading, StateF, StateI,
+    ThresholdState, TimeSeriesState,
+};
+
+/// The mean of `count` measurements summing to `sum` tenths, in tenths, with
+/// ties rounded toward positive infinity like Java's `Math.round` in the
+/// challenge's reference implementation. Computed exactly, so it does not
+/// depend on the order the measurements were added in.
+pub fn round_mean(sum: i64, count: u64) -> i64 {
+    assert!(count != 0, "Mean of no measurements");
+    let (sum, count) = (sum as i128, count as i128);
+    (2 * sum + count).div_euclid(2 * count) as i64
+}
+
+/// `value` in tenths, with ties rounded toward positive infinity.
+pub fn round_tenths(value: f64) -> i64 {
+    let scaled = value * 10.0;
+    let floor = scaled.floor();
+    if scaled - floor >= 0.5 {
+        floor as i64 + 1
+    } else {
+        floor as i64
+    }
+}
+
+/// Writes `tenths` with exactly one fractional digit. Zero is always written
+/// as `0.0`, never `-0.0`.
+pub fn write_tenths<W: Write>(out: &mut W, tenths: i64) -> std::fmt::Result {
+    let sign = if tenths < 0 { "-" } else { "" };
+    let abs = tenths.unsigned_abs();
+    write!(out, "{sign}{}.{}", abs / 10, abs % 10)
+}
+
+fn write_min_mean_max<W: Write>(out: &mut W, min: i64, mean: i64, max: i64) -> std::fmt::Result {
+    write_tenths(out, min)?;
+    out.write_char('/')?;
+    write_tenths(out, mean)?;
+    out.write_char('/')?;
+    write_tenths(out, max)
+}
+
+impl Display for StateI {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_min_mean_max(
+            f,
+            self.min as i64,
+            round_mean(self.sum, self.count),
+            self.max as i64,
+        )
+    }
+}
+
+impl Display for StateF {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_min_mean_max(
+            f,
+            round_tenths(self.min),
+            round_tenths(self.mean()),
+            round_tenths(self.max),
+        )
+    }
+}
+
+/// `<=min/mean+-margin/>=max ~count`: bounds on the extremes, the mean with
+/// its confidence interval to two decimals and the estimated readings.
+impl Display for Estimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<=")?;
+        write_tenths(f, self.min as i64)?;
+        write!(f, "/{:.2}+-{:.2}/>=", self.mean, self.margin)?;
+        write_tenths(f, self.max as i64)?;
+        write!(f, " ~{:.0}", self.count)
+    }
+}
+
+fn write_exceedances<W: Write>(
+    out: &mut W,
+    op: char,
+    threshold: Option<i16>,
+    exceedances: &Exceedances,
+) -> std::fmt::Result {
+    if let Some(threshold) = threshold {
+        write!(out, " {op}")?;
+        write_tenths(out, threshold as i64)?;
+        write!(out, ":{}/{}", exceedances.count, exceedances.longest_run)?;
+    }
+    Ok(())
+}
+
+/// `min/mean/max` followed by ` >T:count/run` and ` <T:count/run` for each set
+/// threshold `T`: the readings beyond it and their longest run.
+impl Display for ThresholdState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.state)?;
+        write_exceedances(f, '>', self.thresholds.above, &self.above)?;
+        write_exceedances(f, '<', self.thresholds.below, &self.below)
+    }
+}
+
+fn write_reading<W: Write>(out: &mut W, reading: &Reading) -> std::fmt::Result {
+    write_tenths(out, reading.tenths as i64)?;
+    write!(out, "@{}", reading.offset)
+}
+
+fn write_readings<W: Write>(out: &mut W, label: &str, readings: &[Reading]) -> std::fmt::Result {
+    write!(out, " {label}:")?;
+    for (i, reading) in readings.iter().enumerate() {
+        if i != 0 {
+            out.write_char(';')?;
+        }
+        write_reading(out, reading)?;
+    }
+    Ok(())
+}
+
+/// `min/mean/max hottest:T@offset;... coldest:T@offset;...`, where `offset`
+/// is the byte offset of the reading's record in the file.
+impl Display for ExtremeState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.state)?;
+        write_readings(f, "hottest", &self.extremes.hottest)?;
+        write_readings(f, "coldest", &self.extremes.coldest)
+    }
+}
+
+/// One line per direction: `hottest: name=T@offset, ...`.
+impl Display for GlobalExtremes<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (label, readings) in [("hottest", &self.hottest), ("coldest", &self.coldest)] {
+            write!(f, "{label}: ")?;
+            for (i, (name, reading)) in readings.iter().enumerate() {
+                if i != 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{name}=")?;
+                write_reading(f, reading)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// `min/mean/max series:BUCKET=min/mean/max;...` with the buckets in
+/// chronological order, e.g. `2026-10-17T12` for hourly ones.
+impl Display for TimeSeriesState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} series:", self.state)?;
+        for (i, (bucket, state)) in self.buckets.iter().enumerate() {
+            if i != 0 {
+                f.write_char(';')?;
+            }
+            self.granularity.write_bucket(f, *bucket)?;
+            write!(f, "={state}")?;
+        }
+        Ok(())
+    }
+}
+
+/// `column:min/mean/max` for every column of the schema, separated by spaces.
+impl Display for MultiState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (column, state)) in self.schema.columns().iter().zip(&self.columns).enumerate() {
+            if i != 0 {
+                f.write_char(' ')?;
+            }
+            write!(f, "{column}:{state}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats sorted results as the challenge expects them on stdout:
+/// `{name=min/mean/max, ...}` followed by a newline.
+pub fn format_output<S: Display>(result: &[(String, S)]) -> String {
+    let mut res = String::with_capacity(result.len() * 32 + 3);
+    res.push('{');
+    for (i, (name, state)) in result.iter().enumerate() {
+        if i != 0 {
+            res.push_str(", ");
+        }
+        res.push_str(name);
+        res.push('=');
+        write!(res, "{state}").unwrap();
+    }
+    res.push_str("}\n");
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Cursor};
+    use std::path::Path;
+
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn test_round_mean() {
+        assert_eq!(3, round_mean(5, 2));
+        assert_eq!(-2, round_mean(-5, 2));
+        assert_eq!(-7, round_mean(-15, 2));
+        assert_eq!(1, round_mean(4, 3));
+        assert_eq!(-1, round_mean(-4, 3));
+        assert_eq!(0, round_mean(-1, 3));
+        assert_eq!(-999, round_mean(-999, 1));
+
+        assert_eq!(3, round_tenths(0.25));
+        assert_eq!(-2, round_tenths(-0.25));
+        assert_eq!(0, round_tenths(-0.0));
+        assert_eq!(-999, round_tenths(-99.9));
+    }
+
+    #[test]
+    fn test_no_negative_zero() {
+        let mut out = String::new();
+        write_tenths(&mut out, 0).unwrap();
+        write_tenths(&mut out, -1).unwrap();
+        write_tenths(&mut out, 999).unwrap();
+        write_tenths(&mut out, -999).unwrap();
+        assert_eq!("0.0-0.199.9-99.9", out);
+
+        let state = StateI {
+            min: -1,
+            max: 0,
+            count: 3,
+            sum: -1,
+        };
+        assert_eq!("-0.1/0.0/0.0", state.to_string());
+        assert_eq!("-0.1/0.0/0.0", state.to_f64().to_string());
+    }
+
+    /// Runs every implementation over each `.txt` of `dir` and compares its
+    /// output with the `.out` next to it, the layout of the challenge's test
+    /// files.
+    fn check_samples(dir: &Path) {
+        let mut samples: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .collect();
+        samples.sort();
+        assert!(!samples.is_empty(), "No samples in {}", dir.display());
+
+        for sample in samples {
+            let content = std::fs::read(&sample).unwrap();
+            let expected = std::fs::read_to_string(sample.with_extension("out")).unwrap();
+            for implementation in implementations::<Cursor<&[u8]>>() {
+                if implementation.name.ends_with("_dummy")
+                    || implementation.input != InputFormat::Text
+                {
+                    continue;
+                }
+                let all = (implementation.parse)(
+                    BufReader::new(Cursor::new(&content[..])),
+                    0,
+                    (content.len() - 1) as u64,
+                    true,
+                );
+                assert_eq!(
+                    expected,
+                    format_output(&all),
+                    "{} on {}",
+                    implementation.name,
+                    sample.display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_samples() {
+        check_samples(&Path::new(env!("CARGO_MANIFEST_DIR")).join("test_resources/samples"));
+    }
+
+    /// Checks the samples of the 1BRC repository, which
+    /// `scripts/fetch_1brc_samples.sh` copies to `test_resources/official` on
+    /// the first run. Needs `git` and network access, run with
+    /// `cargo test -- --ignored test_official_samples`.
+    #[test]
+    #[ignore]
+    fn test_official_samples() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let dir = manifest_dir.join("test_resources/official");
+        let status = std::process::Command::new("bash")
+            .arg(manifest_dir.join("../scripts/fetch_1brc_samples.sh"))
+            .arg(&dir)
+            .status()
+            .unwrap();
+        assert!(
+            status.success(),
+            "Fetching the 1BRC samples failed: {status}"
+        );
+        check_samples(&dir);
+    }
+}
@@ -0,0 +1,498 @@
+use std::fmt::Display;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::simd::cmp::SimdPartialEq;
+use std::simd::u8x64;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Stops the parsers reading through the [`TrackedReader`]s of a
+/// [`ProgressTracker`]. Clones share the flag, so a caller can keep one to
+/// cancel a run from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn cancel(&self) { self.0.store(true, Ordering::Relaxed); }
+
+    pub fn is_cancelled(&self) -> bool { self.0.load(Ordering::Relaxed) }
+}
+
+/// The error of a [`ProgressTracker::run`] cancelled before every chunk was
+/// parsed, and of the reads of a cancelled [`TrackedReader`], which the parse
+/// loops stop on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl Cancelled {
+    /// Whether `error` is the one a cancelled [`TrackedReader`] returns.
+    pub fn is(error: &std::io::Error) -> bool {
+        error.get_ref().is_some_and(|e| e.is::<Cancelled>())
+    }
+}
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Cancelled before every chunk was parsed")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+#[derive(Debug, Default)]
+struct Counters {
+    bytes: AtomicU64,
+    records: AtomicU64,
+}
+
+/// What one worker thread has parsed so far.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThreadProgress {
+    pub bytes: u64,
+    pub records: u64,
+    /// Bytes per second since the tracker was created.
+    pub rate: f64,
+}
+
+/// A snapshot of a [`ProgressTracker`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// Bytes the run is going to parse.
+    pub total_bytes: u64,
+    pub elapsed: Duration,
+    pub threads: Vec<ThreadProgress>,
+    /// Whether this is the last snapshot of the run.
+    pub finished: bool,
+}
+
+impl Progress {
+    pub fn bytes(&self) -> u64 { self.threads.iter().map(|t| t.bytes).sum() }
+
+    pub fn records(&self) -> u64 { self.threads.iter().map(|t| t.records).sum() }
+
+    /// Bytes per second over all threads.
+    pub fn rate(&self) -> f64 { self.threads.iter().map(|t| t.rate).sum() }
+
+    /// Parsed share of the total, within `0.0..=1.0`.
+    pub fn fraction(&self) -> f64 {
+        if self.total_bytes == 0 {
+            1.0
+        } else {
+            (self.bytes() as f64 / self.total_bytes as f64).min(1.0)
+        }
+    }
+}
+
+type ProgressCallback = Box<dyn Fn(&Progress) + Send + Sync>;
+
+/// Counts the bytes and records parsed by every worker thread of a parallel
+/// run, through the readers it hands out, and reports them to a callback.
+///
+/// Every `parse_large_chunks_*` loop reads its chunk in large buffers and
+/// seeks back to the last newline of each, so the counts advance between
+/// buffers. Once cancelled, a [`TrackedReader`] fails its reads with
+/// [`Cancelled`], on which the loops stop, and [`ProgressTracker::run`]
+/// reports the run as cancelled.
+pub struct ProgressTracker {
+    threads: Vec<Arc<Counters>>,
+    token: CancellationToken,
+    /// Whether a reader failed a read with [`Cancelled`], leaving its chunk
+    /// partly parsed.
+    interrupted: Arc<AtomicBool>,
+    start: Instant,
+    total_bytes: AtomicU64,
+    callback: Option<(Duration, ProgressCallback)>,
+}
+
+impl ProgressTracker {
+    pub fn new(threads: usize) -> Self {
+        Self {
+            threads: (0..threads.max(1)).map(|_| Arc::default()).collect(),
+            token: CancellationToken::new(),
+            interrupted: Arc::default(),
+            start: Instant::now(),
+            total_bytes: AtomicU64::new(0),
+            callback: None,
+        }
+    }
+
+    /// Calls `callback` every `interval` during [`ProgressTracker::run`], and
+    /// once more with [`Progress::finished`] set when it is done.
+    pub fn with_callback(
+        mut self,
+        interval: Duration,
+        callback: impl Fn(&Progress) + Send + Sync + 'static,
+    ) -> Self {
+        self.callback = Some((interval, Box::new(callback)));
+        self
+    }
+
+    /// Uses `token`, shared with the caller, instead of a fresh one.
+    pub fn with_token(mut self, token: CancellationToken) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn token(&self) -> &CancellationToken { &self.token }
+
+    /// A reader of the text chunk `start..=end_inclusive` of `inner` for
+    /// worker `thread`, counting the bytes of the chunk and, if the tracker
+    /// has a callback, its lines. Counting lines is a second pass over every
+    /// buffer, so it is skipped when nobody is told about them.
+    pub fn reader<R>(
+        &self,
+        thread: usize,
+        inner: R,
+        start: u64,
+        end_inclusive: u64,
+    ) -> TrackedReader<R> {
+        TrackedReader {
+            inner,
+            counters: self.threads[thread].clone(),
+            token: self.token.clone(),
+            interrupted: self.interrupted.clone(),
+            position: 0,
+            chunk: start..end_inclusive + 1,
+            counted: Vec::new(),
+            fresh: Vec::new(),
+            lines: true,
+            records: self.callback.is_some(),
+            line_start: true,
+            stopped: false,
+        }
+    }
+
+    /// A reader of the binary chunk `start..=end_inclusive` of `inner` for
+    /// worker `thread`, counting its bytes and stopping anywhere when
+    /// cancelled.
+    pub fn binary_reader<R>(
+        &self,
+        thread: usize,
+        inner: R,
+        start: u64,
+        end_inclusive: u64,
+    ) -> TrackedReader<R> {
+        TrackedReader {
+            lines: false,
+            records: false,
+            ..self.reader(thread, inner, start, end_inclusive)
+        }
+    }
+
+    pub fn snapshot(&self) -> Progress {
+        let elapsed = self.start.elapsed();
+        let seconds = elapsed.as_secs_f64().max(f64::MIN_POSITIVE);
+        let threads = self
+            .threads
+            .iter()
+            .map(|counters| {
+                let bytes = counters.bytes.load(Ordering::Relaxed);
+                ThreadProgress {
+                    bytes,
+                    records: counters.records.load(Ordering::Relaxed),
+                    rate: bytes as f64 / seconds,
+                }
+            })
+            .collect();
+        Progress {
+            total_bytes: self.total_bytes.load(Ordering::Relaxed),
+            elapsed,
+            threads,
+            finished: false,
+        }
+    }
+
+    /// Runs `work`, which parses about `total_bytes` through the readers of
+    /// the tracker, on the current thread while another thread reports
+    /// progress to the callback, if there is one. Fails with [`Cancelled`]
+    /// when a reader stopped a parser before the end of its chunk, as the
+    /// result of `work` is then partial.
+    pub fn run<T>(&self, total_bytes: u64, work: impl FnOnce() -> T) -> Result<T, Cancelled> {
+        self.total_bytes.store(total_bytes, Ordering::Relaxed);
+        let result = self.report(work);
+        if self.interrupted.load(Ordering::Relaxed) {
+            Err(Cancelled)
+        } else {
+            Ok(result)
+        }
+    }
+
+    /// Runs `work` while reporting progress to the callback, if there is one.
+    fn report<T>(&self, work: impl FnOnce() -> T) -> T {
+        let Some((interval, callback)) = &self.callback else {
+            return work();
+        };
+        let done = AtomicBool::new(false);
+        let result = thread::scope(|scope| {
+            let reporter = scope.spawn(|| {
+                loop {
+                    thread::park_timeout(*interval);
+                    if done.load(Ordering::Acquire) {
+                        return;
+                    }
+                    callback(&self.snapshot());
+                }
+            });
+            let result = work();
+            done.store(true, Ordering::Release);
+            reporter.thread().unpark();
+            reporter.join().unwrap();
+            result
+        });
+        callback(&Progress {
+            finished: true,
+            ..self.snapshot()
+        });
+        result
+    }
+}
+
+/// A reader handed out by [`ProgressTracker::reader`]. Only the bytes of the
+/// chunk are counted, and each of them once, even when the parser seeks back
+/// and reads them again or reads the end of the file first. Once cancelled, it
+/// finishes the line at hand and then fails every read with [`Cancelled`].
+pub struct TrackedReader<R> {
+    inner: R,
+    counters: Arc<Counters>,
+    token: CancellationToken,
+    interrupted: Arc<AtomicBool>,
+    position: u64,
+    chunk: Range<u64>,
+    /// Disjoint, sorted ranges of the chunk counted so far.
+    counted: Vec<Range<u64>>,
+    /// The ranges of the last read that were not counted before it.
+    fresh: Vec<Range<u64>>,
+    /// Whether the input is text, with lines to finish.
+    lines: bool,
+    /// Whether to count the lines of the chunk as records.
+    records: bool,
+    /// Whether the next read starts a line. Parsers only seek to line starts.
+    line_start: bool,
+    stopped: bool,
+}
+
+impl<R: Read + Seek> Read for TrackedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let cancelled = self.token.is_cancelled();
+        if self.stopped || cancelled && (self.line_start || !self.lines) {
+            self.stopped = true;
+            self.interrupted.store(true, Ordering::Relaxed);
+            return Err(std::io::Error::other(Cancelled));
+        }
+        let mut read = self.inner.read(buf)?;
+        if cancelled && let Some(newline) = memchr::memchr(b'\n', &buf[..read]) {
+            self.inner
+                .seek(SeekFrom::Current(newline as i64 + 1 - read as i64))?;
+            read = newline + 1;
+            self.stopped = true;
+        }
+
+        let read_range =
+            self.position.max(self.chunk.start)..(self.position + read as u64).min(self.chunk.end);
+        self.count(read_range);
+        for new in &self.fresh {
+            let counted =
+                &buf[(new.start - self.position) as usize..(new.end - self.position) as usize];
+            self.counters
+                .bytes
+                .fetch_add(counted.len() as u64, Ordering::Relaxed);
+            if self.records {
+                self.counters
+                    .records
+                    .fetch_add(count_newlines(counted) as u64, Ordering::Relaxed);
+            }
+        }
+        self.position += read as u64;
+        if read != 0 {
+            self.line_start = buf[read - 1] == b'\n';
+        }
+        Ok(read)
+    }
+}
+
+impl<R> TrackedReader<R> {
+    /// Marks `range` as counted, leaving its parts that were not yet in
+    /// `fresh`. Neither list is reallocated once it has grown to its size.
+    fn count(&mut self, range: Range<u64>) {
+        self.fresh.clear();
+        if range.is_empty() {
+            return;
+        }
+        // The counted ranges that overlap or touch `range`
+        let first = self.counted.partition_point(|done| done.end < range.start);
+        let last = first + self.counted[first..].partition_point(|done| done.start <= range.end);
+        let mut from = range.start;
+        for done in &self.counted[first..last] {
+            if done.start > from {
+                self.fresh.push(from..done.start);
+            }
+            from = from.max(done.end);
+        }
+        if from < range.end {
+            self.fresh.push(from..range.end);
+        }
+
+        let merged = if first < last {
+            self.counted[first].start.min(range.start)..self.counted[last - 1].end.max(range.end)
+        } else {
+            range
+        };
+        self.counted.splice(first..last, [merged]);
+    }
+}
+
+fn count_newlines(bytes: &[u8]) -> usize {
+    let newlines = u8x64::splat(b'\n');
+    let mut chunks = bytes.chunks_exact(64);
+    let mut n: usize = (&mut chunks)
+        .map(|chunk| {
+            u8x64::from_slice(chunk)
+                .simd_eq(newlines)
+                .to_bitmask()
+                .count_ones() as usize
+        })
+        .sum();
+    n += chunks.remainder().iter().filter(|&&b| b == b'\n').count();
+    n
+}
+
+impl<R: Seek> Seek for TrackedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+        self.line_start = true;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Cursor};
+
+    use super::*;
+    use crate::{
+        ColumnarFile, ColumnarWriter, StateI, parse_columnar, parse_large_chunks_memchr_table,
+        parse_large_chunks_simd_v2,
+    };
+
+    #[test]
+    fn test_progress_tracker() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let lines = content.iter().filter(|&&b| b == b'\n').count() as u64;
+        let newlines: Vec<usize> = memchr::memchr_iter(b'\n', content).collect();
+        let middle = newlines[newlines.len() / 2] as u64;
+        let end = content.len() as u64 - 1;
+
+        let snapshots = Arc::new(AtomicU64::new(0));
+        let counter = snapshots.clone();
+        let tracker = ProgressTracker::new(2).with_callback(Duration::from_millis(1), move |p| {
+            assert!(p.bytes() <= p.total_bytes);
+            if p.finished {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        // Two chunks on two "threads", read through small buffers
+        tracker
+            .run(content.len() as u64, || {
+                for (thread, (start, end)) in
+                    [(0, middle), (middle + 1, end)].into_iter().enumerate()
+                {
+                    let rdr = tracker.reader(thread, Cursor::new(&content[..]), start, end);
+                    parse_large_chunks_simd_v2(
+                        BufReader::with_capacity(64, rdr),
+                        start,
+                        end,
+                        false,
+                    );
+                }
+            })
+            .unwrap();
+        let progress = tracker.snapshot();
+        assert_eq!(content.len() as u64, progress.bytes());
+        assert_eq!(lines, progress.records());
+        assert_eq!(middle + 1, progress.threads[0].bytes);
+        assert_eq!(1.0, progress.fraction());
+        assert_eq!(1, snapshots.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_binary_progress() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let mut bytes = Vec::new();
+        let mut writer = ColumnarWriter::new(&mut bytes, 100).unwrap();
+        writer.push_text(&content[..]).unwrap();
+        writer.finish().unwrap();
+        let file = ColumnarFile::open(&mut Cursor::new(&bytes)).unwrap();
+        assert!(file.blocks() > 2);
+
+        // Split at a block start, the footer and the dictionary read first
+        let tracker = ProgressTracker::new(2);
+        let middle = file.block_offset(file.blocks() / 2);
+        let end = bytes.len() as u64 - 1;
+        for (thread, (start, end)) in [(0, middle - 1), (middle, end)].into_iter().enumerate() {
+            let rdr = tracker.binary_reader(thread, Cursor::new(&bytes[..]), start, end);
+            parse_columnar(BufReader::with_capacity(64, rdr), start, end, false);
+        }
+        let progress = tracker.snapshot();
+        assert_eq!(bytes.len() as u64, progress.bytes());
+        assert_eq!(middle, progress.threads[0].bytes);
+        assert_eq!(0, progress.records());
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let end = content.len() as u64 - 1;
+        let count = |all: &[(String, StateI)]| all.iter().map(|(_, s)| s.count).sum::<u64>();
+
+        let token = CancellationToken::new();
+        let tracker = ProgressTracker::new(1).with_token(token.clone());
+        token.cancel();
+        let parsed = tracker.run(content.len() as u64, || {
+            let rdr = BufReader::new(tracker.reader(0, Cursor::new(&content[..]), 0, end));
+            parse_large_chunks_memchr_table(rdr, 0, end, false)
+        });
+        assert_eq!(Err(Cancelled), parsed);
+
+        // Binary readers stop anywhere, and the run fails the same way
+        let mut bytes = Vec::new();
+        let mut writer = ColumnarWriter::new(&mut bytes, 100).unwrap();
+        writer.push_text(&content[..]).unwrap();
+        writer.finish().unwrap();
+        let end = bytes.len() as u64 - 1;
+        let parsed = tracker.run(bytes.len() as u64, || {
+            let rdr = tracker.binary_reader(0, Cursor::new(&bytes[..]), 0, end);
+            parse_columnar(BufReader::new(rdr), 0, end, false)
+        });
+        assert_eq!(Err(Cancelled), parsed);
+
+        // Cancelled mid-line, the reader finishes the line for line parsers
+        let tracker = ProgressTracker::new(1).with_callback(Duration::from_secs(60), |_| ());
+        let mut rdr = tracker.reader(0, Cursor::new(&content[..]), 0, end);
+        let mut buf = [0; 100];
+        rdr.read_exact(&mut buf).unwrap();
+        tracker.token().cancel();
+        let (mut rest, mut next) = (Vec::new(), [0; 16]);
+        let error = loop {
+            match rdr.read(&mut next) {
+                Ok(read) => rest.extend_from_slice(&next[..read]),
+                Err(e) => break e,
+            }
+        };
+        assert!(Cancelled::is(&error));
+        let first = memchr::memchr(b'\n', &content[100..]).unwrap() + 1;
+        assert_eq!(&content[100..100 + first], &rest[..]);
+        let parsed = [&buf[..], &rest[..]].concat();
+        let records = tracker.snapshot().records();
+        assert_eq!(
+            parsed.iter().filter(|&&b| b == b'\n').count() as u64,
+            records
+        );
+        let rdr = BufReader::new(Cursor::new(parsed.as_slice()));
+        let all = crate::naive_line_by_line(rdr, 0, parsed.len() as u64 - 1, false);
+        assert_eq!(records, count(&all));
+    }
+}
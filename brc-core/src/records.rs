@@ -0,0 +1,168 @@
+use crate::kernel::Kernel;
+use crate::to_scaled_integer_branchless;
+
+/// Bytes that must follow the records in the buffer given to [`Records`], so
+/// that names and measurements can be loaded eight bytes at a time.
+pub const RECORD_PADDING: usize = 8;
+
+/// Records parsed per kernel call.
+const BATCH_LEN: usize = 256;
+
+/// Records parsed ahead by [`Records`]: name start, name end and value.
+pub(crate) struct RecordBatch<const SIZE: usize> {
+    records: Vec<(usize, usize, i16)>,
+    /// Start of the first record after the batch.
+    end: usize,
+}
+
+impl<const SIZE: usize> RecordBatch<SIZE> {
+    fn new() -> Self {
+        Self {
+            records: Vec::with_capacity(SIZE),
+            end: 0,
+        }
+    }
+}
+
+/// Parses up to `SIZE` records from the start of `buffer` like
+/// `process_buffer_memchr_i64`.
+#[inline(always)]
+fn process_buffer_record_batch<const SIZE: usize>(
+    batch: &mut RecordBatch<SIZE>,
+    buffer: &[u8],
+    valid_len: usize,
+) {
+    batch.records.clear();
+    let mut next_name_idx = 0;
+    for semicolon_idx in memchr::memchr_iter(b';', &buffer[..valid_len]) {
+        let measurement_idx = semicolon_idx + 1;
+        let packed = i64::from_le_bytes(
+            buffer[measurement_idx..measurement_idx + 8]
+                .try_into()
+                .unwrap(),
+        );
+        let (value, record_tail_len) = to_scaled_integer_branchless(packed);
+
+        batch.records.push((next_name_idx, semicolon_idx, value));
+        next_name_idx = measurement_idx + record_tail_len as usize;
+        if batch.records.len() == SIZE {
+            break;
+        }
+    }
+    batch.end = next_name_idx;
+}
+
+multiversion! {
+    /// Runs [`process_buffer_record_batch`] compiled for `kernel`.
+    fn dispatch_record_batch(&mut RecordBatch<SIZE>) => process_buffer_record_batch
+}
+
+/// Zero-copy iterator over the `name;temperature\n` records of a buffer,
+/// yielding the name, the temperature in tenths and the offset of the record
+/// in the buffer. Records are parsed in batches by the branchless decoder of
+/// the fastest parsers, compiled for the [`Kernel`] the iterator was created
+/// with.
+///
+/// Like all parsers, it trusts the input: the buffer must hold whole records
+/// with one fractional digit in the challenge's range.
+pub struct Records<'a> {
+    buffer: &'a [u8],
+    len: usize,
+    kernel: Kernel,
+    batch: RecordBatch<BATCH_LEN>,
+    /// Start of the batch in `buffer`.
+    batch_start: usize,
+    /// Index of the next record to yield in the batch.
+    next: usize,
+}
+
+impl<'a> Records<'a> {
+    /// Iterates over the records in the first `len` bytes of `buffer`, which
+    /// must end with a newline and be followed by at least
+    /// [`RECORD_PADDING`] bytes, using [`Kernel::selected`].
+    pub fn new(buffer: &'a [u8], len: usize) -> Self {
+        Self::with_kernel(buffer, len, Kernel::selected())
+    }
+
+    /// [`Records::new`] for a specific kernel, which must be supported.
+    pub fn with_kernel(buffer: &'a [u8], len: usize, kernel: Kernel) -> Self {
+        assert!(
+            buffer.len() >= len + RECORD_PADDING,
+            "Records need {RECORD_PADDING} bytes of padding after {len} bytes, the buffer has {}",
+            buffer.len()
+        );
+        assert!(
+            len == 0 || buffer[len - 1] == b'\n',
+            "Records must end with a newline"
+        );
+        assert!(kernel.is_supported(), "{kernel} is not supported");
+        Self {
+            buffer,
+            len,
+            kernel,
+            batch: RecordBatch::new(),
+            batch_start: 0,
+            next: 0,
+        }
+    }
+
+    pub fn kernel(&self) -> Kernel { self.kernel }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = (&'a [u8], i16, usize);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == self.batch.records.len() {
+            self.batch_start += self.batch.end;
+            if self.batch_start >= self.len {
+                return None;
+            }
+            dispatch_record_batch(
+                self.kernel,
+                &mut self.batch,
+                &self.buffer[self.batch_start..],
+                self.len - self.batch_start,
+            );
+            self.next = 0;
+        }
+        let (name_start, name_end, value) = self.batch.records[self.next];
+        self.next += 1;
+        let name = &self.buffer[self.batch_start + name_start..self.batch_start + name_end];
+        Some((name, value, self.batch_start + name_start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::to_scaled_integer;
+
+    #[test]
+    fn test_records_match_lines() {
+        let content = include_bytes!("../test_resources/sample.txt");
+        let mut padded = content.to_vec();
+        padded.resize(content.len() + RECORD_PADDING, 0);
+
+        let mut expected = Vec::new();
+        let mut offset = 0;
+        for line in content.split_inclusive(|&b| b == b'\n') {
+            let semicolon = line.iter().position(|&b| b == b';').unwrap();
+            let value = to_scaled_integer(&line[semicolon + 1..line.len() - 1]);
+            expected.push((&line[..semicolon], value, offset));
+            offset += line.len();
+        }
+        assert!(expected.len() > BATCH_LEN);
+
+        for kernel in Kernel::supported() {
+            let records: Vec<_> = Records::with_kernel(&padded, content.len(), kernel).collect();
+            assert_eq!(expected, records, "{kernel}");
+        }
+        assert_eq!(0, Records::new(&padded, 0).count());
+    }
+
+    #[test]
+    #[should_panic(expected = "padding")]
+    fn test_records_need_padding() { Records::new(b"Oslo;1.0\n", 9); }
+}
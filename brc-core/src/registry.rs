@@ -0,0 +1,148 @@
+use std::io::{BufReader, Read, Seek};
+
+use crate::columnar::parse_columnar;
+use crate::kernel::Kernel;
+use crate::*;
+
+/// Signature shared by all implementations: a reader, the inclusive byte range
+/// to aggregate and whether the result should be sorted by station name.
+pub type ParseFn<R> = fn(BufReader<R>, u64, u64, bool) -> Vec<(String, StateI)>;
+
+/// The kind of file an implementation reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// `name;temperature\n` records.
+    Text,
+    /// Files written by [`ColumnarWriter`].
+    Columnar,
+}
+
+/// A named implementation that can be selected at runtime, e.g. by `brc-app`.
+pub struct Implementation<R> {
+    pub name: &'static str,
+    pub parse: ParseFn<R>,
+    /// Whether the hot loop is compiled for several instruction-set levels
+    /// and picked at runtime, see [`Kernel`].
+    pub multiversioned: bool,
+    pub input: InputFormat,
+}
+
+impl<R> Implementation<R> {
+    pub(crate) const fn new(name: &'static str, parse: ParseFn<R>) -> Self {
+        Self {
+            name,
+            parse,
+            multiversioned: false,
+            input: InputFormat::Text,
+        }
+    }
+
+    pub(crate) const fn multiversioned(name: &'static str, parse: ParseFn<R>) -> Self {
+        Self {
+            name,
+            parse,
+            multiversioned: true,
+            input: InputFormat::Text,
+        }
+    }
+
+    pub(crate) const fn columnar(name: &'static str, parse: ParseFn<R>) -> Self {
+        Self {
+            name,
+            parse,
+            multiversioned: false,
+            input: InputFormat::Columnar,
+        }
+    }
+
+    /// The kernel this implementation runs on the current host.
+    pub fn kernel(&self) -> Kernel {
+        if self.multiversioned {
+            Kernel::selected()
+        } else {
+            Kernel::baseline()
+        }
+    }
+}
+
+/// All implementations, in the order they were developed.
+pub fn implementations<R: Read + Seek>() -> Vec<Implementation<R>> {
+    vec![
+        Implementation::new("naive_line_by_line_dummy", naive_line_by_line_dummy),
+        Implementation::new("naive_line_by_line", naive_line_by_line),
+        Implementation::new("naive_line_by_line_v2", naive_line_by_line_v2),
+        Implementation::new(
+            "parse_large_chunks_as_bytes_dummy",
+            parse_large_chunks_as_bytes_dummy,
+        ),
+        Implementation::new("parse_large_chunks_as_bytes", parse_large_chunks_as_bytes),
+        Implementation::new(
+            "parse_large_chunks_as_i64_dummy",
+            parse_large_chunks_as_i64_dummy,
+        ),
+        Implementation::new("parse_large_chunks_as_i64", parse_large_chunks_as_i64),
+        Implementation::new("parse_large_chunks_as_i64_v2", parse_large_chunks_as_i64_v2),
+        Implementation::new(
+            "parse_large_chunks_as_i64_unsafe",
+            parse_large_chunks_as_i64_unsafe,
+        ),
+        Implementation::new(
+            "parse_large_chunks_as_i64_as_java",
+            parse_large_chunks_as_i64_as_java,
+        ),
+        Implementation::new(
+            "parse_large_chunks_simd_dummy",
+            parse_large_chunks_simd_dummy,
+        ),
+        Implementation::new("parse_large_chunks_simd", parse_large_chunks_simd),
+        Implementation::new("parse_large_chunks_simd_v1", parse_large_chunks_simd_v1),
+        Implementation::new(
+            "parse_large_chunks_simd_v2_dummy",
+            parse_large_chunks_simd_v2_dummy,
+        ),
+        Implementation::new("parse_large_chunks_simd_v2", parse_large_chunks_simd_v2),
+        Implementation::multiversioned(
+            "parse_large_chunks_memchr_table",
+            parse_large_chunks_memchr_table,
+        ),
+        // Two to four cursors; other counts are library calls only
+        Implementation::multiversioned(
+            "parse_large_chunks_interleaved2_table",
+            parse_large_chunks_interleaved_table::<2, R>,
+        ),
+        Implementation::multiversioned(
+            "parse_large_chunks_interleaved3_table",
+            parse_large_chunks_interleaved_table::<3, R>,
+        ),
+        Implementation::multiversioned(
+            "parse_large_chunks_interleaved4_table",
+            parse_large_chunks_interleaved_table::<4, R>,
+        ),
+        Implementation::multiversioned(
+            "parse_large_chunks_std_simd_table",
+            parse_large_chunks_std_simd_table,
+        ),
+        Implementation::multiversioned(
+            "parse_large_chunks_simd_temperature_table",
+            parse_large_chunks_simd_temperature_table,
+        ),
+        Implementation::multiversioned(
+            "parse_large_chunks_full_simd_table",
+            parse_large_chunks_full_simd_table,
+        ),
+        Implementation::multiversioned(
+            "parse_large_chunks_avx512_table",
+            parse_large_chunks_avx512_table,
+        ),
+        Implementation::multiversioned(
+            "parse_large_chunks_structural_table",
+            parse_large_chunks_structural_table,
+        ),
+        Implementation::columnar("columnar_blocks", parse_columnar),
+    ]
+}
+
+/// Looks up an implementation by its name.
+pub fn find_implementation<R: Read + Seek>(name: &str) -> Option<Implementation<R>> {
+    implementations().into_iter().find(|i| i.name == name)
+}
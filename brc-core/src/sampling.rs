@@ -0,0 +1,187 @@
+use rustc_hash::FxHashMap;
+
+use crate::StateI;
+
+/// The normal quantile of a two-sided 95% confidence interval.
+pub const Z_95: f64 = 1.96;
+
+/// Sums over the sampled chunks of one station, `x` its records and `y` their
+/// sum in tenths per chunk.
+#[derive(Debug, Clone)]
+struct Cluster {
+    min: i16,
+    max: i16,
+    x: u64,
+    y: i64,
+    xx: f64,
+    yy: f64,
+    xy: f64,
+}
+
+/// Aggregates of a random sample of the chunks of an input, drawn without
+/// replacement from `chunks` chunks of about equal size. Every chunk is one
+/// cluster: the mean of a station is estimated as the ratio of its sampled sum
+/// and count, with the variance of that ratio across the sampled chunks, so
+/// readings that are correlated within a chunk do not narrow the interval.
+#[derive(Debug, Clone)]
+pub struct ChunkSample {
+    chunks: u64,
+    sampled: u64,
+    stations: FxHashMap<String, Cluster>,
+}
+
+/// What a [`ChunkSample`] tells about one station. `min` and `max` are the
+/// extremes of the sampled readings, so the true minimum is at most `min` and
+/// the true maximum at least `max`; with every chunk sampled, all of it is
+/// exact.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub min: i16,
+    pub max: i16,
+    /// Estimated mean, in degrees.
+    pub mean: f64,
+    /// Half-width of the confidence interval of the mean, in degrees;
+    /// infinite if it cannot be estimated from a single chunk.
+    pub margin: f64,
+    /// Estimated number of readings.
+    pub count: f64,
+    /// Readings in the sample.
+    pub sampled: u64,
+}
+
+impl ChunkSample {
+    pub fn new(chunks: u64) -> Self {
+        Self {
+            chunks,
+            sampled: 0,
+            stations: FxHashMap::default(),
+        }
+    }
+
+    /// Adds the aggregate of one sampled chunk, which may have no stations.
+    pub fn add_chunk(&mut self, stations: Vec<(String, StateI)>) {
+        assert!(
+            self.sampled < self.chunks,
+            "More than {} chunks",
+            self.chunks
+        );
+        self.sampled += 1;
+        for (name, state) in stations {
+            let (x, y) = (state.count as f64, state.sum as f64);
+            let cluster = self.stations.entry(name).or_insert(Cluster {
+                min: i16::MAX,
+                max: i16::MIN,
+                x: 0,
+                y: 0,
+                xx: 0.0,
+                yy: 0.0,
+                xy: 0.0,
+            });
+            cluster.min = cluster.min.min(state.min);
+            cluster.max = cluster.max.max(state.max);
+            cluster.x += state.count;
+            cluster.y += state.sum;
+            cluster.xx += x * x;
+            cluster.yy += y * y;
+            cluster.xy += x * y;
+        }
+    }
+
+    /// Number of chunks the sample was drawn from.
+    pub fn chunks(&self) -> u64 { self.chunks }
+
+    /// Number of chunks added so far.
+    pub fn sampled(&self) -> u64 { self.sampled }
+
+    /// The estimates of the stations seen in the sample, unsorted, with
+    /// intervals of `z` standard errors, e.g. [`Z_95`].
+    pub fn estimates(self, z: f64) -> Vec<(String, Estimate)> {
+        let n = self.sampled as f64;
+        let scale = self.chunks as f64 / n;
+        // Finite population correction: no uncertainty left with every chunk
+        let correction = 1.0 - n / self.chunks as f64;
+        self.stations
+            .into_iter()
+            .map(|(name, c)| {
+                let ratio = c.y as f64 / c.x as f64;
+                let margin = if correction == 0.0 {
+                    0.0
+                } else if self.sampled < 2 {
+                    f64::INFINITY
+                } else {
+                    let residuals = (c.yy - 2.0 * ratio * c.xy + ratio * ratio * c.xx).max(0.0);
+                    let mean_x = c.x as f64 / n;
+                    let variance = correction * residuals / (n - 1.0) / (n * mean_x * mean_x);
+                    z * variance.sqrt() / 10.0
+                };
+                let estimate = Estimate {
+                    min: c.min,
+                    max: c.max,
+                    mean: ratio / 10.0,
+                    margin,
+                    count: c.x as f64 * scale,
+                    sampled: c.x,
+                };
+                (name, estimate)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(stations: &[(&str, &[i16])]) -> Vec<(String, StateI)> {
+        stations
+            .iter()
+            .map(|(name, values)| {
+                let mut state = StateI::default();
+                for &v in *values {
+                    state.update(v);
+                }
+                (name.to_string(), state)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunk_sample() {
+        let chunks = [
+            chunk(&[("Oslo", &[10, 20]), ("Lima", &[200])]),
+            chunk(&[("Oslo", &[-40])]),
+            chunk(&[("Oslo", &[30, 30, 0]), ("Lima", &[220, 180])]),
+            chunk(&[]),
+        ];
+
+        // Every chunk: exact
+        let mut sample = ChunkSample::new(4);
+        for c in chunks.clone() {
+            sample.add_chunk(c);
+        }
+        let mut all = sample.estimates(Z_95);
+        crate::sort_result(&mut all);
+        let oslo = all[1].1;
+        assert_eq!((-40, 30), (oslo.min, oslo.max));
+        assert!((oslo.mean - 5.0 / 6.0).abs() < 1e-9);
+        assert_eq!((0.0, 6.0, 6), (oslo.margin, oslo.count, oslo.sampled));
+        assert_eq!("<=-4.0/0.83+-0.00/>=3.0 ~6", oslo.to_string());
+
+        // Two of eight chunks: counts scale, the interval widens
+        let mut sample = ChunkSample::new(8);
+        sample.add_chunk(chunks[0].clone());
+        sample.add_chunk(chunks[2].clone());
+        let mut all = sample.estimates(Z_95);
+        crate::sort_result(&mut all);
+        let (lima, oslo) = (all[0].1, all[1].1);
+        assert!((lima.mean - 20.0).abs() < 1e-9);
+        assert_eq!((0.0, 12.0), (lima.margin, lima.count));
+        assert!((oslo.mean - 1.8).abs() < 1e-9);
+        assert!(oslo.margin > 0.1 && oslo.margin.is_finite());
+        assert_eq!((0, 30), (oslo.min, oslo.max));
+
+        let mut single = ChunkSample::new(8);
+        single.add_chunk(chunks[0].clone());
+        assert!(single.estimates(Z_95)[0].1.margin.is_infinite());
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::StateI;
+use crate::fingerprint_table::{fingerprint, name_words};
+use crate::station_filter::read_station_list;
+
+/// Average number of stations per bucket of the perfect hash. Larger buckets
+/// make the dictionary smaller but slow down its construction.
+const STATIONS_PER_BUCKET: usize = 4;
+
+/// Upper bound on the seeds tried for a single bucket before giving up.
+const MAX_BUCKET_SEED: u32 = 1 << 24;
+
+/// What to do with records of stations that are not in the dictionary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownStations {
+    /// Panic on the first unknown station.
+    #[default]
+    Reject,
+    /// Skip the record and count it.
+    Count,
+}
+
+impl UnknownStations {
+    pub const fn name(self) -> &'static str {
+        match self {
+            UnknownStations::Reject => "reject",
+            UnknownStations::Count => "count",
+        }
+    }
+}
+
+impl Display for UnknownStations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str(self.name()) }
+}
+
+impl FromStr for UnknownStations {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [UnknownStations::Reject, UnknownStations::Count]
+            .into_iter()
+            .find(|u| u.name() == s)
+            .ok_or_else(|| format!("Unknown policy `{s}`, expected one of [\"reject\", \"count\"]"))
+    }
+}
+
+#[inline(always)]
+const fn mix(mut x: u64) -> u64 {
+    // MurmurHash3 finalizer.
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51afd7ed558ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
+    x ^ (x >> 33)
+}
+
+/// Maps a hash to `0..n` without a division.
+#[inline(always)]
+const fn reduce(hash: u64, n: usize) -> usize { ((hash as u128 * n as u128) >> 64) as usize }
+
+/// A fixed station set with a minimal perfect hash built at startup, using the
+/// hash-and-displace scheme: stations are grouped into buckets by their
+/// fingerprint and every bucket gets a seed that sends its stations to free
+/// slots. Station ids are the slots, so they are dense and a lookup is two
+/// hash evaluations and one name comparison, with no probing.
+///
+/// Like [`FingerprintTable`](crate::fingerprint_table::FingerprintTable), the
+/// hash only sees the fingerprint of a name, so two stations of the list that
+/// share a fingerprint are rejected when the dictionary is built.
+pub struct StationDictionary {
+    names: Vec<String>,
+    /// Length and [`name_words`] of every station, so that the verification of
+    /// names up to sixteen bytes long needs no access to `names`.
+    keys: Vec<(usize, u64, u64)>,
+    /// Pilot of every bucket, xored into the station hash to pick the slot.
+    pilots: Vec<u64>,
+}
+
+impl StationDictionary {
+    /// Builds the dictionary of the given station names, which must be unique.
+    pub fn new<I, S>(names: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let names: Vec<String> = names.into_iter().map(Into::into).collect();
+        let n = names.len();
+        let hashes: Vec<u64> = names
+            .iter()
+            .map(|name| mix(fingerprint(name.as_bytes(), &padded(name))))
+            .collect();
+
+        let bucket_count = n.div_ceil(STATIONS_PER_BUCKET).max(1);
+        let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); bucket_count];
+        for (i, hash) in hashes.iter().enumerate() {
+            buckets[reduce(*hash, bucket_count)].push(i);
+        }
+        for bucket in &buckets {
+            for (j, a) in bucket.iter().enumerate() {
+                if let Some(b) = bucket[j + 1..].iter().find(|b| hashes[**b] == hashes[*a]) {
+                    return Err(if names[*a] == names[*b] {
+                        format!("Station `{}` is listed twice", names[*a])
+                    } else {
+                        format!(
+                            "Stations `{}` and `{}` have the same fingerprint",
+                            names[*a], names[*b]
+                        )
+                    });
+                }
+            }
+        }
+
+        // Placing the largest buckets first keeps the seed search short.
+        let mut order: Vec<usize> = (0..bucket_count).collect();
+        order.sort_unstable_by_key(|b| std::cmp::Reverse(buckets[*b].len()));
+
+        let mut pilots = vec![0_u64; bucket_count];
+        let mut slots: Vec<Option<usize>> = vec![None; n];
+        let mut candidate = Vec::with_capacity(STATIONS_PER_BUCKET * 4);
+        for b in order {
+            let bucket = &buckets[b];
+            if bucket.is_empty() {
+                break;
+            }
+            let pilot = (0..MAX_BUCKET_SEED)
+                .map(pilot_of)
+                .find(|pilot| {
+                    candidate.clear();
+                    for i in bucket {
+                        let slot = slot_of(hashes[*i], *pilot, n);
+                        if slots[slot].is_some() || candidate.contains(&slot) {
+                            return false;
+                        }
+                        candidate.push(slot);
+                    }
+                    true
+                })
+                .ok_or_else(|| format!("Could not place a bucket of {} stations", bucket.len()))?;
+            pilots[b] = pilot;
+            for (i, slot) in bucket.iter().zip(&candidate) {
+                slots[*slot] = Some(*i);
+            }
+        }
+
+        let mut names: Vec<Option<String>> = names.into_iter().map(Some).collect();
+        let names: Vec<String> = slots
+            .into_iter()
+            .map(|i| names[i.unwrap()].take().unwrap())
+            .collect();
+        let keys = names
+            .iter()
+            .map(|name| {
+                let (first, last) = name_words(name.as_bytes(), &padded(name));
+                (name.len(), first, last)
+            })
+            .collect();
+        Ok(Self {
+            names,
+            keys,
+            pilots,
+        })
+    }
+
+    /// Reads one station name per line, skipping empty lines.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        Self::new(read_station_list(path)?)
+    }
+
+    pub fn len(&self) -> usize { self.names.len() }
+
+    pub fn is_empty(&self) -> bool { self.names.is_empty() }
+
+    /// Station names, indexed by id.
+    pub fn names(&self) -> &[String] { &self.names }
+
+    /// The id of `name`, where `padded_name` starts with `name` and holds at
+    /// least eight bytes.
+    #[inline(always)]
+    pub(crate) fn lookup(&self, name: &[u8], padded_name: &[u8]) -> Option<usize> {
+        if self.names.is_empty() {
+            return None;
+        }
+        let (first, last) = name_words(name, padded_name);
+        let hash = mix(fingerprint(name, padded_name));
+        let pilot = self.pilots[reduce(hash, self.pilots.len())];
+        let id = slot_of(hash, pilot, self.names.len());
+
+        let (len, expected_first, expected_last) = self.keys[id];
+        let matches = len == name.len()
+            && first == expected_first
+            && last == expected_last
+            && (len <= 16 || self.names[id].as_bytes()[8..len - 8] == name[8..len - 8]);
+        matches.then_some(id)
+    }
+
+    /// The id of `name`.
+    pub fn get(&self, name: &str) -> Option<usize> { self.lookup(name.as_bytes(), &padded(name)) }
+}
+
+/// Pilot of a bucket seed, see [`slot_of`].
+const fn pilot_of(seed: u32) -> u64 { (seed as u64).wrapping_mul(0x9e3779b97f4a7c15) }
+
+/// Slot of a station hash in a bucket with the given pilot.
+#[inline(always)]
+const fn slot_of(hash: u64, pilot: u64, n: usize) -> usize { reduce(mix(hash ^ pilot), n) }
+
+/// `name` followed by eight zero bytes, for [`fingerprint`] and [`name_words`].
+fn padded(name: &str) -> Vec<u8> {
+    let mut padded_name = name.as_bytes().to_vec();
+    padded_name.resize(name.len() + 8, 0);
+    padded_name
+}
+
+/// Per-station states indexed by [`StationDictionary`] ids.
+pub(crate) struct DictionaryTable<'a> {
+    dictionary: &'a StationDictionary,
+    states: Vec<StateI>,
+    unknown_policy: UnknownStations,
+    unknown: u64,
+}
+
+impl<'a> DictionaryTable<'a> {
+    pub(crate) fn new(dictionary: &'a StationDictionary, unknown_policy: UnknownStations) -> Self {
+        Self {
+            dictionary,
+            states: vec![StateI::default(); dictionary.len()],
+            unknown_policy,
+            unknown: 0,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn update(&mut self, name: &[u8], padded_name: &[u8], value: i16) {
+        match self.dictionary.lookup(name, padded_name) {
+            Some(id) => self.states[id].update(value),
+            None => match self.unknown_policy {
+                UnknownStations::Reject => {
+                    panic!("Unknown station `{}`", String::from_utf8_lossy(name))
+                },
+                UnknownStations::Count => self.unknown += 1,
+            },
+        }
+    }
+
+    /// Stations with at least one record and the number of skipped records.
+    pub(crate) fn into_result(self) -> (Vec<(String, StateI)>, u64) {
+        let stations = self
+            .dictionary
+            .names()
+            .iter()
+            .zip(self.states)
+            .filter(|(_, state)| state.count != 0)
+            .map(|(name, state)| (name.clone(), state))
+            .collect();
+        (stations, self.unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perfect_hash_is_minimal_and_exact() {
+        let names: Vec<String> = (0..1000).map(|i| format!("Station {i}")).collect();
+        let dictionary = StationDictionary::new(names.clone()).unwrap();
+        assert_eq!(names.len(), dictionary.len());
+
+        let mut ids: Vec<usize> = names
+            .iter()
+            .map(|name| dictionary.get(name).unwrap())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!((0..names.len()).collect::<Vec<_>>(), ids);
+        for name in &names {
+            assert_eq!(name, &dictionary.names()[dictionary.get(name).unwrap()]);
+        }
+
+        assert_eq!(None, dictionary.get("Station 1000"));
+        assert_eq!(None, dictionary.get(""));
+        assert_eq!(
+            None,
+            StationDictionary::new(Vec::<String>::new())
+                .unwrap()
+                .get("a")
+        );
+    }
+
+    #[test]
+    fn test_duplicate_station() {
+        let err = StationDictionary::new(["Oslo", "Paris", "Oslo"])
+            .err()
+            .unwrap();
+        assert_eq!("Station `Oslo` is listed twice", err);
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::fingerprint_table::{FingerprintSet, FingerprintTable, StationTable, slot_hash};
+use crate::{Normalization, StateI};
+
+/// Which stations to aggregate. A station is aggregated when it passes every
+/// condition added to the filter; an empty filter passes all stations.
+///
+/// The allowlist is also compiled into a [`FingerprintSet`], so that the table
+/// of [`parse_large_chunks_filtered`](crate::parse_large_chunks_filtered)
+/// rejects records of unlisted stations with a single probe and never stores
+/// them. The other conditions are evaluated once per distinct station, when
+/// the table first sees it. When the table normalizes names, every condition
+/// applies to the normalized name and the allowlist probe is skipped.
+#[derive(Default)]
+pub struct StationFilter {
+    allow: Option<(HashSet<String>, FingerprintSet)>,
+    deny: HashSet<String>,
+    prefix: Option<String>,
+    patterns: Vec<Regex>,
+}
+
+impl StationFilter {
+    pub fn new() -> Self { Self::default() }
+
+    /// Passes only the listed stations. Adding a second allowlist keeps the
+    /// stations listed in both.
+    pub fn allow<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut names: HashSet<String> = names.into_iter().map(Into::into).collect();
+        if let Some((allowed, _)) = self.allow.take() {
+            names.retain(|name| allowed.contains(name));
+        }
+        let fingerprints = FingerprintSet::new(names.iter().map(String::as_str));
+        self.allow = Some((names, fingerprints));
+        self
+    }
+
+    /// Rejects the listed stations.
+    pub fn deny<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.deny.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Passes only stations whose name starts with `prefix`.
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        let prefix = prefix.into();
+        assert!(
+            self.prefix.is_none(),
+            "Only one prefix can be set, got `{prefix}` after `{}`",
+            self.prefix.as_ref().unwrap()
+        );
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// Passes only stations whose name contains a match of `pattern`.
+    pub fn regex(mut self, pattern: &str) -> Result<Self, String> {
+        let regex = Regex::new(pattern).map_err(|e| format!("Invalid regex `{pattern}`: {e}"))?;
+        self.patterns.push(regex);
+        Ok(self)
+    }
+
+    /// Passes only stations whose whole name matches the glob `pattern`, with
+    /// `*`, `?` and `[...]` classes, negated by a leading `!`.
+    pub fn glob(mut self, pattern: &str) -> Result<Self, String> {
+        let regex = glob_to_regex(pattern)
+            .and_then(|regex| Regex::new(&regex).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Invalid glob `{pattern}`: {e}"))?;
+        self.patterns.push(regex);
+        Ok(self)
+    }
+
+    /// Whether the filter passes `name`.
+    pub fn accepts(&self, name: &str) -> bool {
+        self.allow
+            .as_ref()
+            .is_none_or(|(allowed, _)| allowed.contains(name))
+            && !self.deny.contains(name)
+            && self
+                .prefix
+                .as_ref()
+                .is_none_or(|prefix| name.starts_with(prefix.as_str()))
+            && self.patterns.iter().all(|regex| regex.is_match(name))
+    }
+
+    /// Whether the filter passes every station.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_none()
+            && self.deny.is_empty()
+            && self.prefix.is_none()
+            && self.patterns.is_empty()
+    }
+}
+
+/// Reads one station name per line, skipping empty lines.
+pub fn read_station_list<P: AsRef<Path>>(path: P) -> Result<Vec<String>, String> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Unable to read station list {}: {e}", path.display()))?;
+    Ok(content
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect())
+}
+
+pub(crate) fn glob_to_regex(glob: &str) -> Result<String, String> {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => {
+                regex.push('[');
+                if chars.clone().next() == Some('!') {
+                    chars.next();
+                    regex.push('^');
+                }
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    match c {
+                        ']' => {
+                            closed = true;
+                            break;
+                        },
+                        '\\' | '[' | '&' | '~' => {
+                            regex.push('\\');
+                            regex.push(c);
+                        },
+                        _ => regex.push(c),
+                    }
+                }
+                if !closed {
+                    return Err("unclosed `[`".to_owned());
+                }
+                regex.push(']');
+            },
+            _ => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    Ok(regex)
+}
+
+/// A [`FingerprintTable`] that only aggregates the stations a
+/// [`StationFilter`] passes. Rejected records get the slot index `SIZE`,
+/// which [`StationTable::update_slot`] ignores.
+pub(crate) struct FilteredTable<'a, const SIZE: usize> {
+    filter: &'a StationFilter,
+    table: FingerprintTable<SIZE>,
+    /// Filter decision of every occupied slot, made on its first record.
+    accepted: Vec<Option<bool>>,
+}
+
+impl<'a, const SIZE: usize> FilteredTable<'a, SIZE> {
+    pub(crate) fn new(filter: &'a StationFilter, normalization: Normalization) -> Self {
+        Self {
+            filter,
+            table: FingerprintTable::with_normalization(normalization),
+            accepted: vec![None; SIZE],
+        }
+    }
+
+    pub(crate) fn into_result(self) -> Vec<(String, StateI)> {
+        let mut all = self.table.into_result();
+        // Rejected stations keep a slot, but never get a measurement
+        all.retain(|(_, state)| state.count != 0);
+        all
+    }
+}
+
+impl<const SIZE: usize> StationTable for FilteredTable<'_, SIZE> {
+    #[inline(always)]
+    fn find_or_insert(&mut self, name: &[u8], padded_name: &[u8]) -> usize {
+        let hash = slot_hash(name, padded_name);
+        // Another spelling of an allowed name is only known after normalizing
+        if let Some((_, fingerprints)) = &self.filter.allow
+            && self.table.normalization() == Normalization::None
+            && !fingerprints.contains(hash)
+        {
+            return SIZE;
+        }
+        let idx = self.table.find_or_insert_hash(name, hash);
+        let accepted =
+            *self.accepted[idx].get_or_insert_with(|| self.filter.accepts(self.table.name(idx)));
+        if accepted { idx } else { SIZE }
+    }
+
+    #[inline(always)]
+    fn update_slot(&mut self, slot_idx: usize, value: i16) {
+        if slot_idx != SIZE {
+            self.table.update_slot(slot_idx, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_conditions() {
+        assert!(StationFilter::new().accepts("Oslo"));
+        assert!(StationFilter::new().is_empty());
+
+        let filter = StationFilter::new()
+            .allow(["Oslo", "Osaka", "Ottawa", "Paris"])
+            .deny(["Ottawa"])
+            .prefix("O");
+        assert!(filter.accepts("Oslo"));
+        assert!(filter.accepts("Osaka"));
+        assert!(!filter.accepts("Ottawa"));
+        assert!(!filter.accepts("Paris"));
+        assert!(!filter.accepts("Odesa"));
+
+        let filter = filter.allow(["Oslo", "Paris"]);
+        assert!(filter.accepts("Oslo"));
+        assert!(!filter.accepts("Osaka"));
+
+        let filter = StationFilter::new().regex("^San|ville$").unwrap();
+        assert!(filter.accepts("San Juan"));
+        assert!(filter.accepts("Nashville"));
+        assert!(filter.accepts("Santiago"));
+        assert!(!filter.accepts("Oslo"));
+        assert!(StationFilter::new().regex("(").is_err());
+    }
+
+    #[test]
+    fn test_glob() {
+        let glob = |pattern: &str| StationFilter::new().glob(pattern).unwrap();
+        assert!(glob("San *").accepts("San Juan"));
+        assert!(!glob("San *").accepts("Santiago"));
+        assert!(glob("?slo").accepts("Oslo"));
+        assert!(!glob("?slo").accepts("Oslo 2"));
+        assert!(glob("[A-C]*").accepts("Cairo"));
+        assert!(!glob("[!A-C]*").accepts("Cairo"));
+        assert!(glob("St. [[]1]").accepts("St. [1]"));
+        assert!(glob("Zürich (*)").accepts("Zürich (ZRH)"));
+        assert!(StationFilter::new().glob("[A-").is_err());
+    }
+
+    #[test]
+    fn test_filtered_table() {
+        let records: [(&str, i16); 6] = [
+            ("Oslo", 10),
+            ("Paris", 20),
+            ("Osaka", 30),
+            ("Oslo", -10),
+            ("Ottawa", 5),
+            ("Bergen", 1),
+        ];
+        let filter = StationFilter::new()
+            .allow(["Oslo", "Osaka", "Ottawa"])
+            .glob("Os*")
+            .unwrap();
+        let fill = || {
+            let mut table = FilteredTable::<64>::new(&filter, Normalization::None);
+            for (name, value) in records {
+                let mut padded_name = name.as_bytes().to_vec();
+                padded_name.resize(name.len() + 8, 0);
+                table.insert_or_update(name.as_bytes(), &padded_name, value);
+            }
+            table
+        };
+        // Unlisted stations never reach the table, listed ones failing the
+        // glob keep an empty slot.
+        assert_eq!(3, fill().table.into_result().len());
+
+        let table = fill();
+        let mut all = table.into_result();
+        crate::sort_result(&mut all);
+        let names: Vec<&str> = all.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(["Osaka", "Oslo"], names.as_slice());
+        assert_eq!("-1.0/0.0/1.0", all[1].1.to_string());
+    }
+}
@@ -16,7 +16,7 @@ struct StationName {
     n13: u64,
 }
 
-#[allow(unused)]
+#[allow(unused, clippy::too_many_arguments, clippy::needless_range_loop)]
 impl StationName {
     #[inline]
     fn new(
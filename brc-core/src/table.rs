@@ -1,13 +1,13 @@
-use crate::{StateF, StateI, byte_to_string_unsafe};
+use crate::{Accumulator, StateI, byte_to_string_unsafe};
 
 /// Open addressing table
-pub struct Table<const MAX_SIZE: usize> {
+pub struct Table<const MAX_SIZE: usize, A = StateI> {
     // inner: [Option<(String, StateI64)>; MAX_SIZE], // stack allocation
-    inner: Vec<Option<(String, StateI)>>,
+    inner: Vec<Option<(String, A)>>,
 }
 
-impl<const MAX_SIZE: usize> Table<MAX_SIZE> {
-    pub fn new() -> Table<MAX_SIZE> {
+impl<const MAX_SIZE: usize, A: Accumulator> Table<MAX_SIZE, A> {
+    pub fn new() -> Table<MAX_SIZE, A> {
         Table {
             // inner: [(); MAX_SIZE].map(|()| None), // stack allocation
             inner: (0..MAX_SIZE).map(|_| None).collect(),
@@ -15,7 +15,7 @@ impl<const MAX_SIZE: usize> Table<MAX_SIZE> {
     }
 
     #[inline]
-    fn find_slot(&mut self, key: &[u8], hash: u64) -> &mut Option<(String, StateI)> {
+    fn find_slot(&mut self, key: &[u8], hash: u64) -> &mut Option<(String, A)> {
         let mut iter_idx: usize = 0;
         let len = self.inner.len();
         let slot_idx = loop {
@@ -41,23 +41,20 @@ impl<const MAX_SIZE: usize> Table<MAX_SIZE> {
     pub fn insert_or_update(&mut self, key: &[u8], hash: u64, value: i16) {
         let slot = self.find_slot(key, hash);
         if slot.is_none() {
-            let _ = std::mem::replace(
-                slot,
-                Some((byte_to_string_unsafe(key).to_string(), StateI::new(value))),
-            );
+            *slot = Some((byte_to_string_unsafe(key).to_string(), A::new(value)));
         } else {
             let (_, state) = slot.as_mut().unwrap();
             state.update(value)
         }
     }
 
-    pub fn to_result(&self) -> Vec<(String, StateF)> {
-        let mut result: Vec<(String, StateF)> = Vec::with_capacity(MAX_SIZE);
+    pub fn to_result(&self) -> Vec<(String, A)> {
+        let mut result: Vec<(String, A)> = Vec::with_capacity(MAX_SIZE);
         for item in &self.inner {
             match item {
                 None => {},
                 Some((k, v)) => {
-                    result.push((k.clone(), v.to_f64()));
+                    result.push((k.clone(), v.clone()));
                 },
             }
         }
@@ -66,17 +63,17 @@ impl<const MAX_SIZE: usize> Table<MAX_SIZE> {
 }
 #[allow(unused)]
 /// Slower than Table because Table has data locality
-pub struct KeyValueTable<const MAX_SIZE: usize> {
+pub struct KeyValueTable<const MAX_SIZE: usize, A = StateI> {
     keys: Vec<Option<String>>,
-    values: Vec<StateI>,
+    values: Vec<A>,
 }
 
 #[allow(unused)]
-impl<const MAX_SIZE: usize> KeyValueTable<MAX_SIZE> {
-    pub fn new() -> KeyValueTable<MAX_SIZE> {
+impl<const MAX_SIZE: usize, A: Accumulator> KeyValueTable<MAX_SIZE, A> {
+    pub fn new() -> KeyValueTable<MAX_SIZE, A> {
         KeyValueTable {
             keys: (0..MAX_SIZE).map(|_| None).collect(),
-            values: (0..MAX_SIZE).map(|_| StateI::default()).collect(),
+            values: (0..MAX_SIZE).map(|_| A::default()).collect(),
         }
     }
 
@@ -84,7 +81,8 @@ impl<const MAX_SIZE: usize> KeyValueTable<MAX_SIZE> {
     fn find_slot(&mut self, hash: u64, key: &[u8]) -> usize {
         let mut iter_idx: usize = 0;
         let len: usize = self.keys.len();
-        let slot_idx = loop {
+
+        loop {
             // Linear probing
             let idx_mod: usize = (hash as usize + iter_idx) % len;
             match &self.keys[idx_mod] {
@@ -98,8 +96,7 @@ impl<const MAX_SIZE: usize> KeyValueTable<MAX_SIZE> {
                     );
                 },
             }
-        };
-        slot_idx
+        }
     }
 
     #[inline]
@@ -109,19 +106,20 @@ impl<const MAX_SIZE: usize> KeyValueTable<MAX_SIZE> {
         if slot.is_none() {
             let new = Some(byte_to_string_unsafe(key).to_string());
             let _ = std::mem::replace(slot, new);
+            self.values[slot_idx] = A::new(value);
         } else {
-            let state: &mut StateI = &mut self.values[slot_idx];
+            let state: &mut A = &mut self.values[slot_idx];
             state.update(value)
         }
     }
 
-    pub fn to_result(&self) -> Vec<(String, StateF)> {
-        let mut result: Vec<(String, StateF)> = Vec::with_capacity(MAX_SIZE);
+    pub fn to_result(&self) -> Vec<(String, A)> {
+        let mut result: Vec<(String, A)> = Vec::with_capacity(MAX_SIZE);
         for i in 0..self.keys.len() {
             match &self.keys[i] {
                 None => {},
                 Some(k) => {
-                    result.push((k.clone(), self.values[i].to_f64()));
+                    result.push((k.clone(), self.values[i].clone()));
                 },
             }
         }
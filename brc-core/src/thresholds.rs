@@ -0,0 +1,261 @@
+use crate::fingerprint_table::FingerprintTable;
+use crate::{Normalization, StateI, round_tenths};
+
+/// Temperatures, in tenths, that readings are compared against: a reading
+/// exceeds `above` when it is strictly greater and `below` when it is strictly
+/// less. Unset thresholds are not tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Thresholds {
+    pub above: Option<i16>,
+    pub below: Option<i16>,
+}
+
+impl Thresholds {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn above(mut self, tenths: i16) -> Self {
+        self.above = Some(tenths);
+        self
+    }
+
+    pub fn below(mut self, tenths: i16) -> Self {
+        self.below = Some(tenths);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool { self.above.is_none() && self.below.is_none() }
+}
+
+/// Parses a threshold in degrees, e.g. `30.0` or `-5`, to tenths.
+pub fn parse_threshold(s: &str) -> Result<i16, String> {
+    let degrees = s
+        .parse::<f64>()
+        .map_err(|e| format!("Invalid threshold `{s}`: {e}"))?;
+    if !(-99.9..=99.9).contains(&degrees) {
+        return Err(format!("Threshold `{s}` is outside [-99.9, 99.9]"));
+    }
+    Ok(round_tenths(degrees) as i16)
+}
+
+/// Readings of one station beyond one threshold, in file order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Exceedances {
+    /// Readings beyond the threshold.
+    pub count: u64,
+    /// Longest run of consecutive readings of the station beyond the threshold.
+    pub longest_run: u64,
+    /// All readings, including those within the threshold.
+    readings: u64,
+    /// Run the readings start with, extended by the trailing run of the
+    /// readings before them on merge.
+    leading_run: u64,
+    /// Run the readings end with, extended by the leading run of the readings
+    /// after them on merge.
+    trailing_run: u64,
+}
+
+impl Exceedances {
+    /// Adds the next reading in file order, without branches.
+    #[inline(always)]
+    pub fn update(&mut self, exceeds: bool) {
+        let exceeds = exceeds as u64;
+        let unbroken = (self.leading_run == self.readings) as u64;
+        self.readings += 1;
+        self.count += exceeds;
+        self.leading_run += exceeds & unbroken;
+        self.trailing_run = (self.trailing_run + 1) * exceeds;
+        self.longest_run = self.longest_run.max(self.trailing_run);
+    }
+
+    /// Appends `later`, the readings that follow these in the file.
+    pub fn merge(&mut self, later: &Self) {
+        self.longest_run = self
+            .longest_run
+            .max(later.longest_run)
+            .max(self.trailing_run + later.leading_run);
+        if self.leading_run == self.readings {
+            self.leading_run += later.leading_run;
+        }
+        self.trailing_run = if later.trailing_run == later.readings {
+            self.trailing_run + later.trailing_run
+        } else {
+            later.trailing_run
+        };
+        self.readings = crate::checked_count(self.readings, later.readings);
+        self.count += later.count;
+    }
+}
+
+/// [`StateI`] extended with the readings beyond [`Thresholds`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThresholdState {
+    pub state: StateI,
+    pub thresholds: Thresholds,
+    pub above: Exceedances,
+    pub below: Exceedances,
+}
+
+impl ThresholdState {
+    /// Appends `later`, the readings that follow these in the file. Runs span
+    /// chunks only when chunks are merged in file order.
+    pub fn merge(&mut self, later: &Self) {
+        assert_eq!(
+            self.thresholds, later.thresholds,
+            "Merging states of different thresholds"
+        );
+        self.state.merge(&later.state);
+        self.above.merge(&later.above);
+        self.below.merge(&later.below);
+    }
+}
+
+/// A [`FingerprintTable`] with the [`Exceedances`] of every slot kept next to
+/// it, updated from the same parsed value.
+pub(crate) struct ThresholdTable<const SIZE: usize> {
+    thresholds: Thresholds,
+    table: FingerprintTable<SIZE>,
+    exceedances: Vec<[Exceedances; 2]>,
+    /// Thresholds compared against; unset ones can never be exceeded.
+    above: i16,
+    below: i16,
+}
+
+impl<const SIZE: usize> ThresholdTable<SIZE> {
+    pub(crate) fn new(thresholds: Thresholds, normalization: Normalization) -> Self {
+        Self {
+            thresholds,
+            table: FingerprintTable::with_normalization(normalization),
+            exceedances: vec![Default::default(); SIZE],
+            above: thresholds.above.unwrap_or(i16::MAX),
+            below: thresholds.below.unwrap_or(i16::MIN),
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn update(&mut self, name: &[u8], padded_name: &[u8], value: i16) {
+        let idx = self.table.find_or_insert(name, padded_name);
+        self.table.update_slot(idx, value);
+        let [above, below] = &mut self.exceedances[idx];
+        above.update(value > self.above);
+        below.update(value < self.below);
+    }
+
+    pub(crate) fn into_result(self) -> Vec<(String, ThresholdState)> {
+        self.table
+            .into_result_with_slots()
+            .into_iter()
+            .map(|(idx, name, state)| {
+                let [above, below] = self.exceedances[idx];
+                let state = ThresholdState {
+                    state,
+                    thresholds: self.thresholds,
+                    above,
+                    below,
+                };
+                (name, state)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts and longest run computed directly over all readings.
+    fn expected(readings: &[bool]) -> (u64, u64) {
+        let count = readings.iter().filter(|&&r| r).count() as u64;
+        let longest = readings
+            .split(|&r| !r)
+            .map(|run| run.len() as u64)
+            .max()
+            .unwrap_or(0);
+        (count, longest)
+    }
+
+    #[test]
+    fn test_exceedances_merge_in_file_order() {
+        let mut seed = 7_u64;
+        let readings: Vec<bool> = (0..200)
+            .map(|i| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                // Long runs in the middle, short ones elsewhere
+                (80..120).contains(&i) || seed >> 62 != 0
+            })
+            .collect();
+        let fill = |readings: &[bool]| {
+            let mut e = Exceedances::default();
+            readings.iter().for_each(|&r| e.update(r));
+            e
+        };
+        let whole = fill(&readings);
+        assert_eq!(expected(&readings), (whole.count, whole.longest_run));
+
+        for parts in [2, 3, 7, 50, 200] {
+            let mut merged = Exceedances::default();
+            for chunk in readings.chunks(readings.len().div_ceil(parts)) {
+                merged.merge(&fill(chunk));
+            }
+            assert_eq!(whole, merged, "{parts} parts");
+        }
+
+        let all = fill(&[true; 5]);
+        let mut merged = all;
+        merged.merge(&Exceedances::default());
+        merged.merge(&all);
+        assert_eq!(fill(&[true; 10]), merged);
+    }
+
+    #[test]
+    fn test_threshold_table() {
+        let records: [(&str, i16); 8] = [
+            ("Oslo", 310),
+            ("Oslo", 305),
+            ("Lima", -20),
+            ("Oslo", 300),
+            ("Oslo", 301),
+            ("Lima", -5),
+            ("Lima", 0),
+            ("Lima", -1),
+        ];
+        let mut table =
+            ThresholdTable::<64>::new(Thresholds::new().above(300).below(0), Normalization::None);
+        for (name, value) in records {
+            let mut padded_name = name.as_bytes().to_vec();
+            padded_name.resize(name.len() + 8, 0);
+            table.update(name.as_bytes(), &padded_name, value);
+        }
+        let mut all = table.into_result();
+        crate::sort_result(&mut all);
+        let (lima, oslo) = (&all[0].1, &all[1].1);
+        assert_eq!((3, 2), (lima.below.count, lima.below.longest_run));
+        assert_eq!((0, 0), (lima.above.count, lima.above.longest_run));
+        assert_eq!((3, 2), (oslo.above.count, oslo.above.longest_run));
+        assert_eq!("-2.0/-0.6/0.0 >30.0:0/0 <0.0:3/2", lima.to_string());
+    }
+
+    #[test]
+    fn test_threshold_table_normalizes_on_insert() {
+        // Alternating spellings of one name form a single run of four
+        let (decomposed, precomposed) = ("Chis\u{326}ina\u{306}u", "Chi\u{219}in\u{103}u");
+        let mut table = ThresholdTable::<64>::new(Thresholds::new().above(300), Normalization::Nfc);
+        for name in [decomposed, precomposed, decomposed, precomposed] {
+            let mut padded_name = name.as_bytes().to_vec();
+            padded_name.resize(name.len() + 8, 0);
+            table.update(name.as_bytes(), &padded_name, 310);
+        }
+        let all = table.into_result();
+        assert_eq!(1, all.len());
+        assert_eq!(precomposed, all[0].0);
+        assert_eq!((4, 4), (all[0].1.above.count, all[0].1.above.longest_run));
+    }
+
+    #[test]
+    fn test_parse_threshold() {
+        assert_eq!(Ok(300), parse_threshold("30.0"));
+        assert_eq!(Ok(-50), parse_threshold("-5"));
+        assert_eq!(Ok(3), parse_threshold("0.25"));
+        assert!(parse_threshold("hot").is_err());
+        assert!(parse_threshold("100").is_err());
+    }
+}
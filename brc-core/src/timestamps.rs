@@ -0,0 +1,338 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use rustc_hash::FxHashMap;
+
+use crate::fingerprint_table::FingerprintTable;
+use crate::{Normalization, StateI};
+
+/// Length of a timestamp in the only supported format, `YYYY-MM-DDTHH:MM:SSZ`.
+pub const TIMESTAMP_LEN: usize = "2026-10-17T12:00:00Z".len();
+
+/// A UTC timestamp with second precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+const fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        },
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// `0x30` in the digit bytes of a word, separators left out.
+const fn digit_mask(digits: u64) -> u64 { digits & 0x3030303030303030 }
+
+/// The digits of `word` at the bytes of `digits`, or `None` if one of them is
+/// not an ASCII digit.
+#[inline(always)]
+const fn swar_digits(word: u64, digits: u64) -> Option<u64> {
+    let high_nibbles = word & 0xF0F0F0F0F0F0F0F0 & digits;
+    let values = (word & 0x0F0F0F0F0F0F0F0F) & digits;
+    // A low nibble above 9 carries into bit 4 of its byte when adding 6
+    let too_large = (values + (0x0606060606060606 & digits)) & 0x1010101010101010;
+    if high_nibbles == digit_mask(digits) && too_large == 0 {
+        Some(values)
+    } else {
+        None
+    }
+}
+
+/// Combines every digit with the next one: byte `i` of the result holds the
+/// two-digit number starting at byte `i`.
+#[inline(always)]
+const fn swar_pairs(values: u64) -> u64 { values.wrapping_mul(10).wrapping_add(values >> 8) }
+
+#[inline(always)]
+const fn byte(word: u64, i: u32) -> u8 { (word >> (8 * i)) as u8 }
+
+/// Parses `YYYY-MM-DDTHH:MM:SSZ` from the first [`TIMESTAMP_LEN`] bytes of
+/// `bytes`. The three words of the timestamp are checked for separators and
+/// digits and converted with a few multiplications each, without a loop or
+/// a branch per character; `None` if the format or a field is invalid.
+#[inline(always)]
+pub fn parse_timestamp(bytes: &[u8]) -> Option<Timestamp> {
+    // YYYY-MM-, DDTHH:MM, :SSZ
+    let w0 = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+    let w1 = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+    let w2 = u32::from_le_bytes(bytes[16..20].try_into().unwrap()) as u64;
+    let separators = (w0 & 0xFF0000FF00000000) == 0x2D00002D00000000
+        && (w1 & 0x0000FF0000FF0000) == 0x00003A0000540000
+        && (w2 & 0xFF0000FF) == 0x5A00003A;
+    if !separators {
+        return None;
+    }
+    let d0 = swar_digits(w0, 0x00FFFF00FFFFFFFF)?;
+    let d1 = swar_digits(w1, 0xFFFF00FFFF00FFFF)?;
+    let d2 = swar_digits(w2, 0x0000000000FFFF00)?;
+    let (p0, p1, p2) = (swar_pairs(d0), swar_pairs(d1), swar_pairs(d2));
+
+    let timestamp = Timestamp {
+        year: byte(p0, 0) as u16 * 100 + byte(p0, 2) as u16,
+        month: byte(p0, 5),
+        day: byte(p1, 0),
+        hour: byte(p1, 3),
+        minute: byte(p1, 6),
+        second: byte(p2, 1),
+    };
+    let valid = (1..=12).contains(&timestamp.month)
+        && timestamp.day >= 1
+        && timestamp.day <= days_in_month(timestamp.year, timestamp.month)
+        && timestamp.hour < 24
+        && timestamp.minute < 60
+        && timestamp.second < 60;
+    valid.then_some(timestamp)
+}
+
+/// Width of the time buckets of
+/// [`parse_large_chunks_time_series`](crate::parse_large_chunks_time_series).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Hour,
+    Day,
+    Month,
+}
+
+impl Granularity {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Granularity::Hour => "hour",
+            Granularity::Day => "day",
+            Granularity::Month => "month",
+        }
+    }
+
+    /// The bucket of `timestamp` as the decimal number `YYYYMMDDHH`, with the
+    /// fields finer than the granularity set to zero, so buckets sort
+    /// chronologically.
+    #[inline(always)]
+    pub const fn bucket(self, timestamp: &Timestamp) -> u32 {
+        let (day, hour) = match self {
+            Granularity::Hour => (timestamp.day, timestamp.hour),
+            Granularity::Day => (timestamp.day, 0),
+            Granularity::Month => (0, 0),
+        };
+        ((timestamp.year as u32 * 100 + timestamp.month as u32) * 100 + day as u32) * 100
+            + hour as u32
+    }
+
+    /// Writes `bucket` as `2026-10-17T12`, `2026-10-17` or `2026-10`.
+    pub fn write_bucket<W: std::fmt::Write>(self, out: &mut W, bucket: u32) -> std::fmt::Result {
+        let (year, month, day, hour) = (
+            bucket / 1_000_000,
+            bucket / 10_000 % 100,
+            bucket / 100 % 100,
+            bucket % 100,
+        );
+        match self {
+            Granularity::Hour => write!(out, "{year:04}-{month:02}-{day:02}T{hour:02}"),
+            Granularity::Day => write!(out, "{year:04}-{month:02}-{day:02}"),
+            Granularity::Month => write!(out, "{year:04}-{month:02}"),
+        }
+    }
+}
+
+impl Display for Granularity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str(self.name()) }
+}
+
+impl FromStr for Granularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Granularity::Hour, Granularity::Day, Granularity::Month]
+            .into_iter()
+            .find(|g| g.name() == s)
+            .ok_or_else(|| {
+                format!("Unknown granularity `{s}`, expected one of [\"hour\", \"day\", \"month\"]")
+            })
+    }
+}
+
+/// [`StateI`] of a station over the whole input and per time bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeSeriesState {
+    pub state: StateI,
+    pub granularity: Granularity,
+    /// Per bucket, see [`Granularity::bucket`], in chronological order.
+    pub buckets: BTreeMap<u32, StateI>,
+}
+
+impl TimeSeriesState {
+    pub fn merge(&mut self, other: &Self) {
+        assert_eq!(
+            self.granularity, other.granularity,
+            "Merging time series of different granularities"
+        );
+        self.state.merge(&other.state);
+        for (bucket, state) in &other.buckets {
+            self.buckets
+                .entry(*bucket)
+                .and_modify(|prev| prev.merge(state))
+                .or_insert_with(|| state.clone());
+        }
+    }
+}
+
+/// Buckets of one station. Exports are usually sorted by time, so the bucket
+/// of the previous record is aggregated separately and only stored in the map
+/// when a record of another bucket arrives.
+#[derive(Default, Clone)]
+struct Series {
+    current_bucket: u32,
+    current: StateI,
+    done: FxHashMap<u32, StateI>,
+}
+
+impl Series {
+    #[inline(always)]
+    fn update(&mut self, bucket: u32, value: i16) {
+        if bucket != self.current_bucket {
+            self.flush();
+            self.current_bucket = bucket;
+        }
+        self.current.update(value);
+    }
+
+    fn flush(&mut self) {
+        if self.current.count != 0 {
+            let state = std::mem::take(&mut self.current);
+            self.done
+                .entry(self.current_bucket)
+                .and_modify(|prev| prev.merge(&state))
+                .or_insert(state);
+        }
+    }
+}
+
+/// A [`FingerprintTable`] with the [`Series`] of every slot kept next to it.
+pub(crate) struct TimeSeriesTable<const SIZE: usize> {
+    granularity: Granularity,
+    table: FingerprintTable<SIZE>,
+    series: Vec<Series>,
+}
+
+impl<const SIZE: usize> TimeSeriesTable<SIZE> {
+    pub(crate) fn new(granularity: Granularity, normalization: Normalization) -> Self {
+        Self {
+            granularity,
+            table: FingerprintTable::with_normalization(normalization),
+            series: vec![Series::default(); SIZE],
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn update(
+        &mut self,
+        name: &[u8],
+        padded_name: &[u8],
+        timestamp: &Timestamp,
+        value: i16,
+    ) {
+        let idx = self.table.find_or_insert(name, padded_name);
+        self.table.update_slot(idx, value);
+        self.series[idx].update(self.granularity.bucket(timestamp), value);
+    }
+
+    pub(crate) fn into_result(mut self) -> Vec<(String, TimeSeriesState)> {
+        self.table
+            .into_result_with_slots()
+            .into_iter()
+            .map(|(idx, name, state)| {
+                let series = &mut self.series[idx];
+                series.flush();
+                let state = TimeSeriesState {
+                    state,
+                    granularity: self.granularity,
+                    buckets: std::mem::take(&mut series.done).into_iter().collect(),
+                };
+                (name, state)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let parse = |s: &str| parse_timestamp(s.as_bytes());
+        assert_eq!(
+            Some(Timestamp {
+                year: 2026,
+                month: 10,
+                day: 17,
+                hour: 12,
+                minute: 34,
+                second: 56,
+            }),
+            parse("2026-10-17T12:34:56Z")
+        );
+        assert_eq!(29, parse("2024-02-29T00:00:00Z").unwrap().day);
+        assert!(parse("2000-02-29T23:59:59Z").is_some());
+        assert!(parse("2100-02-29T00:00:00Z").is_none());
+        assert!(parse("2026-04-31T00:00:00Z").is_none());
+        assert!(parse("2026-13-01T00:00:00Z").is_none());
+        assert!(parse("2026-00-01T00:00:00Z").is_none());
+        assert!(parse("2026-10-17T24:00:00Z").is_none());
+        assert!(parse("2026-10-17T12:60:00Z").is_none());
+        assert!(parse("2026-10-17 12:00:00Z").is_none());
+        assert!(parse("2026-10-17T12:00:00+").is_none());
+        assert!(parse("2026-1O-17T12:00:00Z").is_none());
+        assert!(parse("2026-10-1:T12:00:00Z").is_none());
+        assert!(parse("20/6-10-17T12:00:00Z").is_none());
+    }
+
+    #[test]
+    fn test_buckets() {
+        let timestamp = parse_timestamp(b"2026-10-07T09:15:00Z").unwrap();
+        let bucket = |granularity: Granularity| {
+            let mut out = String::new();
+            granularity
+                .write_bucket(&mut out, granularity.bucket(&timestamp))
+                .unwrap();
+            out
+        };
+        assert_eq!("2026-10-07T09", bucket(Granularity::Hour));
+        assert_eq!("2026-10-07", bucket(Granularity::Day));
+        assert_eq!("2026-10", bucket(Granularity::Month));
+        assert!(Granularity::from_str("week").is_err());
+    }
+
+    #[test]
+    fn test_series_out_of_order() {
+        let mut series = Series::default();
+        for (bucket, value) in [(1, 10), (1, 20), (2, 5), (1, -10), (3, 0), (2, 15)] {
+            series.update(bucket, value);
+        }
+        series.flush();
+        let mut buckets: Vec<(u32, String)> = series
+            .done
+            .iter()
+            .map(|(bucket, state)| (*bucket, state.to_string()))
+            .collect();
+        buckets.sort();
+        assert_eq!(
+            [
+                (1, "-1.0/0.7/2.0".to_owned()),
+                (2, "0.5/1.0/1.5".to_owned()),
+                (3, "0.0/0.0/0.0".to_owned())
+            ],
+            buckets.as_slice()
+        );
+    }
+}
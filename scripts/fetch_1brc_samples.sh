@@ -0,0 +1,39 @@
+#!/usr/bin/env bash
+
+set -euo pipefail
+
+usage() {
+    echo "Usage: $0 [destination-directory]" >&2
+    echo "" >&2
+    echo "Copies the test samples of the 1BRC repository, every .txt with its" >&2
+    echo "expected .out, into the destination directory (default:" >&2
+    echo "brc-core/test_resources/official). Does nothing if it already holds" >&2
+    echo "samples." >&2
+    echo "" >&2
+    echo "Environment variables:" >&2
+    echo "  BRC_1BRC_REPO  Repository to clone" >&2
+    echo "                 (default: https://github.com/gunnarmorling/1brc.git)" >&2
+    echo "  BRC_1BRC_REF   Branch or tag to copy the samples from (default: main)" >&2
+}
+
+if [[ $# -gt 1 ]]; then
+    usage
+    exit 2
+fi
+
+repo=${BRC_1BRC_REPO:-https://github.com/gunnarmorling/1brc.git}
+ref=${BRC_1BRC_REF:-main}
+dest=${1:-$(dirname "$0")/../brc-core/test_resources/official}
+
+if compgen -G "$dest/*.out" >/dev/null; then
+    exit 0
+fi
+
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+git clone --quiet --depth 1 --branch "$ref" --filter=blob:none --sparse "$repo" "$tmp/1brc"
+git -C "$tmp/1brc" sparse-checkout set src/test/resources/samples
+
+mkdir -p "$dest"
+cp "$tmp"/1brc/src/test/resources/samples/*.txt "$tmp"/1brc/src/test/resources/samples/*.out "$dest"/
+echo "Copied $(compgen -G "$dest/*.out" | wc -l) samples of $repo@$ref to $dest" >&2
@@ -10,6 +10,10 @@ usage() {
     echo "  HYPERFINE_RUNS    Measured runs per command (default: 4)" >&2
     echo "  THREAD_COUNTS     Space-separated scaling thread counts" >&2
     echo "                    (default: 1 2 4 8 16 24 32)" >&2
+    echo "  BRC_TARGET        Target triple to cross-build for, e.g." >&2
+    echo "                    aarch64-unknown-linux-gnu (default: the host)" >&2
+    echo "  BRC_RUNNER        Command that runs the binary, e.g." >&2
+    echo "                    'qemu-aarch64 -L /usr/aarch64-linux-gnu'" >&2
 }
 
 if [[ $# -lt 1 || $# -gt 2 ]]; then
@@ -34,10 +38,22 @@ if ! command -v hyperfine >/dev/null 2>&1; then
 fi
 
 mkdir -p "$results_dir"
-cargo build --release
+if [[ -n "${BRC_TARGET:-}" ]]; then
+    cargo build --release --target "$BRC_TARGET"
+    app="./target/${BRC_TARGET}/release/brc-app"
+else
+    cargo build --release
+    app="./target/release/brc-app"
+fi
+
+# Emulated timings are not comparable with native ones, so say so next to them
+if [[ -n "${BRC_RUNNER:-}" ]]; then
+    echo "Emulated with \`${BRC_RUNNER}\`, timings are not native" > "$results_dir/EMULATED"
+    app="${BRC_RUNNER} ${app}"
+fi
 
 printf -v quoted_measurements_file '%q' "$measurements_file"
-run_app="./target/release/brc-app ${quoted_measurements_file}"
+run_app="${app} ${quoted_measurements_file}"
 
 dummy_implementations=(
     naive_line_by_line_dummy
@@ -58,9 +74,14 @@ implementations=(
     parse_large_chunks_simd_v1
     parse_large_chunks_simd_v2
     parse_large_chunks_memchr_table
+    parse_large_chunks_interleaved2_table
+    parse_large_chunks_interleaved3_table
+    parse_large_chunks_interleaved4_table
     parse_large_chunks_std_simd_table
     parse_large_chunks_simd_temperature_table
     parse_large_chunks_full_simd_table
+    parse_large_chunks_avx512_table
+    parse_large_chunks_structural_table
 )
 
 # Keep scaling runs focused on the previous scaling baselines and all new
@@ -69,9 +90,12 @@ scaling_implementations=(
     parse_large_chunks_as_i64_v2
     parse_large_chunks_as_i64_as_java
     parse_large_chunks_memchr_table
+    parse_large_chunks_interleaved3_table
     parse_large_chunks_std_simd_table
     parse_large_chunks_simd_temperature_table
     parse_large_chunks_full_simd_table
+    parse_large_chunks_avx512_table
+    parse_large_chunks_structural_table
 )
 
 run_hyperfine() {